@@ -1,3 +1,390 @@
-fn main() {
-    println!("Hello, world!");
+use clap::{Args, Parser, Subcommand};
+use installer::{ArchiveStatus, IpPreference, ModlistDownloader, ModlistOptions, NetworkPolicy, PlanTotals};
+use installer::parse_wabbajack::parser::WabbaModlist;
+use installer::parse_wabbajack::validate::Severity;
+use installer::parse_wabbajack::wabbajack_file::{is_wabbajack_archive, WabbajackFile};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Unifier command line tools
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show a summary of a modlist (.wabbajack archive or raw modlist JSON)
+    Info {
+        /// Path to the modlist
+        modlist: PathBuf,
+    },
+    /// Check a modlist for structural problems before downloading
+    Lint {
+        /// Path to the modlist
+        modlist: PathBuf,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show what changed between two versions of a modlist
+    Diff {
+        /// The currently installed version
+        old: PathBuf,
+        /// The version to upgrade to
+        new: PathBuf,
+        /// Print the diff as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Download every archive of a modlist; Ctrl-C cancels and keeps partial files for resuming
+    Download {
+        /// Path to the modlist
+        modlist: PathBuf,
+        /// Directory to download archives into
+        destination: PathBuf,
+        /// Maximum concurrent downloads (defaults to the number of CPUs)
+        #[arg(long)]
+        concurrency: Option<usize>,
+        /// Cap combined download speed, in KiB per second
+        #[arg(long, value_name = "KIB_PER_SEC")]
+        limit_rate: Option<u64>,
+        /// Shared archive store; archives already in it are linked instead of downloaded
+        #[arg(long, value_name = "DIR")]
+        store: Option<PathBuf>,
+        /// Disk space to keep free, in MiB; downloads pause when less is left
+        #[arg(long, value_name = "MIB")]
+        min_free_space: Option<u64>,
+        /// Mirror serving archives by hash, tried when an archive's source fails; may be repeated
+        #[arg(long = "mirror", value_name = "URL")]
+        mirrors: Vec<String>,
+        #[command(flatten)]
+        network: NetworkArgs,
+    },
+    /// Show what downloading a modlist would do, without downloading anything
+    Plan {
+        /// Path to the modlist
+        modlist: PathBuf,
+        /// Directory archives would be downloaded into
+        destination: PathBuf,
+        /// Shared archive store to check for archives
+        #[arg(long, value_name = "DIR")]
+        store: Option<PathBuf>,
+        /// Download speed to estimate the time with, in MiB per second
+        /// (default: 10)
+        #[arg(long, value_name = "MIB_PER_SEC")]
+        speed: Option<u64>,
+        /// Print the plan as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Connection settings shared by every request
+#[derive(Args)]
+struct NetworkArgs {
+    /// Proxy for every request (http://, https://, socks5:// or socks5h://)
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+    /// Comma separated hosts that bypass the proxy
+    #[arg(long, value_name = "HOSTS", requires = "proxy")]
+    no_proxy: Option<String>,
+    /// PEM file of extra root certificates to trust; may be repeated
+    #[arg(long = "ca-cert", value_name = "FILE")]
+    ca_certs: Vec<PathBuf>,
+    /// Only connect over IPv4
+    #[arg(long, conflicts_with = "ipv6")]
+    ipv4: bool,
+    /// Only connect over IPv6
+    #[arg(long)]
+    ipv6: bool,
+}
+
+impl NetworkArgs {
+    fn policy(self) -> NetworkPolicy {
+        let ip_preference = match (self.ipv4, self.ipv6) {
+            (true, _) => IpPreference::Ipv4Only,
+            (_, true) => IpPreference::Ipv6Only,
+            _ => IpPreference::System,
+        };
+        NetworkPolicy {
+            proxy: self.proxy,
+            no_proxy: self.no_proxy,
+            root_certificates: self.ca_certs,
+            ip_preference,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Info { modlist } => info(&modlist).map(|_| ExitCode::SUCCESS),
+        Command::Lint { modlist, json } => lint(&modlist, json),
+        Command::Diff { old, new, json } => diff(&old, &new, json).map(|_| ExitCode::SUCCESS),
+        Command::Download { modlist, destination, concurrency, limit_rate, store, min_free_space, mirrors, network } => {
+            let options = modlist_options(concurrency, limit_rate, store, min_free_space, mirrors, network.policy());
+            download(&modlist, &destination, options)
+        }
+        Command::Plan { modlist, destination, store, speed, json } => {
+            plan(&modlist, &destination, store, speed, json).map(|_| ExitCode::SUCCESS)
+        }
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn info(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (modlist, inline_entries) = if is_wabbajack_archive(path)? {
+        let wabbajack = WabbajackFile::open(path)?;
+        let inline_entries = wabbajack.inline_data_ids().len();
+        (wabbajack.into_modlist(), Some(inline_entries))
+    } else {
+        (WabbaModlist::from_path(path)?, None)
+    };
+
+    println!("{} {} by {}", modlist.name, modlist.version, modlist.author);
+    println!("Game:        {}", if modlist.game_type.is_empty() { &modlist.game } else { &modlist.game_type });
+    if !modlist.wabbajack_version.is_empty() {
+        println!("Wabbajack:   {}", modlist.wabbajack_version);
+    }
+    if modlist.is_nsfw {
+        println!("NSFW:        yes");
+    }
+    println!("Archives:    {}", modlist.archives.len());
+    println!("Directives:  {}", modlist.directives.len());
+    if let Some(count) = inline_entries {
+        println!("Inline data: {} entries", count);
+    }
+
+    let total_size: u64 = modlist.archives.iter().map(|a| a.size).sum();
+    println!("Download:    {:.1} MB", total_size as f64 / 1_048_576.0);
+
+    if let Err(e) = modlist.check_compatibility() {
+        println!("Warning:     {}", e);
+    }
+    Ok(())
+}
+
+fn lint(path: &Path, json: bool) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let modlist = WabbaModlist::from_path(path)?;
+    let report = modlist.validate();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for diagnostic in &report.diagnostics {
+            println!("{}", diagnostic);
+        }
+        println!(
+            "{} errors, {} warnings",
+            report.count(Severity::Error),
+            report.count(Severity::Warning)
+        );
+    }
+
+    Ok(if report.has_errors() { ExitCode::FAILURE } else { ExitCode::SUCCESS })
+}
+
+fn diff(old: &Path, new: &Path, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let old = WabbaModlist::from_path(old)?;
+    let new = WabbaModlist::from_path(new)?;
+    let diff = old.diff(&new);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    println!("{} {} -> {}", new.name, old.version, new.version);
+    if diff.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+
+    for archive in &diff.added_archives {
+        println!("+ archive {} ({:.1} MB)", archive.name, archive.size as f64 / 1_048_576.0);
+    }
+    for archive in &diff.removed_archives {
+        println!("- archive {}", archive.name);
+    }
+    for change in &diff.changed_archives {
+        println!("~ archive {} ({})", change.new.name, change.changed_fields.join(", "));
+    }
+    for to in &diff.added_directives {
+        println!("+ {}", to);
+    }
+    for to in &diff.removed_directives {
+        println!("- {}", to);
+    }
+    for change in &diff.changed_directives {
+        println!("~ {}", change.to);
+    }
+
+    println!();
+    println!(
+        "Archives:    +{} -{} ~{}",
+        diff.added_archives.len(),
+        diff.removed_archives.len(),
+        diff.changed_archives.len()
+    );
+    println!(
+        "Files:       +{} -{} ~{}",
+        diff.added_directives.len(),
+        diff.removed_directives.len(),
+        diff.changed_directives.len()
+    );
+    println!("Download:    {:.1} MB", diff.new_download_bytes() as f64 / 1_048_576.0);
+    Ok(())
+}
+
+fn modlist_options(
+    concurrency: Option<usize>,
+    limit_rate: Option<u64>,
+    store: Option<PathBuf>,
+    min_free_space: Option<u64>,
+    mirrors: Vec<String>,
+    network: NetworkPolicy,
+) -> ModlistOptions {
+    let mut options = ModlistOptions::default();
+    if let Some(concurrency) = concurrency {
+        options.max_concurrent_downloads = concurrency.max(1);
+    }
+    options.bandwidth_limit = limit_rate.map(|kib| kib * 1024);
+    options.archive_store = store;
+    if let Some(mib) = min_free_space {
+        options.min_free_space = mib * 1024 * 1024;
+    }
+    options.mirrors = mirrors;
+    options.network = network;
+    options
+}
+
+fn download(
+    modlist: &Path,
+    destination: &Path,
+    options: ModlistOptions,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let downloader = ModlistDownloader::new(
+            &modlist.to_string_lossy(),
+            &destination.to_string_lossy(),
+            options,
+            None,
+        ).with_dashboard_progress();
+
+        // First Ctrl-C cancels gracefully, a second one exits immediately
+        let controller = downloader.controller();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eprintln!("\nCancelling, partial downloads are kept and will resume on the next run (Ctrl-C again to quit now)");
+                controller.cancel();
+            }
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        });
+
+        let result = downloader.download().await?;
+
+        println!();
+        println!("Downloaded:  {}", result.successful_downloads);
+        println!("Skipped:     {}", result.skipped_downloads);
+        println!("Failed:      {}", result.failed_downloads);
+        if result.cancelled_downloads > 0 {
+            println!("Cancelled:   {}", result.cancelled_downloads);
+        }
+        println!("Time:        {:.1}s", result.elapsed_time.as_secs_f64());
+        for error in &result.error_messages {
+            eprintln!("error: {}", error);
+        }
+
+        Ok(if result.failed_downloads > 0 || result.cancelled_downloads > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        })
+    })
+}
+
+fn plan(
+    modlist: &Path,
+    destination: &Path,
+    store: Option<PathBuf>,
+    speed: Option<u64>,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = ModlistOptions { archive_store: store, ..ModlistOptions::default() };
+    let downloader = ModlistDownloader::new(
+        &modlist.to_string_lossy(),
+        &destination.to_string_lossy(),
+        options,
+        None,
+    );
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut plan = runtime.block_on(downloader.plan())?;
+    if let Some(mib) = speed {
+        plan = plan.with_speed(mib * 1024 * 1024);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
+
+    for archive in &plan.archives {
+        match &archive.status {
+            ArchiveStatus::Resumable { downloaded } => println!(
+                "resume      {} ({:.1} of {:.1} MB)",
+                archive.filename,
+                *downloaded as f64 / 1_048_576.0,
+                archive.size as f64 / 1_048_576.0
+            ),
+            ArchiveStatus::Download => println!(
+                "download    {} ({:.1} MB, {:?})",
+                archive.filename,
+                archive.size as f64 / 1_048_576.0,
+                archive.source
+            ),
+            ArchiveStatus::Manual { instructions, url } => match url {
+                Some(url) => println!("manual      {}: {} ({})", archive.filename, instructions, url),
+                None => println!("manual      {}: {}", archive.filename, instructions),
+            },
+            ArchiveStatus::Unsupported { reason } => println!("unsupported {}: {}", archive.filename, reason),
+            _ => {}
+        }
+    }
+
+    let line = |label: &str, totals: PlanTotals| {
+        println!("{:<13}{} ({:.1} MB)", label, totals.count, totals.bytes as f64 / 1_048_576.0);
+    };
+    println!();
+    line("Valid:", plan.valid);
+    if plan.in_store.count > 0 {
+        line("In store:", plan.in_store);
+    }
+    line("Unverified:", plan.unverified);
+    line("Resume:", plan.resumable);
+    line("Download:", plan.download);
+    line("Manual:", plan.manual);
+    line("Unsupported:", plan.unsupported);
+    println!("Transfer:    {:.1} MB", plan.bytes_to_download as f64 / 1_048_576.0);
+    if plan.nexus_api_calls > 0 {
+        println!("Nexus API:   {} requests", plan.nexus_api_calls);
+    }
+    if let (Some(seconds), Some(speed)) = (plan.estimated_seconds, plan.assumed_speed) {
+        println!("Estimate:    {}m {}s at {:.1} MiB/s", seconds / 60, seconds % 60, speed as f64 / 1_048_576.0);
+    }
+    Ok(())
 }
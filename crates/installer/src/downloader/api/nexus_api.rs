@@ -14,7 +14,7 @@ use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::time::sleep;
 use tracing::debug;
 
-use crate::downloader::core::{DownloadError, Result};
+use crate::downloader::core::{DownloadConfig, DownloadError, Result};
 
 /// Nexus API endpoints
 const NEXUS_API_BASE: &str = "https://api.nexusmods.com";
@@ -217,6 +217,12 @@ pub struct NexusAPI {
 impl NexusAPI {
     /// Create new Nexus authentication client with API key from environment
     pub fn new() -> Result<Self> {
+        Self::from_config(&DownloadConfig::default())
+    }
+
+    /// Create a Nexus client with API key from environment, connecting with the
+    /// user agent and network policy of `config`
+    pub fn from_config(config: &DownloadConfig) -> Result<Self> {
         // Load API key from environment
         dotenv::dotenv().ok(); // Ignore error if .env not present
         let api_key = std::env::var("NEXUS_API_KEY")
@@ -226,8 +232,7 @@ impl NexusAPI {
                 suggestion: Some("Set NEXUS_API_KEY in your .env file with your personal API key from Nexus Mods".to_string()),
             })?;
 
-        let client = Client::builder()
-            .user_agent("Unifier/1.0")
+        let client = config.network.client_builder(&config.user_agent)?
             .timeout(Duration::from_secs(30))
             .build()
             .map_err(|e| DownloadError::Legacy(format!("Failed to create HTTP client: {}", e)))?;
@@ -412,7 +417,6 @@ impl NexusAPI {
         let request = self.client
             .get(url)
             .header("apikey", &self.api_key)
-            .header("User-Agent", "Unifier/1.0")
             .header("Application-Name", "Unifier")
             .header("Application-Version", "1.0");
 
@@ -0,0 +1,163 @@
+//! Batch control: cancellation, pause and resume
+//!
+//! [`DownloadPipeline::start_batch`](crate::downloader::DownloadPipeline::start_batch)
+//! returns a [`BatchHandle`] for a running batch. Its [`BatchController`] can be
+//! cloned and handed to other tasks, e.g. a Ctrl-C handler or a UI command.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::downloader::core::{DownloadError, Result, VerifiedDownloadResult};
+
+#[derive(Default)]
+struct ControlState {
+    /// Cancels the whole batch; every task token is a child of this one
+    cancel: CancellationToken,
+    paused: AtomicBool,
+    /// Per-task tokens, created on first use
+    tasks: Mutex<HashMap<usize, CancellationToken>>,
+    /// Tasks cancelled one by one that the batch has not taken out of its queue yet
+    cancelled_tasks: Mutex<Vec<usize>>,
+    /// Signalled on every control change and whenever the batch makes progress
+    changed: Notify,
+}
+
+/// Cloneable control surface for a download batch
+///
+/// Cancelling stops queued tasks from starting and aborts in-flight downloads.
+/// Partial `.part` files are left on disk, so running the same requests again
+/// resumes where the cancelled batch stopped. Pausing only stops new tasks from
+/// starting; downloads already in progress run to completion.
+///
+/// A controller is meant for a single batch: once cancelled it stays cancelled.
+#[derive(Clone, Default)]
+pub struct BatchController {
+    inner: Arc<ControlState>,
+}
+
+impl BatchController {
+    /// Create a controller to pass to
+    /// [`start_batch_with_controller`](crate::downloader::DownloadPipeline::start_batch_with_controller)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every task that has not finished yet
+    pub fn cancel(&self) {
+        self.inner.cancel.cancel();
+        self.inner.changed.notify_waiters();
+    }
+
+    /// Cancel a single task by its index in the batch
+    ///
+    /// Has no effect if the task already finished.
+    pub fn cancel_task(&self, index: usize) {
+        self.task_token(index).cancel();
+        self.inner.cancelled_tasks.lock().unwrap().push(index);
+        self.inner.changed.notify_waiters();
+    }
+
+    /// Stop starting new tasks until [`resume`](Self::resume) is called
+    pub fn pause(&self) {
+        self.inner.paused.store(true, Ordering::Release);
+        self.inner.changed.notify_waiters();
+    }
+
+    /// Start processing queued tasks again
+    pub fn resume(&self) {
+        self.inner.paused.store(false, Ordering::Release);
+        self.inner.changed.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::Acquire)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancel.is_cancelled()
+    }
+
+    /// True if the given task or the whole batch was cancelled
+    pub fn is_task_cancelled(&self, index: usize) -> bool {
+        self.is_cancelled()
+            || self.inner.tasks.lock().unwrap().get(&index).is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Token that fires when the given task or the whole batch is cancelled
+    pub(crate) fn task_token(&self, index: usize) -> CancellationToken {
+        self.inner.tasks.lock().unwrap()
+            .entry(index)
+            .or_insert_with(|| self.inner.cancel.child_token())
+            .clone()
+    }
+
+    /// Tasks cancelled by [`cancel_task`](Self::cancel_task) since the last call
+    pub(crate) fn take_cancelled_tasks(&self) -> Vec<usize> {
+        std::mem::take(&mut *self.inner.cancelled_tasks.lock().unwrap())
+    }
+
+    /// Notifier shared by the controller and the batch it drives
+    pub(crate) fn changed(&self) -> &Notify {
+        &self.inner.changed
+    }
+}
+
+/// Error recorded for a task that was cancelled before it finished
+pub(crate) fn cancelled_error(index: usize) -> DownloadError {
+    DownloadError::Cancelled {
+        reason: format!("task {} was cancelled", index),
+        url: None,
+    }
+}
+
+/// Handle to a running download batch
+pub struct BatchHandle {
+    controller: BatchController,
+    join: JoinHandle<Vec<Result<VerifiedDownloadResult>>>,
+}
+
+impl BatchHandle {
+    pub(crate) fn new(controller: BatchController, join: JoinHandle<Vec<Result<VerifiedDownloadResult>>>) -> Self {
+        Self { controller, join }
+    }
+
+    /// The batch's controller, cloneable for use from other tasks
+    pub fn controller(&self) -> &BatchController {
+        &self.controller
+    }
+
+    /// Cancel every task that has not finished yet
+    pub fn cancel(&self) {
+        self.controller.cancel();
+    }
+
+    /// Cancel a single task by its index in the batch
+    pub fn cancel_task(&self, index: usize) {
+        self.controller.cancel_task(index);
+    }
+
+    /// Stop starting new tasks
+    pub fn pause(&self) {
+        self.controller.pause();
+    }
+
+    /// Start processing queued tasks again
+    pub fn resume(&self) {
+        self.controller.resume();
+    }
+
+    /// Wait for the batch to finish and return results in request order
+    ///
+    /// Cancelled tasks come back as [`DownloadError::Cancelled`].
+    pub async fn wait(self) -> Vec<Result<VerifiedDownloadResult>> {
+        match self.join.await {
+            Ok(results) => results,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => panic!("Download batch was aborted: {}", e),
+        }
+    }
+}
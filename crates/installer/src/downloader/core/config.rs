@@ -1,7 +1,32 @@
 //! Configuration types for the downloader system
 
+use std::collections::HashMap;
 use std::time::Duration;
 
+use crate::downloader::core::network::NetworkPolicy;
+use crate::downloader::core::throttle::BandwidthLimiter;
+use crate::downloader::sources::SourceKind;
+use crate::downloader::mirror::MirrorResolver;
+use crate::downloader::store::ArchiveStore;
+
+/// Order in which queued downloads are started
+///
+/// Requests are always ordered by [`priority`](crate::DownloadRequest::priority)
+/// first (lower starts earlier); the strategy decides between requests of equal priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedulingStrategy {
+    /// In the order the requests were submitted
+    #[default]
+    Fifo,
+    /// Smallest expected size first, to finish as many files as possible early
+    SmallestFirst,
+    /// Largest expected size first, to keep long downloads from trailing at the end
+    LargestFirst,
+    /// Alternate between sources (Nexus, game files, each HTTP host, ...)
+    /// so no single server is hit with every concurrent download
+    RoundRobinBySource,
+}
+
 /// Configuration for download operations
 #[derive(Debug, Clone)]
 pub struct DownloadConfig {
@@ -27,6 +52,49 @@ pub struct DownloadConfig {
     pub retry_delay: Duration,
     /// Maximum retry delay cap (prevents exponential backoff from getting too long)
     pub max_retry_delay: Duration,
+    /// How queued downloads are ordered within the same priority
+    pub scheduling: SchedulingStrategy,
+    /// Added to a request's priority for each retry, so failing downloads make way for fresh ones
+    pub retry_priority_penalty: u32,
+    /// Maximum concurrent downloads per source kind, on top of the pipeline-wide limit
+    pub source_limits: HashMap<SourceKind, usize>,
+    /// Maximum concurrent downloads per hostname (lowercase) for HTTP and CDN sources
+    pub host_limits: HashMap<String, usize>,
+    /// Limit for hosts not listed in `host_limits` (`None` = only the other limits apply)
+    pub default_host_limit: Option<usize>,
+    /// Maximum parallel connections per HTTP download when the server supports range requests
+    pub max_segments: usize,
+    /// Smallest range worth its own connection; files under twice this size use one connection
+    pub min_segment_size: u64,
+    /// Parts of a WabbajackCDN file fetched at the same time
+    pub max_concurrent_parts: usize,
+    /// Shared cap on combined throughput; clones of the config share the same limiter,
+    /// so adjusting it affects downloads already in progress
+    pub bandwidth_limiter: BandwidthLimiter,
+    /// Keep a download journal in each destination directory so a restarted batch
+    /// skips archives that were already validated (off by default, as it writes
+    /// a journal file next to the archives)
+    pub use_journal: bool,
+    /// Cache file hashes in each destination directory, keyed by path, size and
+    /// modification time, so unchanged files are not rehashed (off by default,
+    /// as it writes a cache file next to the archives). Fresh downloads are
+    /// hashed as they stream either way; the cache spares rehashing files that
+    /// already exist.
+    pub use_hash_cache: bool,
+    /// Shared store that archives are linked from when present and added to once validated
+    pub archive_store: Option<ArchiveStore>,
+    /// Modlist recorded in the store as referencing the archives of this configuration
+    pub archive_store_modlist: Option<String>,
+    /// Free space to keep on each destination filesystem; a batch pauses when
+    /// space drops below it (0 = no monitoring)
+    pub min_free_space: u64,
+    /// How often a running batch checks the free space of its destinations
+    pub space_check_interval: Duration,
+    /// Hash-keyed mirrors to fetch an archive from once its source has failed
+    pub mirror_resolver: Option<MirrorResolver>,
+    /// Proxy, root certificates and address family used by every HTTP client,
+    /// including the Nexus API and Wabbajack CDN clients
+    pub network: NetworkPolicy,
 }
 
 impl DownloadConfig {
@@ -44,6 +112,61 @@ impl DownloadConfig {
         size >= self.large_file_threshold
     }
 
+    /// Limit concurrent downloads from one kind of source
+    pub fn with_source_limit(mut self, kind: SourceKind, limit: usize) -> Self {
+        self.source_limits.insert(kind, limit);
+        self
+    }
+
+    /// Limit concurrent downloads from one host
+    pub fn with_host_limit<S: Into<String>>(mut self, host: S, limit: usize) -> Self {
+        self.host_limits.insert(host.into().to_lowercase(), limit);
+        self
+    }
+
+    /// Cap combined download throughput at `bytes_per_second` with a new limiter
+    pub fn with_bandwidth_limit(mut self, bytes_per_second: u64) -> Self {
+        self.bandwidth_limiter = BandwidthLimiter::new(bytes_per_second);
+        self
+    }
+
+    /// Resolve archives against a shared store, recording them as used by `modlist`
+    pub fn with_archive_store<S: Into<String>>(mut self, store: ArchiveStore, modlist: S) -> Self {
+        self.archive_store = Some(store);
+        self.archive_store_modlist = Some(modlist.into());
+        self
+    }
+
+    /// Keep a download journal in each destination directory
+    pub fn with_journal(mut self) -> Self {
+        self.use_journal = true;
+        self
+    }
+
+    /// Cache file hashes in each destination directory
+    pub fn with_hash_cache(mut self) -> Self {
+        self.use_hash_cache = true;
+        self
+    }
+
+    /// Keep at least `bytes` free on every destination filesystem
+    pub fn with_min_free_space(mut self, bytes: u64) -> Self {
+        self.min_free_space = bytes;
+        self
+    }
+
+    /// Fall back to hash-keyed mirrors when a source fails
+    pub fn with_mirror_resolver(mut self, resolver: MirrorResolver) -> Self {
+        self.mirror_resolver = Some(resolver);
+        self
+    }
+
+    /// Connect according to `policy`
+    pub fn with_network_policy(mut self, policy: NetworkPolicy) -> Self {
+        self.network = policy;
+        self
+    }
+
     /// Calculate retry delay for the given attempt using exponential backoff
     pub fn get_retry_delay(&self, attempt: usize) -> Duration {
         let delay = self.retry_delay.as_millis() as u64 * 2_u64.pow(attempt as u32);
@@ -67,6 +190,23 @@ impl Default for DownloadConfig {
             parallel_validation: true,
             retry_delay: Duration::from_millis(1000), // Start with 1 second
             max_retry_delay: Duration::from_secs(60), // Cap at 1 minute
+            scheduling: SchedulingStrategy::Fifo,
+            retry_priority_penalty: 1,
+            source_limits: HashMap::new(),
+            host_limits: HashMap::new(),
+            default_host_limit: None,
+            max_segments: 4,
+            min_segment_size: 8 * 1024 * 1024, // 8MiB
+            max_concurrent_parts: 4,
+            bandwidth_limiter: BandwidthLimiter::unlimited(),
+            use_journal: false,
+            use_hash_cache: false,
+            archive_store: None,
+            archive_store_modlist: None,
+            min_free_space: 512 * 1024 * 1024, // 512MiB
+            space_check_interval: Duration::from_secs(5),
+            mirror_resolver: None,
+            network: NetworkPolicy::default(),
         }
     }
 }
@@ -22,6 +22,14 @@ pub enum DownloadError {
         duration_secs: u64,
     },
 
+    /// The server sent less (or more) than a response promised
+    #[error("Transfer from '{url}' ended after {received} of {expected} bytes")]
+    IncompleteTransfer {
+        url: String,
+        expected: u64,
+        received: u64,
+    },
+
     /// File system I/O errors with file context
     #[error("File operation failed on '{path}'")]
     FileSystem {
@@ -177,6 +185,7 @@ impl DownloadError {
                 source.status().map_or(true, |status| status.is_server_error() || status == 429)
             }
             DownloadError::NetworkTimeout { .. } => true,
+            DownloadError::IncompleteTransfer { .. } => true,
             DownloadError::FileSystem { source, .. } => {
                 // Retry on temporary file system issues
                 matches!(source.kind(),
@@ -204,6 +213,7 @@ impl DownloadError {
         match self {
             DownloadError::HttpRequest { .. } => "http_request",
             DownloadError::NetworkTimeout { .. } => "network_timeout",
+            DownloadError::IncompleteTransfer { .. } => "incomplete_transfer",
             DownloadError::FileSystem { .. } => "file_system",
             DownloadError::InvalidUrl { .. } => "invalid_url",
             DownloadError::ValidationFailed { .. } => "validation_failed",
@@ -224,6 +234,7 @@ impl DownloadError {
         match self {
             DownloadError::HttpRequest { .. } => ErrorSeverity::Medium,
             DownloadError::NetworkTimeout { .. } => ErrorSeverity::Medium,
+            DownloadError::IncompleteTransfer { .. } => ErrorSeverity::Medium,
             DownloadError::FileSystem { .. } => ErrorSeverity::High,
             DownloadError::InvalidUrl { .. } => ErrorSeverity::High,
             DownloadError::ValidationFailed { .. } => ErrorSeverity::High,
@@ -10,6 +10,13 @@ use crate::downloader::core::{
     DownloadResult, ProgressCallback, ProgressEvent, FileValidation, Result
 };
 
+/// Modification time in nanoseconds since the Unix epoch, if the platform reports one
+pub(crate) fn modified_ns(metadata: &std::fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?;
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    u64::try_from(since_epoch.as_nanos()).ok()
+}
+
 /// Check if a file exists and validate it if needed
 ///
 /// This function encapsulates the common pattern of checking for existing files
@@ -28,7 +35,7 @@ pub async fn check_existing_file(
 
 
     // Check if validation is configured
-    if validation.xxhash64_base64.is_some() || validation.expected_size.is_some() {
+    if validation.xxhash64.is_some() || validation.expected_size.is_some() {
         // Validate existing file
         match validation.validate_file(dest_path, progress_callback.clone()).await {
             Ok(true) => {
@@ -0,0 +1,213 @@
+//! Persistent xxHash64 cache
+//!
+//! Hashing a large archive takes far longer than reading its metadata, so the
+//! hash of every file that passes validation is remembered together with its
+//! size and modification time. As long as both are unchanged the cached hash
+//! is used instead of reading the file again. Entries live in an append-only
+//! JSON Lines file in the downloads directory, keyed by canonical path.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+use crate::downloader::core::jsonl::{DirectoryMap, JsonlRecord, JsonlStore};
+use crate::hash::Hash;
+
+/// File name of the hash cache inside a downloads directory
+pub const HASH_CACHE_FILE_NAME: &str = ".unifier-hashes.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    size: u64,
+    modified_ns: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheRecord {
+    path: PathBuf,
+    #[serde(flatten)]
+    fingerprint: Fingerprint,
+    hash: Hash,
+}
+
+impl JsonlRecord for CacheRecord {
+    type Key = PathBuf;
+
+    fn key(&self) -> PathBuf {
+        self.path.clone()
+    }
+}
+
+struct CacheState {
+    store: JsonlStore<CacheRecord>,
+    hits: AtomicU64,
+    hit_bytes: AtomicU64,
+}
+
+/// Cache of file hashes keyed by canonical path, size and modification time
+///
+/// Cloning yields a handle to the same cache. Inserting writes to disk, so
+/// async code should call it through `spawn_blocking`.
+#[derive(Clone)]
+pub struct HashCache {
+    state: Arc<CacheState>,
+}
+
+impl std::fmt::Debug for HashCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HashCache")
+            .field("location", &self.state.store.path())
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+impl HashCache {
+    /// Open (or create) the cache stored in `directory`
+    ///
+    /// Unreadable lines, e.g. one cut short by a crash, are skipped.
+    pub fn open(directory: &Path) -> std::io::Result<Self> {
+        Ok(Self::with_store(JsonlStore::open(&directory.join(HASH_CACHE_FILE_NAME))?))
+    }
+
+    /// Read the cache stored in `directory` without creating, compacting or writing to it
+    ///
+    /// A missing cache reads as empty, and hashes inserted afterwards are not saved.
+    pub fn load(directory: &Path) -> std::io::Result<Self> {
+        Ok(Self::with_store(JsonlStore::load(&directory.join(HASH_CACHE_FILE_NAME))?))
+    }
+
+    /// A cache that is not persisted
+    pub fn in_memory() -> Self {
+        Self::with_store(JsonlStore::in_memory())
+    }
+
+    fn with_store(store: JsonlStore<CacheRecord>) -> Self {
+        Self {
+            state: Arc::new(CacheState {
+                store,
+                hits: AtomicU64::new(0),
+                hit_bytes: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Cached hash of the file at `path` if its size and modification time still
+    /// match `metadata`
+    pub fn get(&self, path: &Path, metadata: &std::fs::Metadata) -> Option<Hash> {
+        let fingerprint = fingerprint(metadata)?;
+        let record = self.state.store.get(&canonical(path))?;
+        if record.fingerprint != fingerprint {
+            return None;
+        }
+
+        self.state.hits.fetch_add(1, Ordering::Relaxed);
+        self.state.hit_bytes.fetch_add(fingerprint.size, Ordering::Relaxed);
+        Some(record.hash)
+    }
+
+    /// Remember the hash of the file at `path`
+    ///
+    /// `metadata` should be read before hashing, so a file modified while it was
+    /// being hashed does not match the entry afterwards.
+    pub fn insert(&self, path: &Path, metadata: &std::fs::Metadata, hash: Hash) -> std::io::Result<()> {
+        let Some(fingerprint) = fingerprint(metadata) else {
+            return Ok(());
+        };
+        self.state.store.append(CacheRecord { path: canonical(path), fingerprint, hash })
+    }
+
+    /// [`insert`](Self::insert) on the blocking thread pool, logging failures
+    pub async fn insert_in_background(&self, path: &Path, metadata: std::fs::Metadata, hash: Hash) {
+        let (cache, path) = (self.clone(), path.to_path_buf());
+        let inserted = tokio::task::spawn_blocking(move || cache.insert(&path, &metadata, hash).map_err(|e| (path, e))).await;
+        match inserted {
+            Ok(Ok(())) => {}
+            Ok(Err((path, e))) => warn!("Failed to update hash cache for {}: {}", path.display(), e),
+            Err(e) => warn!("Hash cache task panicked: {}", e),
+        }
+    }
+
+    /// Number of cached hashes
+    pub fn len(&self) -> usize {
+        self.state.store.len()
+    }
+
+    /// True if no hashes are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of lookups answered from the cache, and the bytes they saved reading
+    pub fn hits(&self) -> (u64, u64) {
+        (self.state.hits.load(Ordering::Relaxed), self.state.hit_bytes.load(Ordering::Relaxed))
+    }
+}
+
+fn fingerprint(metadata: &std::fs::Metadata) -> Option<Fingerprint> {
+    Some(Fingerprint {
+        size: metadata.len(),
+        modified_ns: super::files::modified_ns(metadata)?,
+    })
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Hash caches for every downloads directory in use, opened on first use
+#[derive(Default)]
+pub struct HashCacheSet {
+    caches: DirectoryMap<HashCache>,
+}
+
+impl HashCacheSet {
+    /// Cache for `directory`, or `None` if it cannot be opened
+    pub fn cache_for(&self, directory: &Path) -> Option<HashCache> {
+        self.caches.get_or_open(directory, "Hash cache", HashCache::open)
+    }
+
+    /// Combined hits of all caches, as in [`HashCache::hits`]
+    pub fn hits(&self) -> (u64, u64) {
+        self.caches.values().iter()
+            .map(HashCache::hits)
+            .fold((0, 0), |(hits, bytes), (h, b)| (hits + h, bytes + b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("archive.7z");
+        std::fs::write(&file, b"archive").unwrap();
+        let metadata = std::fs::metadata(&file).unwrap();
+
+        let cache = HashCache::open(dir.path()).unwrap();
+        cache.insert(&file, &metadata, Hash::of_bytes(b"archive")).unwrap();
+        drop(cache);
+
+        let cache = HashCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get(&file, &metadata), Some(Hash::of_bytes(b"archive")));
+        assert_eq!(cache.hits(), (1, 7));
+    }
+
+    #[test]
+    fn test_modified_file_misses() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("archive.7z");
+        std::fs::write(&file, b"archive").unwrap();
+
+        let cache = HashCache::in_memory();
+        cache.insert(&file, &std::fs::metadata(&file).unwrap(), Hash::of_bytes(b"archive")).unwrap();
+
+        std::fs::write(&file, b"changed archive").unwrap();
+        assert_eq!(cache.get(&file, &std::fs::metadata(&file).unwrap()), None);
+        assert_eq!(cache.hits(), (0, 0));
+    }
+}
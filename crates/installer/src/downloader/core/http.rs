@@ -6,14 +6,26 @@
 use futures::StreamExt;
 use reqwest::Client;
 use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
-use tracing::debug;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tracing::{debug, warn};
 
 use crate::downloader::core::{ProgressCallback, ProgressEvent, Result, DownloadError, FileOperation};
+use crate::downloader::core::config::DownloadConfig;
+use crate::downloader::core::network::NetworkPolicy;
+use crate::downloader::core::partial::{self, ResumeValidators, SegmentPlan};
+use crate::hash::{Hash, HashState};
 use super::files::{create_temp_path, atomic_rename};
 
+/// Bytes a segment writes between syncing the file and saving its progress
+const SEGMENT_CHECKPOINT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Bytes a single-connection download writes between syncing the file and saving its hash state
+const HASH_CHECKPOINT_BYTES: u64 = 4 * 1024 * 1024;
+
 /// HTTP client with integrated download functionality
 ///
 /// This combines HTTP client configuration and streaming download capabilities
@@ -21,74 +33,99 @@ use super::files::{create_temp_path, atomic_rename};
 /// - HTTP client configuration (timeout, user agent, etc.)
 /// - Streaming downloads with progress tracking
 /// - Resume support via .part files
+/// - Segmented downloads over several connections when the server supports ranges
 /// - Atomic file operations
+/// - Bandwidth throttling through the configured limiter
+/// - Hashing downloads as they stream, so validation need not read them again
 pub struct HttpClient {
     client: Client,
-    allow_resume: bool,
+    config: DownloadConfig,
+}
+
+/// Shared progress of the segments of one download
+struct SegmentProgress {
+    total_size: u64,
+    downloaded: AtomicU64,
+    resumed_from: u64,
+    started: std::time::Instant,
+    last_report: Mutex<std::time::Instant>,
+}
+
+/// How requesting the rest of a segment ended
+enum SegmentOutcome {
+    Complete,
+    /// The server no longer serves the file the plan was made for
+    RemoteChanged(String),
 }
 
 impl HttpClient {
     /// Create a new HTTP client from download configuration
-    pub fn from_config(config: &crate::downloader::core::config::DownloadConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(config.timeout)
-            .user_agent(&config.user_agent)
-            .build()
-            .map_err(|e| DownloadError::Legacy(format!("Failed to create HTTP client: {}", e)))?;
-
-        Ok(Self {
-            client,
-            allow_resume: config.allow_resume,
-        })
+    pub fn from_config(config: &DownloadConfig) -> Result<Self> {
+        Self::with_timeout(config, config.timeout)
     }
 
     /// Create an HTTP client with custom timeout
-    pub fn with_timeout(config: &crate::downloader::core::config::DownloadConfig, timeout: Duration) -> Result<Self> {
-        let client = Client::builder()
+    pub fn with_timeout(config: &DownloadConfig, timeout: Duration) -> Result<Self> {
+        let client = config.network.client_builder(&config.user_agent)?
             .timeout(timeout)
-            .user_agent(&config.user_agent)
             .build()
             .map_err(|e| DownloadError::Legacy(format!("Failed to create HTTP client: {}", e)))?;
 
         Ok(Self {
             client,
-            allow_resume: config.allow_resume,
+            config: config.clone(),
         })
     }
 
     /// Create an HTTP client with custom configuration
     pub fn with_config(timeout: Duration, user_agent: String, allow_resume: bool) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(timeout)
-            .user_agent(&user_agent)
-            .build()
-            .map_err(|e| DownloadError::Legacy(format!("Failed to create HTTP client: {}", e)))?;
-
-        Ok(Self {
-            client,
+        let config = DownloadConfig {
+            timeout,
+            user_agent,
             allow_resume,
-        })
+            ..DownloadConfig::default()
+        };
+        Self::with_timeout(&config, timeout)
     }
 
     /// Download from URL to file with full streaming support
     ///
     /// This is the centralized implementation that replaces the duplicate
     /// download logic in HttpSource and NexusSource.
+    ///
+    /// Returns the size and the xxHash64 of the file. The hash is computed from
+    /// the bytes as they arrive, or for segmented downloads as the leading
+    /// segments finish; it is `None` only if part of the file could not be read back.
     pub async fn download_to_file(
         &self,
         url: &str,
         dest_path: &Path,
         expected_size: Option<u64>,
         progress_callback: Option<ProgressCallback>,
-    ) -> Result<u64> {
+    ) -> Result<(u64, Option<Hash>)> {
         debug!("Stream downloading: {} to {}", url, dest_path.display());
 
         // Ensure destination directory exists
         fs::create_dir_all(dest_path.parent().unwrap()).await?;
 
-        // Check for existing partial file and resume support
         let temp_path = create_temp_path(dest_path);
-        let start_byte = if self.allow_resume && temp_path.exists() {
+        if let Some(total_size) = expected_size
+            && let Some(plan) = self.plan_segments(url, &temp_path, total_size).await?
+        {
+            // A remote file that changed mid-download is fetched again over one connection
+            if let Some(downloaded) = self.download_segmented(url, dest_path, plan, progress_callback.clone()).await? {
+                return Ok(downloaded);
+            }
+        }
+
+        // A preallocated segmented .part file cannot be resumed by length
+        if SegmentPlan::sidecar_path(&temp_path).exists() {
+            debug!("Discarding segmented partial file {}", temp_path.display());
+            discard_partial(&temp_path).await;
+        }
+
+        // Check for existing partial file and resume support
+        let mut start_byte = if self.config.allow_resume && temp_path.exists() {
             let size = fs::metadata(&temp_path).await
                 .map_err(|e| DownloadError::FileSystem {
                     path: temp_path.clone(),
@@ -101,6 +138,56 @@ impl HttpClient {
             0
         };
 
+        // Resume only if the server sends the rest of the same file
+        let saved_validators = if start_byte > 0 { ResumeValidators::load(&temp_path).await } else { None };
+        let mut response = self.send_from(url, start_byte, saved_validators.as_ref()).await?;
+        if start_byte > 0
+            && let Some(reason) = resume_rejection(&response, start_byte, expected_size, saved_validators.as_ref())
+        {
+            debug!("Restarting download of {} from the beginning: {}", url, reason);
+            start_byte = 0;
+            // A complete response to the range request is the whole new file
+            if response.status() != reqwest::StatusCode::OK {
+                response = self.send_from(url, 0, None).await?;
+            }
+        }
+
+        // Check for success status
+        if let Err(e) = response.error_for_status_ref() {
+            return Err(DownloadError::HttpRequest {
+                url: url.to_string(),
+                source: e,
+            });
+        }
+
+        // A fresh .part file remembers which version of the remote file it holds
+        if start_byte == 0 {
+            let validators = ResumeValidators::from_headers(response.headers());
+            let saved = if validators.is_empty() {
+                ResumeValidators::remove(&temp_path).await
+            } else {
+                validators.save(&temp_path).await
+            };
+            if let Err(e) = saved {
+                debug!("Failed to save resume validators for {}: {}", temp_path.display(), e);
+            }
+        }
+
+        // Continue hashing where the saved state left off; without one, the
+        // existing part of the file is read once here instead of after the download
+        let mut hash_state = if start_byte == 0 {
+            let _ = partial::remove_hash_state(&temp_path).await;
+            Some(HashState::new())
+        } else {
+            match partial::catch_up_hash(partial::load_hash_state(&temp_path).await, &temp_path, start_byte).await {
+                Ok(state) => Some(state),
+                Err(e) => {
+                    warn!("Cannot hash partial file {}, validation will read it: {}", temp_path.display(), e);
+                    None
+                }
+            }
+        };
+
         // Get total size for progress tracking
         let total_size = if let Some(expected) = expected_size {
             debug!("Using expected size: {} bytes", expected);
@@ -110,28 +197,6 @@ impl HttpClient {
             None
         };
 
-        // Build request with range header for resume
-        let mut request = self.client.get(url);
-        if start_byte > 0 {
-            request = request.header("Range", format!("bytes={}-", start_byte));
-            debug!("Requesting range: bytes={}-", start_byte);
-        }
-
-        // Send request
-        let response = request.send().await
-            .map_err(|e| DownloadError::HttpRequest {
-                url: url.to_string(),
-                source: e,
-            })?;
-
-        // Check for success status
-        if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
-            return Err(DownloadError::HttpRequest {
-                url: url.to_string(),
-                source: reqwest::Error::from(response.error_for_status().unwrap_err()),
-            });
-        }
-
         // Get content length and calculate total size
         let content_length = response.content_length().unwrap_or(0);
         let total_size = total_size.or(Some(if start_byte > 0 {
@@ -176,12 +241,24 @@ impl HttpClient {
         let mut downloaded = start_byte;
         let start_time = std::time::Instant::now();
         let mut last_progress_time = start_time;
+        let mut last_checkpoint = start_byte;
 
         while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result.map_err(|e| DownloadError::HttpRequest {
-                url: url.to_string(),
-                source: e,
-            })?;
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    // Let the retry continue the hash instead of reading the file again
+                    if let Some(ref state) = hash_state {
+                        checkpoint_hash(&mut file, state, &temp_path).await;
+                    }
+                    return Err(DownloadError::HttpRequest {
+                        url: url.to_string(),
+                        source: e,
+                    });
+                }
+            };
+
+            self.config.bandwidth_limiter.consume(chunk.len()).await;
 
             file.write_all(&chunk).await
                 .map_err(|e| DownloadError::FileSystem {
@@ -192,6 +269,14 @@ impl HttpClient {
 
             downloaded += chunk.len() as u64;
 
+            if let Some(ref mut state) = hash_state {
+                state.update(&chunk);
+                if downloaded - last_checkpoint >= HASH_CHECKPOINT_BYTES {
+                    checkpoint_hash(&mut file, state, &temp_path).await;
+                    last_checkpoint = downloaded;
+                }
+            }
+
             // Report progress at most every 100ms to avoid spam
             let now = std::time::Instant::now();
             if now.duration_since(last_progress_time).as_millis() >= 100 {
@@ -231,6 +316,8 @@ impl HttpClient {
 
         // Atomically rename temp file to final destination
         atomic_rename(&temp_path, dest_path).await?;
+        let _ = ResumeValidators::remove(&temp_path).await;
+        let _ = partial::remove_hash_state(&temp_path).await;
 
         // Report completion
         if let Some(ref callback) = progress_callback {
@@ -241,7 +328,294 @@ impl HttpClient {
         }
 
         debug!("Stream download completed: {} bytes", downloaded);
-        Ok(downloaded)
+        Ok((downloaded, hash_state.as_ref().map(HashState::finish)))
+    }
+
+    /// Decide whether to download `total_size` bytes from `url` in segments
+    ///
+    /// Resumes the plan saved next to `temp_path` if there is one, and otherwise
+    /// probes the server for range support. Returns `None` to use a single connection.
+    async fn plan_segments(&self, url: &str, temp_path: &Path, total_size: u64) -> Result<Option<SegmentPlan>> {
+        let min_segment_size = self.config.min_segment_size.max(1);
+        let count = self.config.max_segments.min((total_size / min_segment_size) as usize);
+        if count < 2 {
+            return Ok(None);
+        }
+
+        if self.config.allow_resume
+            && temp_path.exists()
+            && let Some(plan) = SegmentPlan::load(temp_path).await
+            && plan.total_size == total_size
+        {
+            debug!("Resuming segmented download at {} of {} bytes", plan.downloaded(), total_size);
+            return Ok(Some(plan));
+        }
+
+        let Some(remote) = self.probe_ranges(url, total_size).await else {
+            debug!("Server does not support range requests for {}, using one connection", url);
+            return Ok(None);
+        };
+
+        let mut plan = SegmentPlan::new(total_size, count);
+        plan.validators = remote.clone();
+        if self.config.allow_resume
+            && temp_path.exists()
+            && !SegmentPlan::sidecar_path(temp_path).exists()
+            && !ResumeValidators::load(temp_path).await.is_some_and(|saved| saved.conflicts_with(&remote))
+        {
+            // Keep what an earlier single-connection attempt already fetched of the same file
+            let existing = fs::metadata(temp_path).await
+                .map_err(|e| DownloadError::FileSystem {
+                    path: temp_path.to_path_buf(),
+                    operation: FileOperation::Metadata,
+                    source: e,
+                })?.len();
+            plan.mark_prefix_done(existing.min(total_size));
+            plan.hash_state = partial::load_hash_state(temp_path).await;
+        }
+        Ok(Some(plan))
+    }
+
+    /// Check whether the server serves byte ranges of a `total_size` byte file,
+    /// returning the validators of the file if it does
+    async fn probe_ranges(&self, url: &str, total_size: u64) -> Option<ResumeValidators> {
+        let response = self.client.head(url).send().await.ok()?;
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok());
+        // Read the header directly; the body size hint of a HEAD response is always zero
+        let accepts_bytes = header(reqwest::header::ACCEPT_RANGES).is_some_and(|value| value.eq_ignore_ascii_case("bytes"));
+        let length = header(reqwest::header::CONTENT_LENGTH).and_then(|value| value.parse::<u64>().ok());
+        (response.status().is_success() && accepts_bytes && length == Some(total_size))
+            .then(|| ResumeValidators::from_headers(response.headers()))
+    }
+
+    /// Request `url` from byte `start_byte` on, conditional on `validators` still matching
+    async fn send_from(&self, url: &str, start_byte: u64, validators: Option<&ResumeValidators>) -> Result<reqwest::Response> {
+        let mut request = self.client.get(url);
+        if start_byte > 0 {
+            debug!("Requesting range: bytes={}-", start_byte);
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", start_byte));
+            if let Some(if_range) = validators.and_then(ResumeValidators::if_range) {
+                request = request.header(reqwest::header::IF_RANGE, if_range);
+            }
+        }
+
+        request.send().await
+            .map_err(|e| DownloadError::HttpRequest {
+                url: url.to_string(),
+                source: e,
+            })
+    }
+
+    /// Download all incomplete segments of `plan` concurrently into a preallocated .part file
+    ///
+    /// Returns the size and hash as [`download_to_file`](Self::download_to_file) does,
+    /// or `None` if the remote file no longer matches the plan, after
+    /// discarding the plan and the .part file.
+    async fn download_segmented(
+        &self,
+        url: &str,
+        dest_path: &Path,
+        mut plan: SegmentPlan,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<Option<(u64, Option<Hash>)>> {
+        let temp_path = create_temp_path(dest_path);
+        let total_size = plan.total_size;
+        debug!("Segmented download of {} in {} ranges", url, plan.segments.len());
+
+        // The leading segments are hashed as they complete
+        if plan.hash_state.is_none() {
+            plan.hash_state = Some(HashState::new());
+        }
+
+        let file = fs::OpenOptions::new().create(true).write(true).truncate(false).open(&temp_path).await
+            .map_err(|e| DownloadError::FileSystem {
+                path: temp_path.clone(),
+                operation: FileOperation::Create,
+                source: e,
+            })?;
+        file.set_len(total_size).await
+            .map_err(|e| DownloadError::FileSystem {
+                path: temp_path.clone(),
+                operation: FileOperation::Write,
+                source: e,
+            })?;
+        drop(file);
+        save_plan(&plan, &temp_path).await?;
+
+        if let Some(ref callback) = progress_callback {
+            callback(ProgressEvent::DownloadStarted {
+                url: url.to_string(),
+                total_size: Some(total_size),
+            });
+        }
+
+        let now = std::time::Instant::now();
+        let progress = SegmentProgress {
+            total_size,
+            downloaded: AtomicU64::new(plan.downloaded()),
+            resumed_from: plan.downloaded(),
+            started: now,
+            last_report: Mutex::new(now),
+        };
+        let pending: Vec<usize> = (0..plan.segments.len())
+            .filter(|&index| !plan.segments[index].is_complete())
+            .collect();
+        let plan = tokio::sync::Mutex::new(plan);
+        let hashing = tokio::sync::Mutex::new(());
+
+        // Each range retries on its own, continuing from its last checkpoint
+        let mut segments: futures::stream::FuturesUnordered<_> = pending.into_iter().map(|index| {
+            let (plan, hashing, progress, temp_path, callback) = (&plan, &hashing, &progress, &temp_path, &progress_callback);
+            async move {
+                let outcome = retry_with_backoff(
+                    move || self.download_segment(url, temp_path, plan, index, progress, callback.clone()),
+                    &self.config,
+                    callback.clone(),
+                    url,
+                ).await?;
+                if let SegmentOutcome::Complete = outcome {
+                    let _hashing = hashing.lock().await;
+                    hash_leading_segments(plan, temp_path).await?;
+                }
+                Ok::<_, DownloadError>(outcome)
+            }
+        }).collect();
+        while let Some(outcome) = segments.next().await {
+            if let SegmentOutcome::RemoteChanged(reason) = outcome? {
+                drop(segments);
+                debug!("Discarding segmented download of {}: {}", url, reason);
+                discard_partial(&temp_path).await;
+                return Ok(None);
+            }
+        }
+        drop(segments);
+
+        // Segments finished by an earlier run, or after a later one, have not been hashed yet
+        let hash_state = match plan.into_inner().hash_state {
+            Some(state) => partial::catch_up_hash(Some(state), &temp_path, total_size).await
+                .inspect_err(|e| debug!("Cannot hash {}, validation will read it: {}", temp_path.display(), e))
+                .ok(),
+            None => None,
+        };
+
+        SegmentPlan::remove(&temp_path).await?;
+        // The plan carried the hash; drop any state left by a single-connection attempt
+        let _ = partial::remove_hash_state(&temp_path).await;
+        let _ = ResumeValidators::remove(&temp_path).await;
+        atomic_rename(&temp_path, dest_path).await?;
+
+        if let Some(ref callback) = progress_callback {
+            callback(ProgressEvent::DownloadComplete {
+                url: url.to_string(),
+                final_size: total_size,
+            });
+        }
+
+        debug!("Segmented download completed: {} bytes", total_size);
+        Ok(Some((total_size, hash_state.as_ref().map(HashState::finish))))
+    }
+
+    /// Download the rest of one segment, checkpointing its progress as it goes
+    ///
+    /// The range is requested on condition that the remote file still matches
+    /// the plan's validators.
+    async fn download_segment(
+        &self,
+        url: &str,
+        temp_path: &Path,
+        plan: &tokio::sync::Mutex<SegmentPlan>,
+        index: usize,
+        progress: &SegmentProgress,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<SegmentOutcome> {
+        let (segment_start, mut position, end, total_size, validators) = {
+            let plan = plan.lock().await;
+            let segment = &plan.segments[index];
+            (segment.start, segment.position(), segment.end, plan.total_size, plan.validators.clone())
+        };
+        if position >= end {
+            return Ok(SegmentOutcome::Complete);
+        }
+
+        let mut request = self.client.get(url)
+            .header(reqwest::header::RANGE, format!("bytes={}-{}", position, end - 1));
+        if let Some(if_range) = validators.if_range() {
+            request = request.header(reqwest::header::IF_RANGE, if_range);
+        }
+        let response = request.send().await
+            .map_err(|e| DownloadError::HttpRequest {
+                url: url.to_string(),
+                source: e,
+            })?;
+        if let Some(reason) = resume_rejection(&response, position, Some(total_size), Some(&validators)) {
+            return Ok(SegmentOutcome::RemoteChanged(reason));
+        }
+        let response = response.error_for_status()
+            .map_err(|e| DownloadError::HttpRequest {
+                url: url.to_string(),
+                source: e,
+            })?;
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(DownloadError::Legacy(format!(
+                "Server ignored range request for bytes {}-{} of {}", position, end - 1, url
+            )));
+        }
+
+        let file_error = |operation, e| DownloadError::FileSystem {
+            path: temp_path.to_path_buf(),
+            operation,
+            source: e,
+        };
+        let mut file = fs::OpenOptions::new().write(true).open(temp_path).await
+            .map_err(|e| file_error(FileOperation::Write, e))?;
+        file.seek(std::io::SeekFrom::Start(position)).await
+            .map_err(|e| file_error(FileOperation::Write, e))?;
+
+        // Sync what was written and record it, so a retry or a later run continues from here
+        let checkpoint = async |file: &mut fs::File, position: u64| -> Result<()> {
+            file.sync_data().await
+                .map_err(|e| file_error(FileOperation::Write, e))?;
+            let mut plan = plan.lock().await;
+            plan.segments[index].done = position - segment_start;
+            save_plan(&plan, temp_path).await
+        };
+
+        let mut stream = response.bytes_stream();
+        let mut last_checkpoint = position;
+        while position < end {
+            let chunk = match stream.next().await {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    checkpoint(&mut file, position).await?;
+                    return Err(DownloadError::HttpRequest { url: url.to_string(), source: e });
+                }
+                None => {
+                    checkpoint(&mut file, position).await?;
+                    return Err(DownloadError::IncompleteTransfer {
+                        url: url.to_string(),
+                        expected: end - segment_start,
+                        received: position - segment_start,
+                    });
+                }
+            };
+            let chunk = &chunk[..chunk.len().min((end - position) as usize)];
+
+            self.config.bandwidth_limiter.consume(chunk.len()).await;
+            file.write_all(chunk).await
+                .map_err(|e| file_error(FileOperation::Write, e))?;
+            position += chunk.len() as u64;
+
+            let downloaded = progress.downloaded.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+            progress.report(url, downloaded, progress_callback.as_ref());
+
+            if position - last_checkpoint >= SEGMENT_CHECKPOINT_BYTES {
+                checkpoint(&mut file, position).await?;
+                last_checkpoint = position;
+            }
+        }
+
+        checkpoint(&mut file, position).await?;
+        Ok(SegmentOutcome::Complete)
     }
 
 
@@ -252,7 +626,7 @@ impl HttpClient {
         expected_size: Option<u64>,
         progress_callback: Option<ProgressCallback>,
         config: &crate::downloader::core::config::DownloadConfig,
-    ) -> Result<u64> {
+    ) -> Result<(u64, Option<Hash>)> {
 
         retry_with_backoff(
             || async {
@@ -285,11 +659,136 @@ impl HttpClient {
     }
 }
 
+impl SegmentProgress {
+    /// Report combined progress at most every 100ms
+    fn report(&self, url: &str, downloaded: u64, progress_callback: Option<&ProgressCallback>) {
+        let Some(callback) = progress_callback else {
+            return;
+        };
+        let now = std::time::Instant::now();
+        {
+            let mut last_report = self.last_report.lock().unwrap();
+            if now.duration_since(*last_report).as_millis() < 100 {
+                return;
+            }
+            *last_report = now;
+        }
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            downloaded.saturating_sub(self.resumed_from) as f64 / elapsed
+        } else {
+            0.0
+        };
+        callback(ProgressEvent::DownloadProgress {
+            url: url.to_string(),
+            downloaded,
+            total: Some(self.total_size),
+            speed_bps: speed,
+        });
+    }
+}
+
+/// Extend the hash of `plan` over its complete leading segments and save it
+///
+/// The segments are read back from disk, where they were synced as they
+/// finished. If the file cannot be read the hash is dropped, and validation
+/// reads the file instead. Callers must not extend the same plan concurrently.
+async fn hash_leading_segments(plan: &tokio::sync::Mutex<SegmentPlan>, temp_path: &Path) -> Result<()> {
+    let (state, prefix) = {
+        let plan = plan.lock().await;
+        match &plan.hash_state {
+            Some(state) if state.len() < plan.complete_prefix() => (state.clone(), plan.complete_prefix()),
+            _ => return Ok(()),
+        }
+    };
+
+    let extended = partial::catch_up_hash(Some(state), temp_path, prefix).await
+        .inspect_err(|e| debug!("Cannot hash {}, validation will read it: {}", temp_path.display(), e))
+        .ok();
+    let mut plan = plan.lock().await;
+    plan.hash_state = extended;
+    save_plan(&plan, temp_path).await
+}
+
+/// Remove a .part file along with every sidecar kept next to it
+async fn discard_partial(temp_path: &Path) {
+    let _ = fs::remove_file(temp_path).await;
+    let _ = SegmentPlan::remove(temp_path).await;
+    let _ = partial::remove_hash_state(temp_path).await;
+    let _ = ResumeValidators::remove(temp_path).await;
+}
+
+async fn save_plan(plan: &SegmentPlan, temp_path: &Path) -> Result<()> {
+    plan.save(temp_path).await
+        .map_err(|e| DownloadError::FileSystem {
+            path: SegmentPlan::sidecar_path(temp_path),
+            operation: FileOperation::Write,
+            source: e,
+        })
+}
+
+/// First byte and total size from a `Content-Range: bytes first-last/total` header
+fn content_range(headers: &reqwest::header::HeaderMap) -> Option<(u64, Option<u64>)> {
+    let value = headers.get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (first, _) = range.split_once('-')?;
+    Some((first.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+/// Why a response to a resume request from `start_byte` cannot be appended
+/// to the .part file, or `None` if it can
+///
+/// Error statuses other than 416 are left for the caller to report.
+fn resume_rejection(
+    response: &reqwest::Response,
+    start_byte: u64,
+    expected_size: Option<u64>,
+    saved: Option<&ResumeValidators>,
+) -> Option<String> {
+    match response.status() {
+        reqwest::StatusCode::PARTIAL_CONTENT => {}
+        reqwest::StatusCode::OK => return Some("server sent the whole file".to_string()),
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE => return Some("requested range not satisfiable".to_string()),
+        _ => return None,
+    }
+
+    let Some((start, total)) = content_range(response.headers()) else {
+        return Some("partial response without a valid Content-Range".to_string());
+    };
+    if start != start_byte {
+        return Some(format!("server sent bytes from {} instead of {}", start, start_byte));
+    }
+    if let (Some(total), Some(expected)) = (total, expected_size)
+        && total != expected
+    {
+        return Some(format!("remote file is {} bytes, expected {}", total, expected));
+    }
+    if saved.is_some_and(|saved| saved.conflicts_with(&ResumeValidators::from_headers(response.headers()))) {
+        return Some("remote file changed since the partial download".to_string());
+    }
+    None
+}
+
+/// Sync the .part file and save the hash of its contents next to it
+///
+/// The state is only an optimisation, so failures are logged and the download carries on.
+async fn checkpoint_hash(file: &mut fs::File, state: &HashState, temp_path: &Path) {
+    let saved = match file.flush().await.and(file.sync_data().await) {
+        Ok(()) => partial::save_hash_state(state, temp_path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        debug!("Failed to save hash state for {}: {}", temp_path.display(), e);
+    }
+}
+
 // Legacy compatibility - keep old builder and functions for backward compatibility
 /// Builder for creating configured HTTP clients (legacy compatibility)
 pub struct HttpClientBuilder {
     timeout: Duration,
     user_agent: String,
+    network: NetworkPolicy,
 }
 
 impl HttpClientBuilder {
@@ -298,6 +797,7 @@ impl HttpClientBuilder {
         Self {
             timeout: config.timeout,
             user_agent: config.user_agent.clone(),
+            network: config.network.clone(),
         }
     }
 
@@ -315,9 +815,8 @@ impl HttpClientBuilder {
 
     /// Build the HTTP client with the configured settings
     pub fn build(self) -> Result<Client> {
-        Client::builder()
+        self.network.client_builder(&self.user_agent)?
             .timeout(self.timeout)
-            .user_agent(&self.user_agent)
             .build()
             .map_err(|e| DownloadError::Legacy(format!("Failed to create HTTP client: {}", e)))
     }
@@ -0,0 +1,186 @@
+//! Persistent download journal
+//!
+//! Each downloads directory gets an append-only JSON Lines file recording the
+//! state transitions of every archive, keyed by archive hash. After a crash
+//! or restart the pipeline reads it back: archives recorded as validated are
+//! skipped without rehashing as long as the file on disk is unchanged, and
+//! partially downloaded archives resume from their `.part` files.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::downloader::core::jsonl::{DirectoryMap, JsonlRecord, JsonlStore};
+use crate::hash::Hash;
+
+/// File name of the journal inside a downloads directory
+pub const JOURNAL_FILE_NAME: &str = ".unifier-journal.jsonl";
+
+/// Where an archive is in its download lifecycle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JournalState {
+    Queued,
+    Downloading,
+    Downloaded,
+    /// Passed validation; `size` and `modified_ns` identify the file that passed
+    Validated { size: u64, modified_ns: Option<u64> },
+    Failed { reason: String },
+}
+
+/// The latest recorded state of one archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub hash: Hash,
+    pub filename: String,
+    #[serde(flatten)]
+    pub state: JournalState,
+}
+
+impl JsonlRecord for JournalEntry {
+    type Key = Hash;
+
+    fn key(&self) -> Hash {
+        self.hash
+    }
+}
+
+/// Journal for a single downloads directory
+///
+/// Recording writes to disk, so async code should call it through `spawn_blocking`.
+pub struct DownloadJournal {
+    directory: PathBuf,
+    store: JsonlStore<JournalEntry>,
+}
+
+impl DownloadJournal {
+    /// Open (or create) the journal in `directory`
+    ///
+    /// Unreadable lines, e.g. one cut short by a crash, are skipped.
+    pub fn open(directory: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            store: JsonlStore::open(&directory.join(JOURNAL_FILE_NAME))?,
+        })
+    }
+
+    /// Read the journal in `directory` without creating, compacting or writing to it
+    ///
+    /// A missing journal reads as empty, and records added afterwards are not saved.
+    pub fn load(directory: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            store: JsonlStore::load(&directory.join(JOURNAL_FILE_NAME))?,
+        })
+    }
+
+    /// Directory this journal covers
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Latest recorded entry for an archive
+    pub fn entry(&self, hash: &Hash) -> Option<JournalEntry> {
+        self.store.get(hash)
+    }
+
+    /// Append a state transition
+    pub fn record(&self, hash: Hash, filename: &str, state: JournalState) -> std::io::Result<()> {
+        self.store.append(JournalEntry { hash, filename: filename.to_string(), state })
+    }
+
+    /// Record that `filename` passed validation, fingerprinting the file on disk
+    pub fn record_validated(&self, hash: Hash, filename: &str) -> std::io::Result<()> {
+        let (size, modified_ns) = fingerprint(&self.directory.join(filename))?;
+        self.record(hash, filename, JournalState::Validated { size, modified_ns })
+    }
+
+    /// True if the archive was validated and its file is unchanged since
+    pub fn is_verified(&self, hash: &Hash, filename: &str) -> bool {
+        let Some(entry) = self.entry(hash) else {
+            return false;
+        };
+        let JournalState::Validated { size, modified_ns } = entry.state else {
+            return false;
+        };
+        if entry.filename != filename {
+            return false;
+        }
+
+        match fingerprint(&self.directory.join(filename)) {
+            Ok(current) => current == (size, modified_ns),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Size and modification time of a file, used to detect changes since validation
+fn fingerprint(path: &Path) -> std::io::Result<(u64, Option<u64>)> {
+    let metadata = std::fs::metadata(path)?;
+    Ok((metadata.len(), super::files::modified_ns(&metadata)))
+}
+
+/// Journals for every downloads directory a batch writes to, opened on first use
+///
+/// Cloning yields a handle to the same journals.
+#[derive(Clone, Default)]
+pub struct JournalSet {
+    journals: DirectoryMap<Arc<DownloadJournal>>,
+}
+
+impl JournalSet {
+    /// Journal for `directory`, or `None` if it cannot be opened
+    pub fn journal_for(&self, directory: &Path) -> Option<Arc<DownloadJournal>> {
+        self.journals.get_or_open(directory, "Download journal", |directory| {
+            DownloadJournal::open(directory).map(Arc::new)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    #[test]
+    fn test_journal_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let hash = Hash::of_bytes(b"archive");
+        std::fs::write(dir.path().join("a.7z"), b"archive").unwrap();
+
+        let journal = DownloadJournal::open(dir.path()).unwrap();
+        journal.record(hash, "a.7z", JournalState::Queued).unwrap();
+        journal.record(hash, "a.7z", JournalState::Downloading).unwrap();
+        journal.record_validated(hash, "a.7z").unwrap();
+        drop(journal);
+
+        // Simulate a crash in the middle of writing a line
+        let mut file = OpenOptions::new().append(true).open(dir.path().join(JOURNAL_FILE_NAME)).unwrap();
+        file.write_all(b"{\"hash\": \"trunc").unwrap();
+        drop(file);
+
+        let journal = DownloadJournal::open(dir.path()).unwrap();
+        assert!(journal.is_verified(&hash, "a.7z"));
+        assert!(!journal.is_verified(&hash, "b.7z"));
+        assert!(!journal.is_verified(&Hash::of_bytes(b"other"), "a.7z"));
+    }
+
+    #[test]
+    fn test_changed_file_is_not_verified() {
+        let dir = tempfile::tempdir().unwrap();
+        let hash = Hash::of_bytes(b"archive");
+        std::fs::write(dir.path().join("a.7z"), b"archive").unwrap();
+
+        let journal = DownloadJournal::open(dir.path()).unwrap();
+        journal.record_validated(hash, "a.7z").unwrap();
+        std::fs::write(dir.path().join("a.7z"), b"tampered archive").unwrap();
+        assert!(!journal.is_verified(&hash, "a.7z"));
+
+        journal.record(hash, "a.7z", JournalState::Failed { reason: "bad hash".to_string() }).unwrap();
+        assert_eq!(
+            journal.entry(&hash).unwrap().state,
+            JournalState::Failed { reason: "bad hash".to_string() }
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Append-only JSON Lines stores
+//!
+//! The download journal and the hash cache both keep the latest record per key
+//! in memory and append every change to a JSON Lines file in the downloads
+//! directory. [`JsonlStore`] holds that file and its records, and
+//! [`DirectoryMap`] opens one store per directory on first use.
+//!
+//! Writes are blocking; async callers go through `spawn_blocking`.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// Rewrite a store on open once it holds this many lines per record
+const COMPACT_RATIO: usize = 4;
+
+/// A record kept in a [`JsonlStore`]; later records replace earlier ones with the same key
+pub(crate) trait JsonlRecord: Serialize + DeserializeOwned + Clone {
+    type Key: Eq + std::hash::Hash;
+
+    fn key(&self) -> Self::Key;
+}
+
+struct StoreInner<R: JsonlRecord> {
+    /// Append handle, `None` for stores that are not written back
+    file: Option<File>,
+    records: HashMap<R::Key, R>,
+}
+
+/// What [`JsonlStore::read`] found in a file
+struct Contents<R: JsonlRecord> {
+    records: HashMap<R::Key, R>,
+    /// Number of complete lines
+    lines: usize,
+    /// Offset of an unterminated last line, if there is one
+    torn_at: Option<u64>,
+}
+
+/// Latest record per key, backed by an append-only JSON Lines file
+pub(crate) struct JsonlStore<R: JsonlRecord> {
+    path: Option<PathBuf>,
+    inner: Mutex<StoreInner<R>>,
+}
+
+impl<R: JsonlRecord> JsonlStore<R> {
+    /// Open (or create) the store at `path`, compacting it if it has grown
+    ///
+    /// Unreadable lines are skipped. A last line cut short by a crash is
+    /// truncated away, so the next append starts on a line of its own.
+    pub(crate) fn open(path: &Path) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let Contents { records, lines, torn_at } = Self::read(path)?;
+
+        if lines > records.len().max(1) * COMPACT_RATIO {
+            debug!("Compacting {} ({} lines, {} records)", path.display(), lines, records.len());
+            Self::rewrite(path, &records)?;
+        } else if let Some(len) = torn_at {
+            debug!("Truncating the unfinished last line of {}", path.display());
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(len)?;
+            file.sync_all()?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::with_records(Some(path.to_path_buf()), Some(file), records))
+    }
+
+    /// Read the store at `path` without creating, compacting or appending to it
+    ///
+    /// A missing file reads as an empty store. Records added afterwards are only kept in memory.
+    pub(crate) fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::with_records(Some(path.to_path_buf()), None, Self::read(path)?.records))
+    }
+
+    /// A store that lives only in memory
+    pub(crate) fn in_memory() -> Self {
+        Self::with_records(None, None, HashMap::new())
+    }
+
+    fn with_records(path: Option<PathBuf>, file: Option<File>, records: HashMap<R::Key, R>) -> Self {
+        Self { path, inner: Mutex::new(StoreInner { file, records }) }
+    }
+
+    /// Records and lines of the file at `path`
+    fn read(path: &Path) -> std::io::Result<Contents<R>> {
+        let mut contents = Contents { records: HashMap::new(), lines: 0, torn_at: None };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(contents),
+            Err(e) => return Err(e),
+        };
+
+        // Lines are read as bytes, so one with invalid UTF-8 is skipped like any other unreadable line
+        let mut reader = BufReader::new(file);
+        let mut line = Vec::new();
+        let mut offset = 0;
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 {
+                break;
+            }
+            if line.pop() != Some(b'\n') {
+                debug!("Ignoring unfinished last line of {}", path.display());
+                contents.torn_at = Some(offset);
+                break;
+            }
+            offset += read as u64;
+            contents.lines += 1;
+            match serde_json::from_slice::<R>(&line) {
+                Ok(record) => {
+                    contents.records.insert(record.key(), record);
+                }
+                Err(e) => warn!("Skipping unreadable line in {}: {}", path.display(), e),
+            }
+        }
+        Ok(contents)
+    }
+
+    fn rewrite(path: &Path, records: &HashMap<R::Key, R>) -> std::io::Result<()> {
+        let mut temp = tempfile::NamedTempFile::new_in(path.parent().unwrap_or(Path::new(".")))?;
+        for record in records.values() {
+            serde_json::to_writer(&mut temp, record)?;
+            temp.write_all(b"\n")?;
+        }
+        temp.as_file().sync_all()?;
+        temp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// File backing the store, if any
+    pub(crate) fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Latest record for `key`
+    pub(crate) fn get(&self, key: &R::Key) -> Option<R> {
+        self.inner.lock().unwrap().records.get(key).cloned()
+    }
+
+    /// Append `record`, replacing the previous record with its key
+    pub(crate) fn append(&self, record: R) -> std::io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(file) = inner.file.as_mut() {
+            let mut line = serde_json::to_vec(&record)?;
+            line.push(b'\n');
+            file.write_all(&line)?;
+            file.flush()?;
+        }
+        inner.records.insert(record.key(), record);
+        Ok(())
+    }
+
+    /// Number of records
+    pub(crate) fn len(&self) -> usize {
+        self.inner.lock().unwrap().records.len()
+    }
+}
+
+/// One value per downloads directory, opened on first use
+///
+/// Clones share the same directories.
+pub(crate) struct DirectoryMap<T> {
+    entries: Arc<Mutex<HashMap<PathBuf, Option<T>>>>,
+}
+
+impl<T> Default for DirectoryMap<T> {
+    fn default() -> Self {
+        Self { entries: Arc::default() }
+    }
+}
+
+impl<T> Clone for DirectoryMap<T> {
+    fn clone(&self) -> Self {
+        Self { entries: Arc::clone(&self.entries) }
+    }
+}
+
+impl<T: Clone> DirectoryMap<T> {
+    /// Value for `directory`, opening it with `open` the first time; `None` if
+    /// opening failed, which is logged once as `what` being unavailable
+    pub(crate) fn get_or_open(
+        &self,
+        directory: &Path,
+        what: &str,
+        open: impl FnOnce(&Path) -> std::io::Result<T>,
+    ) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.entry(directory.to_path_buf())
+            .or_insert_with(|| match open(directory) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    warn!("{} unavailable for {}: {}", what, directory.display(), e);
+                    None
+                }
+            })
+            .clone()
+    }
+
+    /// Every value opened so far
+    pub(crate) fn values(&self) -> Vec<T> {
+        self.entries.lock().unwrap().values().flatten().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Counter {
+        name: String,
+        value: u32,
+    }
+
+    impl JsonlRecord for Counter {
+        type Key = String;
+
+        fn key(&self) -> String {
+            self.name.clone()
+        }
+    }
+
+    #[test]
+    fn test_store_compacts_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counters.jsonl");
+
+        let store = JsonlStore::<Counter>::open(&path).unwrap();
+        for value in 0..10 {
+            store.append(Counter { name: "a".to_string(), value }).unwrap();
+        }
+        drop(store);
+        let lines = |path: &Path| std::fs::read_to_string(path).unwrap().lines().count();
+        assert_eq!(lines(&path), 10);
+
+        let store = JsonlStore::<Counter>::open(&path).unwrap();
+        assert_eq!(store.get(&"a".to_string()).unwrap().value, 9);
+        assert_eq!(lines(&path), 1);
+    }
+
+    #[test]
+    fn test_open_truncates_torn_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counters.jsonl");
+
+        // A crash cut the second record short, after a line that is not UTF-8
+        let mut contents = b"{\"name\":\"a\",\"value\":1}\n\xff\xfe\n".to_vec();
+        contents.extend_from_slice(b"{\"name\":\"b\",\"va");
+        std::fs::write(&path, &contents).unwrap();
+
+        let store = JsonlStore::<Counter>::open(&path).unwrap();
+        assert_eq!(store.len(), 1);
+        store.append(Counter { name: "c".to_string(), value: 3 }).unwrap();
+        drop(store);
+
+        let store = JsonlStore::<Counter>::open(&path).unwrap();
+        assert_eq!(store.get(&"a".to_string()).unwrap().value, 1);
+        assert_eq!(store.get(&"c".to_string()).unwrap().value, 3);
+        assert!(store.get(&"b".to_string()).is_none());
+        assert!(std::fs::read(&path).unwrap().ends_with(b"{\"name\":\"c\",\"value\":3}\n"));
+    }
+
+    #[test]
+    fn test_load_never_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("counters.jsonl");
+        assert_eq!(JsonlStore::<Counter>::load(&path).unwrap().len(), 0);
+        assert!(!path.parent().unwrap().exists());
+
+        let store = JsonlStore::<Counter>::open(&path).unwrap();
+        for value in 0..10 {
+            store.append(Counter { name: "a".to_string(), value }).unwrap();
+        }
+        drop(store);
+        let before = std::fs::read(&path).unwrap();
+
+        let loaded = JsonlStore::<Counter>::load(&path).unwrap();
+        assert_eq!(loaded.get(&"a".to_string()).unwrap().value, 9);
+        loaded.append(Counter { name: "b".to_string(), value: 1 }).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+}
@@ -11,6 +11,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 /// using atomic counters for thread-safe updates across concurrent downloads.
 #[derive(Debug, Default)]
 pub struct DownloadMetrics {
+    /// Bytes of completed downloads
     pub total_bytes: AtomicU64,
     pub total_downloads: AtomicU64,
     pub successful_downloads: AtomicU64,
@@ -18,6 +19,8 @@ pub struct DownloadMetrics {
     pub validation_failures: AtomicU64,
     pub retries_attempted: AtomicU64,
     pub cache_hits: AtomicU64,
+    /// Bytes of the files behind `cache_hits`, which were neither downloaded nor read
+    pub cache_hit_bytes: AtomicU64,
 }
 
 impl DownloadMetrics {
@@ -50,7 +53,7 @@ impl DownloadMetrics {
     /// Record a cache hit (file already existed and was valid)
     pub fn record_cache_hit(&self, size: u64) {
         self.cache_hits.fetch_add(1, Ordering::Relaxed);
-        self.total_bytes.fetch_add(size, Ordering::Relaxed);
+        self.cache_hit_bytes.fetch_add(size, Ordering::Relaxed);
     }
 
     /// Get a snapshot of current metrics
@@ -63,6 +66,7 @@ impl DownloadMetrics {
             validation_failures: self.validation_failures.load(Ordering::Relaxed),
             retries_attempted: self.retries_attempted.load(Ordering::Relaxed),
             cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_hit_bytes: self.cache_hit_bytes.load(Ordering::Relaxed),
         }
     }
 }
@@ -80,6 +84,7 @@ pub struct DownloadMetricsSnapshot {
     pub validation_failures: u64,
     pub retries_attempted: u64,
     pub cache_hits: u64,
+    pub cache_hit_bytes: u64,
 }
 
 impl DownloadMetricsSnapshot {
@@ -92,13 +97,13 @@ impl DownloadMetricsSnapshot {
         }
     }
 
-    /// Calculate average file size
+    /// Calculate average file size, over downloads and cache hits
     pub fn average_size(&self) -> f64 {
         let completed = self.successful_downloads + self.cache_hits;
         if completed == 0 {
             0.0
         } else {
-            self.total_bytes as f64 / completed as f64
+            (self.total_bytes + self.cache_hit_bytes) as f64 / completed as f64
         }
     }
 }
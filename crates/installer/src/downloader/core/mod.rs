@@ -10,16 +10,28 @@ pub mod config;
 pub mod metrics;
 pub mod http;
 pub mod files;
+pub mod throttle;
+pub(crate) mod jsonl;
+pub mod journal;
+pub mod hash_cache;
+pub mod partial;
+pub mod network;
 
 // Re-export main types for convenience
 pub use error::{DownloadError, Result, ErrorSeverity, FileOperation, ValidationType, ErrorContext};
 pub use validation::{FileValidation, ValidationHandle, ValidationPool};
 pub use progress::{ProgressEvent, ProgressCallback, ProgressReporter, IntoProgressCallback, ConsoleProgressReporter, NullProgressReporter, CompositeProgressReporter};
-pub use config::DownloadConfig;
+pub use config::{DownloadConfig, SchedulingStrategy};
+pub use throttle::BandwidthLimiter;
+pub use network::{NetworkPolicy, IpPreference};
+pub use journal::{DownloadJournal, JournalEntry, JournalState};
+pub use hash_cache::HashCache;
 pub use metrics::{DownloadMetrics, DownloadMetricsSnapshot, BatchDownloadResult};
 
 use std::path::PathBuf;
 
+use crate::hash::Hash;
+
 // Re-export the structured DownloadSource for convenience
 pub use crate::downloader::sources::DownloadSource;
 
@@ -52,7 +64,7 @@ pub struct DownloadRequest {
     /// Final filename for the downloaded file
     pub filename: String,
     /// Expected file hash for validation
-    pub expected_hash: String,
+    pub expected_hash: Hash,
     /// Hash algorithm used (e.g., "XXHASH64", "SHA256")
     pub hash_algorithm: String,
     /// Expected file size in bytes
@@ -85,10 +97,9 @@ impl DownloadRequest {
         destination: P,
         filename: F,
         expected_size: u64,
-        expected_hash: String
+        expected_hash: Hash
     ) -> Self {
         use crate::downloader::sources::HttpSource;
-        let hash_copy = expected_hash.clone();
         Self {
             source: DownloadSource::Http(HttpSource::new(url)),
             destination: destination.into(),
@@ -96,7 +107,7 @@ impl DownloadRequest {
             expected_hash,
             hash_algorithm: "XXHASH64".to_string(),
             expected_size,
-            validation: FileValidation::new(hash_copy, expected_size),
+            validation: FileValidation::new(Some(expected_hash), expected_size),
             priority: 0,
             metadata: DownloadMetadata::default(),
         }
@@ -108,9 +119,8 @@ impl DownloadRequest {
         destination: P,
         filename: F,
         expected_size: u64,
-        expected_hash: String
+        expected_hash: Hash
     ) -> Self {
-        let hash_copy = expected_hash.clone();
         Self {
             source,
             destination: destination.into(),
@@ -118,7 +128,7 @@ impl DownloadRequest {
             expected_hash,
             hash_algorithm: "XXHASH64".to_string(),
             expected_size,
-            validation: FileValidation::new(hash_copy, expected_size),
+            validation: FileValidation::new(Some(expected_hash), expected_size),
             priority: 0,
             metadata: DownloadMetadata::default(),
         }
@@ -178,11 +188,18 @@ impl DownloadRequest {
 #[derive(Debug)]
 pub enum DownloadResult {
     /// File was successfully downloaded
-    Downloaded { size: u64, file_path: PathBuf },
+    ///
+    /// `hash` is the xxHash64 computed while the file streamed in, if the
+    /// source could compute it; validation then does not read the file again.
+    /// `mirror` is the mirror URL that served the file when its source failed:
+    /// one of the source's own mirror URLs, or a hash-keyed mirror, see
+    /// [`MirrorResolver`](crate::downloader::mirror::MirrorResolver).
+    Downloaded { size: u64, file_path: PathBuf, hash: Option<Hash>, mirror: Option<String> },
     /// File already existed and was validated
     AlreadyExists { size: u64, file_path: PathBuf, validated: bool },
-    /// File was partially downloaded and resumed to completion
-    Resumed { size: u64, file_path: PathBuf },
+    /// File was partially downloaded and resumed to completion, with `hash`
+    /// and `mirror` as for [`Downloaded`](Self::Downloaded)
+    Resumed { size: u64, file_path: PathBuf, hash: Option<Hash>, mirror: Option<String> },
     /// File downloaded but validation is still in progress
     ///
     /// This variant is used when async validation is enabled.
@@ -195,6 +212,16 @@ pub enum DownloadResult {
     Skipped { reason: String },
 }
 
+impl DownloadResult {
+    /// The hash computed while downloading the file, if any
+    pub fn streamed_hash(&self) -> Option<Hash> {
+        match self {
+            DownloadResult::Downloaded { hash, .. } | DownloadResult::Resumed { hash, .. } => *hash,
+            _ => None,
+        }
+    }
+}
+
 /// Result of validation operation
 #[derive(Debug)]
 pub enum ValidationResult {
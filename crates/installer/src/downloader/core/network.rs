@@ -0,0 +1,199 @@
+//! Network policy shared by every HTTP client of the crate
+//!
+//! Downloads, the Wabbajack CDN and the Nexus API each build their own
+//! `reqwest` client; [`NetworkPolicy::client_builder`] gives them all the same
+//! user agent, proxy, extra root certificates and address family preference.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Certificate, ClientBuilder, NoProxy, Proxy};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::downloader::core::{DownloadError, FileOperation, Result};
+
+/// Which address family to connect over when a host has both
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Use addresses in the order the system resolver returns them
+    #[default]
+    System,
+    /// Try IPv4 addresses first, falling back to IPv6
+    PreferIpv4,
+    /// Try IPv6 addresses first, falling back to IPv4
+    PreferIpv6,
+    /// Never connect over IPv6
+    Ipv4Only,
+    /// Never connect over IPv4
+    Ipv6Only,
+}
+
+impl IpPreference {
+    fn allows(self, ip: IpAddr) -> bool {
+        match self {
+            IpPreference::Ipv4Only => ip.is_ipv4(),
+            IpPreference::Ipv6Only => ip.is_ipv6(),
+            _ => true,
+        }
+    }
+
+    /// Sort key putting preferred addresses first
+    fn rank(self, ip: IpAddr) -> u8 {
+        match self {
+            IpPreference::PreferIpv4 => u8::from(!ip.is_ipv4()),
+            IpPreference::PreferIpv6 => u8::from(!ip.is_ipv6()),
+            _ => 0,
+        }
+    }
+}
+
+/// Proxy, certificate and address settings for outgoing connections
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkPolicy {
+    /// Proxy for every request, as an `http://`, `https://`, `socks5://` or
+    /// `socks5h://` URL, optionally with `user:password@`. Without one, the
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` environment variables apply.
+    pub proxy: Option<String>,
+    /// Hosts that bypass [`proxy`](Self::proxy), comma separated as in `NO_PROXY`
+    pub no_proxy: Option<String>,
+    /// PEM files of root certificates to trust in addition to the system ones
+    pub root_certificates: Vec<PathBuf>,
+    /// Address family to connect over
+    pub ip_preference: IpPreference,
+}
+
+impl NetworkPolicy {
+    /// Send every request through the proxy at `url`
+    pub fn with_proxy<S: Into<String>>(mut self, url: S) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Let `hosts` (comma separated) bypass the proxy
+    pub fn with_no_proxy<S: Into<String>>(mut self, hosts: S) -> Self {
+        self.no_proxy = Some(hosts.into());
+        self
+    }
+
+    /// Trust the root certificates in the PEM file at `path`
+    pub fn with_root_certificate<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.root_certificates.push(path.into());
+        self
+    }
+
+    /// Connect over the given address family
+    pub fn with_ip_preference(mut self, preference: IpPreference) -> Self {
+        self.ip_preference = preference;
+        self
+    }
+
+    /// A client builder sending `user_agent` with this policy applied
+    pub fn client_builder(&self, user_agent: &str) -> Result<ClientBuilder> {
+        let mut builder = reqwest::Client::builder().user_agent(user_agent);
+
+        if let Some(url) = &self.proxy {
+            let proxy = Proxy::all(url.as_str()).map_err(|e| DownloadError::Configuration {
+                message: format!("Invalid proxy URL '{}': {}", url, e),
+                field: Some("network.proxy".to_string()),
+                suggestion: Some("Use a URL such as http://proxy:3128 or socks5://proxy:1080".to_string()),
+            })?;
+            let no_proxy = self.no_proxy.as_deref().and_then(NoProxy::from_string);
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        }
+
+        for path in &self.root_certificates {
+            let pem = std::fs::read(path).map_err(|source| DownloadError::FileSystem {
+                path: path.clone(),
+                operation: FileOperation::Read,
+                source,
+            })?;
+            let certificates = Certificate::from_pem_bundle(&pem).map_err(|e| DownloadError::Configuration {
+                message: format!("Invalid root certificate {}: {}", path.display(), e),
+                field: Some("network.root_certificates".to_string()),
+                suggestion: Some("Root certificates must be PEM encoded".to_string()),
+            })?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        if self.ip_preference != IpPreference::System {
+            builder = builder.dns_resolver(Arc::new(PreferenceResolver(self.ip_preference)));
+        }
+        Ok(builder)
+    }
+}
+
+/// Resolver ordering and filtering the system resolver's addresses by family
+struct PreferenceResolver(IpPreference);
+
+impl Resolve for PreferenceResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let preference = self.0;
+        Box::pin(async move {
+            let mut addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?
+                .filter(|addr| preference.allows(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                let message = format!("{} has no address allowed by {:?}", name.as_str(), preference);
+                return Err(std::io::Error::new(std::io::ErrorKind::NotFound, message).into());
+            }
+            addrs.sort_by_key(|addr| preference.rank(addr.ip()));
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_preference_orders_and_filters() {
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+        let mut addrs = vec![v6, v4];
+        addrs.sort_by_key(|ip| IpPreference::PreferIpv4.rank(*ip));
+        assert_eq!(addrs, vec![v4, v6]);
+        assert!(IpPreference::PreferIpv4.allows(v6));
+        assert!(!IpPreference::Ipv4Only.allows(v6));
+        assert!(!IpPreference::Ipv6Only.allows(v4));
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        // The proxy receives the request for the unresolvable host and answers it
+        let proxy = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/archive.7z"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"via proxy".as_slice()))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        let client = NetworkPolicy::default()
+            .with_proxy(proxy.uri())
+            .with_ip_preference(IpPreference::Ipv4Only)
+            .client_builder("installer-test")
+            .unwrap()
+            .build()
+            .unwrap();
+        let body = client.get("http://archives.invalid/archive.7z").send().await.unwrap().bytes().await.unwrap();
+        assert_eq!(body.as_ref(), b"via proxy");
+    }
+
+    #[test]
+    fn test_invalid_settings_are_reported() {
+        let invalid_proxy = NetworkPolicy::default().with_proxy("not a url").client_builder("installer-test");
+        assert!(matches!(invalid_proxy, Err(DownloadError::Configuration { .. })));
+
+        let missing_certificate = NetworkPolicy::default()
+            .with_root_certificate("/nonexistent/ca.pem")
+            .client_builder("installer-test");
+        assert!(matches!(missing_certificate, Err(DownloadError::FileSystem { .. })));
+    }
+}
@@ -0,0 +1,325 @@
+//! Resume state kept next to `.part` files
+//!
+//! A segmented download writes every range at its offset in a `.part` file
+//! preallocated to the full size. Which bytes of each range have been written
+//! and synced is kept next to it in a `.part.segments` sidecar, so an
+//! interrupted download resumes each range where it stopped. The plan also
+//! keeps the validators of the remote file, so every range is requested from
+//! the same version of it.
+//!
+//! Downloads that receive their bytes in order also hash them on the way in.
+//! The hash state is checkpointed in a `.part.xxh` sidecar (or in the segment
+//! plan), so a resumed download continues hashing instead of reading the file again.
+//!
+//! A single-stream `.part` file also records the `ETag` and `Last-Modified`
+//! validators of the response it came from in a `.part.validators` sidecar,
+//! so resuming can ask the server to confirm the remote file is unchanged.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::hash::HashState;
+
+/// One byte range of a segmented download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    /// First byte of the range
+    pub start: u64,
+    /// One past the last byte of the range
+    pub end: u64,
+    /// Bytes from `start` that are written and synced
+    pub done: u64,
+}
+
+impl Segment {
+    /// Offset of the next byte to download
+    pub fn position(&self) -> u64 {
+        self.start + self.done
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.position() >= self.end
+    }
+}
+
+/// The ranges of a segmented download and how far each has got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentPlan {
+    pub total_size: u64,
+    pub segments: Vec<Segment>,
+    /// Hash of the completed leading segments, for downloads that finish segments in order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_state: Option<HashState>,
+    /// Validators of the remote file the segments are downloaded from
+    #[serde(default, skip_serializing_if = "ResumeValidators::is_empty")]
+    pub validators: ResumeValidators,
+}
+
+impl SegmentPlan {
+    /// Split `total_size` bytes into `count` ranges of nearly equal length
+    pub fn new(total_size: u64, count: usize) -> Self {
+        let count = (count.max(1) as u64).min(total_size.max(1));
+        let base = total_size / count;
+        let extra = total_size % count;
+
+        let mut start = 0;
+        let segments = (0..count)
+            .map(|i| {
+                let end = start + base + u64::from(i < extra);
+                let segment = Segment { start, end, done: 0 };
+                start = end;
+                segment
+            })
+            .collect();
+
+        Self { total_size, segments, hash_state: None, validators: ResumeValidators::default() }
+    }
+
+    /// Treat the first `bytes` bytes as already downloaded, e.g. from an
+    /// earlier single-connection attempt
+    pub fn mark_prefix_done(&mut self, bytes: u64) {
+        for segment in &mut self.segments {
+            segment.done = bytes.clamp(segment.start, segment.end) - segment.start;
+        }
+    }
+
+    /// Total bytes written so far
+    pub fn downloaded(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.done).sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.segments.iter().all(Segment::is_complete)
+    }
+
+    /// Length of the file prefix covered by complete segments
+    pub fn complete_prefix(&self) -> u64 {
+        self.segments.iter()
+            .take_while(|segment| segment.is_complete())
+            .last()
+            .map_or(0, |segment| segment.end)
+    }
+
+    /// Sidecar file recording the plan for `part_path`
+    pub fn sidecar_path(part_path: &Path) -> PathBuf {
+        with_suffix(part_path, ".segments")
+    }
+
+    /// Load the plan saved for `part_path`, if there is a readable one
+    pub async fn load(part_path: &Path) -> Option<Self> {
+        let data = fs::read(Self::sidecar_path(part_path)).await.ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Save the plan for `part_path`, replacing the previous one atomically
+    pub async fn save(&self, part_path: &Path) -> std::io::Result<()> {
+        write_atomically(&Self::sidecar_path(part_path), &serde_json::to_vec(self)?).await
+    }
+
+    /// Remove the sidecar for `part_path`, if any
+    pub async fn remove(part_path: &Path) -> std::io::Result<()> {
+        remove_if_exists(&Self::sidecar_path(part_path)).await
+    }
+}
+
+/// Validators of the remote file a single-stream `.part` file was downloaded from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeValidators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl ResumeValidators {
+    /// Validators sent with a response
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        Self {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Strong entity tag, the only kind a range request may be conditioned on
+    pub fn strong_etag(&self) -> Option<&str> {
+        self.etag.as_deref().filter(|etag| !etag.starts_with("W/"))
+    }
+
+    /// Value for an `If-Range` header: the strong ETag, or else the modification date
+    pub fn if_range(&self) -> Option<&str> {
+        self.strong_etag().or(self.last_modified.as_deref())
+    }
+
+    /// True if `other` describes a different version of the file
+    ///
+    /// Only validators present on both sides are compared.
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        let differs = |a: &Option<String>, b: &Option<String>| matches!((a, b), (Some(a), Some(b)) if a != b);
+        differs(&self.etag, &other.etag) || differs(&self.last_modified, &other.last_modified)
+    }
+
+    /// Sidecar file recording the validators for `part_path`
+    pub fn sidecar_path(part_path: &Path) -> PathBuf {
+        with_suffix(part_path, ".validators")
+    }
+
+    /// Load the validators saved for `part_path`, if there are readable ones
+    pub async fn load(part_path: &Path) -> Option<Self> {
+        let data = fs::read(Self::sidecar_path(part_path)).await.ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Save the validators for `part_path`, replacing the previous ones atomically
+    pub async fn save(&self, part_path: &Path) -> std::io::Result<()> {
+        write_atomically(&Self::sidecar_path(part_path), &serde_json::to_vec(self)?).await
+    }
+
+    /// Remove the validators sidecar for `part_path`, if any
+    pub async fn remove(part_path: &Path) -> std::io::Result<()> {
+        remove_if_exists(&Self::sidecar_path(part_path)).await
+    }
+}
+
+/// Sidecar file holding the hash state of a single-stream `.part` file
+pub fn hash_state_path(part_path: &Path) -> PathBuf {
+    with_suffix(part_path, ".xxh")
+}
+
+/// Load the hash state saved for `part_path`, if there is a readable one
+pub async fn load_hash_state(part_path: &Path) -> Option<HashState> {
+    let data = fs::read(hash_state_path(part_path)).await.ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Save the hash state for `part_path`, replacing the previous one atomically
+pub async fn save_hash_state(state: &HashState, part_path: &Path) -> std::io::Result<()> {
+    write_atomically(&hash_state_path(part_path), &serde_json::to_vec(state)?).await
+}
+
+/// Remove the hash state sidecar for `part_path`, if any
+pub async fn remove_hash_state(part_path: &Path) -> std::io::Result<()> {
+    remove_if_exists(&hash_state_path(part_path)).await
+}
+
+/// Bring a saved hash state up to the first `len` bytes of `path`
+///
+/// Only the bytes the state has not covered are read. A missing state, or one
+/// that is ahead of the file (e.g. after a crash lost unsynced data), starts over.
+pub async fn catch_up_hash(state: Option<HashState>, path: &Path, len: u64) -> std::io::Result<HashState> {
+    let mut state = state.filter(|state| state.len() <= len).unwrap_or_default();
+    if state.len() == len {
+        return Ok(state);
+    }
+
+    let mut file = fs::File::open(path).await?;
+    file.seek(std::io::SeekFrom::Start(state.len())).await?;
+    let mut remaining = len - state.len();
+    let mut buffer = vec![0u8; 64 * 1024];
+    while remaining > 0 {
+        let want = buffer.len().min(remaining as usize);
+        let read = file.read(&mut buffer[..want]).await?;
+        if read == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        state.update(&buffer[..read]);
+        remaining -= read as u64;
+    }
+    Ok(state)
+}
+
+/// Bytes already written to `part_path`, or `None` if there is no `.part` file
+///
+/// Segmented `.part` files are preallocated, so only their plan tells what is written.
+pub async fn bytes_written(part_path: &Path) -> Option<u64> {
+    if let Some(plan) = SegmentPlan::load(part_path).await {
+        return Some(plan.downloaded());
+    }
+    fs::metadata(part_path).await.ok().map(|metadata| metadata.len())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+async fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let staging = with_suffix(path, ".tmp");
+    fs::write(&staging, data).await?;
+    fs::rename(&staging, path).await
+}
+
+async fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_covers_every_byte() {
+        let plan = SegmentPlan::new(10, 3);
+        let ranges: Vec<_> = plan.segments.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(ranges, vec![(0, 4), (4, 7), (7, 10)]);
+
+        // Never more segments than bytes
+        assert_eq!(SegmentPlan::new(2, 8).segments.len(), 2);
+    }
+
+    #[test]
+    fn test_mark_prefix_done() {
+        let mut plan = SegmentPlan::new(10, 3);
+        plan.mark_prefix_done(5);
+        let done: Vec<_> = plan.segments.iter().map(|s| s.done).collect();
+        assert_eq!(done, vec![4, 1, 0]);
+        assert_eq!(plan.downloaded(), 5);
+        assert_eq!(plan.complete_prefix(), 4);
+        assert!(!plan.is_complete());
+    }
+
+    #[test]
+    fn test_validators_if_range_and_conflicts() {
+        let saved = ResumeValidators {
+            etag: Some("W/\"weak\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+        // Weak tags cannot be used with If-Range
+        assert_eq!(saved.if_range(), Some("Wed, 21 Oct 2015 07:28:00 GMT"));
+
+        let only_etag = ResumeValidators { etag: Some("W/\"weak\"".to_string()), last_modified: None };
+        assert!(!saved.conflicts_with(&only_etag));
+        let changed = ResumeValidators { etag: Some("\"strong\"".to_string()), last_modified: None };
+        assert!(saved.conflicts_with(&changed));
+        assert_eq!(changed.if_range(), Some("\"strong\""));
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_hash_reads_only_the_gap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.part");
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let mut saved = HashState::new();
+        saved.update(&data[..3000]);
+        let state = catch_up_hash(Some(saved), &path, 4000).await.unwrap();
+        assert_eq!(state.finish(), crate::hash::Hash::of_bytes(&data[..4000]));
+
+        // A state ahead of the file is discarded
+        let mut ahead = HashState::new();
+        ahead.update(&[0; 6000]);
+        let state = catch_up_hash(Some(ahead), &path, 5000).await.unwrap();
+        assert_eq!(state.finish(), crate::hash::Hash::of_bytes(&data));
+    }
+}
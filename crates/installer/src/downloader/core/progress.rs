@@ -47,6 +47,17 @@ pub enum ProgressEvent {
         url: String,
         error: String,
     },
+    /// Free space on a destination dropped below the configured reserve; the batch is paused
+    DiskSpaceLow {
+        path: String,
+        available: u64,
+        reserve: u64,
+    },
+    /// Free space is back above the reserve; a batch paused for it continues
+    DiskSpaceRecovered {
+        path: String,
+        available: u64,
+    },
 }
 
 /// Trait for progress reporting with more granular control
@@ -60,6 +71,8 @@ pub trait ProgressReporter: Send + Sync {
     fn on_retry_attempt(&self, _url: &str, _attempt: usize, _max_attempts: usize) {}
     fn on_warning(&self, _url: &str, _message: &str) {}
     fn on_error(&self, _url: &str, _error: &str) {}
+    fn on_disk_space_low(&self, _path: &str, _available: u64, _reserve: u64) {}
+    fn on_disk_space_recovered(&self, _path: &str, _available: u64) {}
 }
 
 /// Extension trait to convert ProgressReporter to ProgressCallback
@@ -97,6 +110,12 @@ impl<T: ProgressReporter + 'static> IntoProgressCallback for T {
             ProgressEvent::Error { url, error } => {
                 self.on_error(&url, &error);
             }
+            ProgressEvent::DiskSpaceLow { path, available, reserve } => {
+                self.on_disk_space_low(&path, available, reserve);
+            }
+            ProgressEvent::DiskSpaceRecovered { path, available } => {
+                self.on_disk_space_recovered(&path, available);
+            }
         })
     }
 }
@@ -147,7 +166,7 @@ impl ProgressReporter for ConsoleProgressReporter {
     fn on_validation_started(&self, file: &str, validation: &crate::downloader::core::validation::FileValidation) {
         if self.verbose {
             let mut algos = Vec::new();
-            if validation.xxhash64_base64.is_some() { algos.push("XXHASH64"); }
+            if validation.xxhash64.is_some() { algos.push("XXHASH64"); }
             if validation.expected_size.is_some() { algos.push("SIZE"); }
 
             let algo_str = if algos.is_empty() { "NONE".to_string() } else { algos.join("+") };
@@ -171,6 +190,15 @@ impl ProgressReporter for ConsoleProgressReporter {
     fn on_error(&self, url: &str, error: &str) {
         eprintln!("❌ Error downloading {}: {}", url, error);
     }
+
+    fn on_disk_space_low(&self, path: &str, available: u64, reserve: u64) {
+        eprintln!("💾 Low disk space on {}: {} bytes free, keeping {} in reserve; downloads paused",
+            path, available, reserve);
+    }
+
+    fn on_disk_space_recovered(&self, path: &str, available: u64) {
+        println!("💾 Disk space recovered on {}: {} bytes free; downloads resumed", path, available);
+    }
 }
 
 /// Null progress reporter that does nothing
@@ -265,4 +293,16 @@ impl ProgressReporter for CompositeProgressReporter {
             reporter.on_error(url, error);
         }
     }
+
+    fn on_disk_space_low(&self, path: &str, available: u64, reserve: u64) {
+        for reporter in &self.reporters {
+            reporter.on_disk_space_low(path, available, reserve);
+        }
+    }
+
+    fn on_disk_space_recovered(&self, path: &str, available: u64) {
+        for reporter in &self.reporters {
+            reporter.on_disk_space_recovered(path, available);
+        }
+    }
 }
@@ -0,0 +1,138 @@
+//! Bandwidth throttling
+//!
+//! A [`BandwidthLimiter`] is a token bucket shared by every download that
+//! holds a clone of it, so the combined throughput of a batch stays under
+//! the limit. The limit can be changed while downloads are running.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes per second, `None` for unlimited
+    rate: Option<u64>,
+    /// Available bytes; negative when callers have borrowed ahead
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, rate: u64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        // Allow at most one second worth of burst
+        self.tokens = (self.tokens + elapsed * rate as f64).min(rate as f64);
+    }
+}
+
+/// Shared token-bucket rate limiter for download throughput
+///
+/// Cloning yields a handle to the same bucket.
+#[derive(Debug, Clone)]
+pub struct BandwidthLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl BandwidthLimiter {
+    /// A limiter capped at `bytes_per_second`
+    pub fn new(bytes_per_second: u64) -> Self {
+        let limiter = Self::unlimited();
+        limiter.set_limit(Some(bytes_per_second));
+        limiter
+    }
+
+    /// A limiter that never waits
+    pub fn unlimited() -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate: None,
+                tokens: 0.0,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// Change the limit for every download sharing this limiter
+    ///
+    /// `None` removes the limit. A limit of zero is treated as one byte per second.
+    pub fn set_limit(&self, bytes_per_second: Option<u64>) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.rate = bytes_per_second.map(|rate| rate.max(1));
+        bucket.tokens = bucket.tokens.min(bucket.rate.unwrap_or(0) as f64);
+        bucket.last_refill = Instant::now();
+    }
+
+    /// Current limit in bytes per second
+    pub fn limit(&self) -> Option<u64> {
+        self.bucket.lock().unwrap().rate
+    }
+
+    /// Account for `bytes` of transfer, waiting as long as needed to stay under the limit
+    pub async fn consume(&self, bytes: usize) {
+        if let Some(wait) = self.reserve(bytes) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take `bytes` from the bucket and return how long the caller must wait
+    ///
+    /// The bucket may go into debt so large chunks and many concurrent callers
+    /// are all served, each waiting in proportion to what it took.
+    fn reserve(&self, bytes: usize) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let rate = bucket.rate?;
+        bucket.refill(rate);
+        bucket.tokens -= bytes as f64;
+
+        if bucket.tokens >= 0.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(-bucket.tokens / rate as f64))
+        }
+    }
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let limiter = BandwidthLimiter::unlimited();
+        assert_eq!(limiter.reserve(usize::MAX), None);
+        assert_eq!(limiter.limit(), None);
+    }
+
+    #[test]
+    fn test_debt_is_shared_between_clones() {
+        let limiter = BandwidthLimiter::new(1000);
+        let other = limiter.clone();
+
+        // The bucket starts empty, so each caller waits for everything taken before it
+        let first = limiter.reserve(500).unwrap();
+        let second = other.reserve(500).unwrap();
+        assert!(first.as_secs_f64() > 0.4 && first.as_secs_f64() <= 0.5);
+        assert!(second.as_secs_f64() > 0.9 && second.as_secs_f64() <= 1.0);
+
+        other.set_limit(None);
+        assert_eq!(limiter.reserve(1_000_000), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_consume_respects_rate() {
+        let limiter = BandwidthLimiter::new(10_000);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.consume(10_000).await;
+        }
+        assert!(start.elapsed() >= Duration::from_secs(4));
+    }
+}
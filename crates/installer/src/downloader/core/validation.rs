@@ -2,7 +2,8 @@
 
 use crate::downloader::core::{DownloadRequest, error::{DownloadError, Result}};
 use crate::downloader::core::progress::ProgressCallback;
-use base64;
+use crate::downloader::core::hash_cache::HashCache;
+use crate::hash::Hash;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -12,13 +13,6 @@ use tokio::io::AsyncReadExt;
 use tracing::debug;
 use xxhash_rust::xxh64::Xxh64;
 
-/// Convert xxHash64 u64 to base64 format (matching Wabbajack format)
-fn xxhash64_to_base64(hash: u64) -> String {
-    // Convert u64 to bytes in little-endian format (matching Wabbajack)
-    let bytes = hash.to_le_bytes();
-    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &bytes)
-}
-
 // Buffer pool for efficient memory reuse (fixed to prevent dirty buffer issues)
 static BUFFER_POOL: Lazy<Mutex<Vec<Vec<u8>>>> = Lazy::new(|| Mutex::new(Vec::new()));
 
@@ -52,31 +46,55 @@ fn return_buffer(mut buf: Vec<u8>) {
 /// File validation configuration - xxHash64 only
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileValidation {
-    /// Expected xxHash64 hash in base64 format (matching Wabbajack format)
-    pub xxhash64_base64: Option<String>,
+    /// Expected xxHash64 hash
+    pub xxhash64: Option<Hash>,
     /// Expected file size in bytes
     pub expected_size: Option<u64>,
+    /// Cache consulted before hashing, and updated when a file passes
+    #[serde(skip)]
+    pub hash_cache: Option<HashCache>,
 }
 
 
 
 impl FileValidation {
-    pub fn new(hash: String, size: u64) -> Self {
+    pub fn new(hash: Option<Hash>, size: u64) -> Self {
         Self {
-            xxhash64_base64: if hash.is_empty() { None } else { Some(hash) },
+            xxhash64: hash.filter(|h| !h.is_empty()),
             expected_size: Some(size),
+            hash_cache: None,
         }
     }
 
+    /// Look up and remember hashes in `cache`
+    pub fn with_hash_cache(mut self, cache: HashCache) -> Self {
+        self.hash_cache = Some(cache);
+        self
+    }
+
 
     /// Validate a file against the configured validation parameters
     pub async fn validate_file<P: AsRef<Path>>(
         &self,
         path: P,
         progress_callback: Option<ProgressCallback>,
+    ) -> Result<bool> {
+        self.validate_file_with_hash(path, None, progress_callback).await
+    }
+
+    /// Validate a file whose hash was computed while downloading it
+    ///
+    /// With a `streamed_hash` the file is not read at all; without one this is
+    /// [`validate_file`](Self::validate_file).
+    pub async fn validate_file_with_hash<P: AsRef<Path>>(
+        &self,
+        path: P,
+        streamed_hash: Option<Hash>,
+        progress_callback: Option<ProgressCallback>,
     ) -> Result<bool> {
         let path = path.as_ref();
-        let file_size = fs::metadata(path).await?.len();
+        let metadata = fs::metadata(path).await?;
+        let file_size = metadata.len();
 
         if let Some(ref callback) = progress_callback {
             callback(crate::downloader::core::progress::ProgressEvent::ValidationStarted {
@@ -103,13 +121,43 @@ impl FileValidation {
             }
         }
 
+        // A file hashed as it was downloaded, or an unchanged file with a cached
+        // hash, does not need to be read at all
+        if let (Some(streamed_hash), Some(expected_hash)) = (streamed_hash, self.xxhash64) {
+            let valid = streamed_hash == expected_hash;
+            debug!("XXHash64 streamed validation: expected={}, streamed={}, passed={}",
+                   expected_hash, streamed_hash, valid);
+            if valid && let Some(cache) = &self.hash_cache {
+                cache.insert_in_background(path, metadata, expected_hash).await;
+            }
+            self.report_validation_complete(path, valid, progress_callback);
+            return Ok(valid);
+        }
+        if let (Some(cache), Some(expected_hash)) = (&self.hash_cache, self.xxhash64)
+            && let Some(cached_hash) = cache.get(path, &metadata)
+        {
+            let valid = cached_hash == expected_hash;
+            debug!("XXHash64 cached validation: expected={}, cached={}, passed={}",
+                   expected_hash, cached_hash, valid);
+            self.report_validation_complete(path, valid, progress_callback);
+            return Ok(valid);
+        }
+
         // For small files (< streaming threshold), use parallel in-memory validation
         const DEFAULT_STREAMING_THRESHOLD: u64 = 50_000_000; // 50MB
-        if file_size < DEFAULT_STREAMING_THRESHOLD {
-            self.validate_file_in_memory(path, progress_callback).await
+        let valid = if file_size < DEFAULT_STREAMING_THRESHOLD {
+            self.validate_file_in_memory(path, progress_callback).await?
         } else {
-            self.validate_file_streaming(path, progress_callback).await
+            self.validate_file_streaming(path, progress_callback).await?
+        };
+
+        // A file that passed has exactly the expected hash
+        if valid
+            && let (Some(cache), Some(expected_hash)) = (&self.hash_cache, self.xxhash64)
+        {
+            cache.insert_in_background(path, metadata, expected_hash).await;
         }
+        Ok(valid)
     }
 
     /// Validate small files in memory using xxHash64
@@ -124,7 +172,7 @@ impl FileValidation {
         debug!("Using in-memory validation for {} bytes", file_data.len());
 
         // Compute xxHash64 if we have an expected hash
-        if let Some(ref expected_hash_base64) = self.xxhash64_base64 {
+        if let Some(expected_hash) = self.xxhash64 {
             let file_size = file_data.len();
             let actual_hash = if file_size > 10_000_000 {
                 // For files > 10MB, use blocking thread to prevent UI blocking
@@ -139,8 +187,7 @@ impl FileValidation {
                         // to make the work more granular
                     }
 
-                    let hash = hasher.digest();
-                    xxhash64_to_base64(hash)
+                    Hash::from_hasher(&hasher)
                 }).await.map_err(|e| {
                     DownloadError::ValidationTaskFailed {
                         file: path.to_path_buf(),
@@ -150,15 +197,12 @@ impl FileValidation {
                 })?
             } else {
                 // For smaller files, compute hash directly (fast enough)
-                let mut hasher = Xxh64::new(0);
-                hasher.update(&file_data);
-                let hash = hasher.digest();
-                xxhash64_to_base64(hash)
+                Hash::of_bytes(&file_data)
             };
 
-            let validation_passed = &actual_hash == expected_hash_base64;
+            let validation_passed = actual_hash == expected_hash;
             debug!("XXHash64 in-memory validation: expected={}, actual={}, passed={}",
-                   expected_hash_base64, actual_hash, validation_passed);
+                   expected_hash, actual_hash, validation_passed);
 
             if !validation_passed {
                 debug!("Hash validation failed for {}: xxhash64 mismatch", path.display());
@@ -183,7 +227,7 @@ impl FileValidation {
         debug!("Using streaming validation for {} bytes", file_size);
 
         // Only create xxhash64 hasher if we need it
-        let mut xxhash64_hasher = self.xxhash64_base64.as_ref().map(|_| {
+        let mut xxhash64_hasher = self.xxhash64.map(|_| {
             debug!("Creating new xxHash64 hasher for streaming validation");
             Xxh64::new(0)
         });
@@ -238,13 +282,12 @@ impl FileValidation {
         return_buffer(buffer);
 
         // Validate xxHash64 result
-        if let (Some(expected_hash_base64), Some(hasher)) = (self.xxhash64_base64.as_ref(), xxhash64_hasher) {
-            let actual_hash_u64 = hasher.digest();
-            debug!("Streaming: Raw hash u64={}, bytes processed={}", actual_hash_u64, bytes_read_total);
-            let actual_hash_base64 = xxhash64_to_base64(actual_hash_u64);
-            let passed = &actual_hash_base64 == expected_hash_base64;
+        if let (Some(expected_hash), Some(hasher)) = (self.xxhash64, xxhash64_hasher) {
+            let actual_hash = Hash::from_hasher(&hasher);
+            debug!("Streaming: Raw hash u64={}, bytes processed={}", actual_hash.to_u64(), bytes_read_total);
+            let passed = actual_hash == expected_hash;
             debug!("XXHash64 streaming validation: expected={}, actual={}, passed={}",
-                   expected_hash_base64, actual_hash_base64, passed);
+                   expected_hash, actual_hash, passed);
             if !passed {
                 debug!("XXHash64 streaming validation failed for {}", path.display());
                 self.report_validation_complete(path, false, progress_callback);
@@ -295,10 +338,13 @@ impl ValidationPool {
     }
 
     /// Spawn async validation task
+    ///
+    /// See [`FileValidation::validate_file_with_hash`] for `streamed_hash`.
     pub fn validate_async(
         &self,
         validation: FileValidation,
         file_path: PathBuf,
+        streamed_hash: Option<Hash>,
         url: String,
         request: DownloadRequest,
         progress_callback: Option<ProgressCallback>,
@@ -308,7 +354,7 @@ impl ValidationPool {
 
         let task_handle = tokio::spawn(async move {
             let _permit = semaphore.acquire().await.unwrap();
-            validation.validate_file(&path_clone, progress_callback).await
+            validation.validate_file_with_hash(&path_clone, streamed_hash, progress_callback).await
         });
 
         ValidationHandle {
@@ -16,11 +16,20 @@
 //! Core types (core/*)
 
 use crate::downloader::{
+    control::{cancelled_error, BatchController, BatchHandle},
+    scheduler::{ConcurrencyLimits, SourcePermits, TaskQueue},
     core::{DownloadRequest, DownloadResult, ProgressCallback, Result, ValidationPool, DownloadSource, DownloadConfig, ValidationResult, VerifiedDownloadResult, DownloadError, ValidationType},
+    core::journal::{DownloadJournal, JournalSet, JournalState},
+    core::hash_cache::HashCacheSet,
+    store::ArchiveStore,
+    space::SpaceMonitor,
 };
-use std::sync::Arc;
-use std::collections::{HashMap, VecDeque};
-use tokio::sync::{Mutex, Semaphore};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 use tracing::{debug, warn, info};
 
 /// Dispatch function to handle different download source types
@@ -55,9 +64,42 @@ async fn dispatch_download(
     }
 }
 
+/// Dispatch a download, falling back to the configured hash-keyed mirrors
+/// when its source cannot handle it, or fails on the `last_attempt` the
+/// pipeline will make
+async fn download_with_fallback(
+    request: &DownloadRequest,
+    progress_callback: Option<ProgressCallback>,
+    config: &DownloadConfig,
+    last_attempt: bool,
+) -> Result<DownloadResult> {
+    let result = dispatch_download(&request.source, request, progress_callback.clone(), config).await;
+    let Some(resolver) = config.mirror_resolver.as_ref() else {
+        return result;
+    };
+
+    match result {
+        Ok(DownloadResult::Skipped { reason }) => {
+            match resolver.download(request, progress_callback, config).await {
+                Some(Ok(result)) => Ok(result),
+                _ => Ok(DownloadResult::Skipped { reason }),
+            }
+        }
+        Err(e) if last_attempt && !matches!(e, DownloadError::Cancelled { .. } | DownloadError::InsufficientSpace { .. }) => {
+            warn!("Download of {} failed ({}), trying mirrors", request.filename, e);
+            match resolver.download(request, progress_callback, config).await {
+                Some(Ok(result)) => Ok(result),
+                // The source's error says more about why the archive is unavailable
+                _ => Err(e),
+            }
+        }
+        result => result,
+    }
+}
+
 /// A download task with retry tracking
 #[derive(Clone, Debug)]
-struct DownloadTask {
+pub(crate) struct DownloadTask {
     /// The original download request
     pub request: DownloadRequest,
     /// Number of retry attempts made
@@ -66,6 +108,363 @@ struct DownloadTask {
     pub original_index: usize,
 }
 
+/// Shared state for a single batch
+struct BatchState {
+    /// Cancellation and pause state, shared with the batch's handle
+    control: BatchController,
+    /// Tasks waiting for a download worker, in scheduling order
+    queue: Mutex<TaskQueue>,
+    /// Cancelled tasks taken out of the queue, waiting to be resolved
+    cancelled: Mutex<Vec<DownloadTask>>,
+    /// Per-source and per-host limits, shared with other batches of the pipeline
+    limits: Arc<ConcurrencyLimits>,
+    /// Final results indexed by original task index
+    results: Mutex<HashMap<usize, Result<VerifiedDownloadResult>>>,
+    /// Number of tasks without a final result yet
+    remaining: AtomicUsize,
+    /// Spawned validation and store tasks, awaited before the batch returns
+    background: Mutex<Vec<JoinHandle<()>>>,
+    /// Download journals of the destination directories, if journaling is enabled
+    journals: Option<JournalSet>,
+    /// Shared archive store, and the modlist to record as referencing its archives
+    store: Option<ArchiveStore>,
+    store_modlist: Option<String>,
+}
+
+impl BatchState {
+    fn new(
+        control: BatchController,
+        queue: TaskQueue,
+        limits: Arc<ConcurrencyLimits>,
+        journals: Option<JournalSet>,
+        config: &DownloadConfig,
+    ) -> Self {
+        Self {
+            control,
+            remaining: AtomicUsize::new(queue.len()),
+            queue: Mutex::new(queue),
+            cancelled: Mutex::new(Vec::new()),
+            limits,
+            results: Mutex::new(HashMap::new()),
+            background: Mutex::new(Vec::new()),
+            journals,
+            store: config.archive_store.clone(),
+            store_modlist: config.archive_store_modlist.clone(),
+        }
+    }
+
+    /// Run `update` on the journal covering a task's destination on the blocking
+    /// pool, logging rather than failing on I/O errors; tasks without a hash are not journaled
+    fn update_journal<F>(&self, task: &DownloadTask, update: F) -> Option<JoinHandle<()>>
+    where
+        F: FnOnce(&DownloadJournal, &DownloadRequest) -> std::io::Result<()> + Send + 'static,
+    {
+        let journals = self.journals.clone()?;
+        let request = task.request.clone();
+        Some(tokio::task::spawn_blocking(move || {
+            if let Some(journal) = journal_for(&journals, &request)
+                && let Err(e) = update(&journal, &request)
+            {
+                warn!("Failed to update download journal for {}: {}", request.filename, e);
+            }
+        }))
+    }
+
+    /// Record a state transition for a task
+    async fn record(&self, task: &DownloadTask, state: JournalState) {
+        let update = move |journal: &DownloadJournal, request: &DownloadRequest| {
+            journal.record(request.expected_hash, &request.filename, state)
+        };
+        if let Some(handle) = self.update_journal(task, update)
+            && let Err(e) = handle.await
+        {
+            warn!("Journal task panicked for {}: {}", task.request.filename, e);
+        }
+    }
+
+    /// Record how a task ended: validated files are fingerprinted and added to
+    /// the archive store, failures keep their reason, and cancelled tasks stay resumable
+    fn record_outcome(&self, task: &DownloadTask, result: &Result<VerifiedDownloadResult>) {
+        let state = match result {
+            Ok(verified) => match &verified.validation_result {
+                ValidationResult::Valid | ValidationResult::AlreadyValidated => {
+                    self.store_archive(task);
+                    let update = |journal: &DownloadJournal, request: &DownloadRequest| {
+                        journal.record_validated(request.expected_hash, &request.filename)
+                    };
+                    self.journal_in_background(task, update);
+                    return;
+                }
+                ValidationResult::Invalid(e) => JournalState::Failed { reason: e.to_string() },
+                ValidationResult::Skipped => return,
+            },
+            Err(DownloadError::Cancelled { .. }) => return,
+            Err(e) => JournalState::Failed { reason: e.to_string() },
+        };
+        let update = move |journal: &DownloadJournal, request: &DownloadRequest| {
+            journal.record(request.expected_hash, &request.filename, state)
+        };
+        self.journal_in_background(task, update);
+    }
+
+    /// Run a journal update without waiting for it; it is awaited before the batch returns
+    fn journal_in_background<F>(&self, task: &DownloadTask, update: F)
+    where
+        F: FnOnce(&DownloadJournal, &DownloadRequest) -> std::io::Result<()> + Send + 'static,
+    {
+        if let Some(handle) = self.update_journal(task, update) {
+            self.background.lock().unwrap().push(handle);
+        }
+    }
+
+    /// Add a validated file to the archive store in the background
+    fn store_archive(&self, task: &DownloadTask) {
+        let request = &task.request;
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        if request.expected_hash.is_empty() {
+            return;
+        }
+
+        let hash = request.expected_hash;
+        let file = request.destination.join(&request.filename);
+        let modlist = self.store_modlist.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            match store.insert(&hash, &file) {
+                Ok(Some(method)) => debug!("Added {} to the archive store ({:?})", file.display(), method),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to add {} to the archive store: {}", file.display(), e);
+                    return;
+                }
+            }
+            if let Some(modlist) = modlist
+                && let Err(e) = store.add_reference(&hash, &modlist)
+            {
+                warn!("Failed to record archive store reference for {}: {}", file.display(), e);
+            }
+        });
+        self.background.lock().unwrap().push(handle);
+    }
+
+    /// Place the task's archive from the store and validate it, returning false
+    /// if the store does not have it or its copy is damaged
+    async fn link_from_store(&self, task: &DownloadTask) -> bool {
+        let request = &task.request;
+        let Some(store) = self.store.clone() else {
+            return false;
+        };
+        if request.expected_hash.is_empty() {
+            return false;
+        }
+
+        let hash = request.expected_hash;
+        let destination = request.destination.join(&request.filename);
+        let linking = {
+            let (store, destination) = (store.clone(), destination.clone());
+            tokio::task::spawn_blocking(move || store.contains(&hash).then(|| store.link_into(&hash, &destination)).transpose())
+        };
+        match linking.await {
+            Ok(Ok(Some(method))) => debug!("Placed {} from the archive store ({:?})", request.filename, method),
+            Ok(Ok(None)) => return false,
+            Ok(Err(e)) => {
+                warn!("Failed to place {} from the archive store, downloading instead: {}", request.filename, e);
+                return false;
+            }
+            Err(e) => {
+                warn!("Archive store task panicked for {}: {}", request.filename, e);
+                return false;
+            }
+        }
+
+        // The stored copy is checked like any existing file, using the hash cache if there is one
+        let reason = match request.validation.validate_file(&destination, None).await {
+            Ok(true) => return true,
+            Ok(false) => "hash mismatch".to_string(),
+            Err(e) => e.to_string(),
+        };
+        warn!("Archive store copy of {} is damaged ({}), downloading instead", request.filename, reason);
+        let _ = tokio::fs::remove_file(&destination).await;
+        match tokio::task::spawn_blocking(move || store.remove(&hash)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to drop {} from the archive store: {}", request.filename, e),
+            Err(e) => warn!("Archive store task panicked for {}: {}", request.filename, e),
+        }
+        false
+    }
+
+    /// True if the journal shows the task's file as validated and unchanged since
+    async fn is_verified(&self, task: &DownloadTask) -> bool {
+        let Some(journals) = self.journals.clone() else {
+            return false;
+        };
+        let request = task.request.clone();
+        tokio::task::spawn_blocking(move || {
+            journal_for(&journals, &request)
+                .is_some_and(|journal| journal.is_verified(&request.expected_hash, &request.filename))
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.remaining.load(Ordering::Acquire) == 0
+    }
+
+    /// Take the next task to work on, with the source slots it may use
+    ///
+    /// Cancelled tasks are handed out even while paused, and without slots,
+    /// so they can be resolved straight away. Tasks cancelled one by one are
+    /// taken out of the queue in a single pass when the next task is popped.
+    fn pop_task(&self) -> Option<(DownloadTask, Option<SourcePermits>)> {
+        let mut queue = self.queue.lock().unwrap();
+        let mut cancelled = self.cancelled.lock().unwrap();
+        let indices = self.control.take_cancelled_tasks();
+        if !indices.is_empty() {
+            cancelled.extend(queue.remove_all(|t| indices.contains(&t.original_index)));
+        }
+        if let Some(task) = cancelled.pop() {
+            return Some((task, None));
+        }
+        if self.control.is_cancelled() {
+            return queue.pop_any().map(|task| (task, None));
+        }
+        if self.control.is_paused() {
+            return None;
+        }
+
+        let (task, permits) = queue.pop_available(&self.limits)?;
+        // Cancelled since the pending cancellations were taken
+        if self.control.is_task_cancelled(task.original_index) {
+            return Some((task, None));
+        }
+        Some((task, Some(permits)))
+    }
+
+    /// Put a task back in the queue; the scheduler deprioritizes it by its retry count
+    fn requeue(&self, task: DownloadTask) {
+        if self.control.is_task_cancelled(task.original_index) {
+            self.cancelled.lock().unwrap().push(task);
+        } else {
+            self.queue.lock().unwrap().push(task);
+        }
+        self.control.changed().notify_waiters();
+    }
+
+    /// Record a task's final result; only the first result for a task counts
+    fn finish(&self, index: usize, result: Result<VerifiedDownloadResult>) {
+        {
+            let mut results = self.results.lock().unwrap();
+            if results.contains_key(&index) {
+                warn!("Ignoring duplicate result for task {}", index);
+                return;
+            }
+            results.insert(index, result);
+        }
+
+        if self.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            debug!("Last task finished, waking waiters");
+            self.control.changed().notify_waiters();
+        }
+    }
+
+    /// Wait until `ready` holds, re-checking whenever the batch changes
+    async fn wait_until(&self, mut ready: impl FnMut(&Self) -> bool) {
+        loop {
+            // Register for notification before checking so a wake-up between
+            // the check and the await is not lost
+            let changed = self.control.changed().notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            if ready(self) {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+/// A task taken from the queue, which must end in exactly one final result or a retry
+///
+/// If the slot is dropped unresolved, e.g. because a worker panicked, an error
+/// result is recorded so the batch never waits on a lost task.
+struct TaskSlot {
+    batch: Arc<BatchState>,
+    task: Option<DownloadTask>,
+}
+
+impl TaskSlot {
+    fn new(batch: Arc<BatchState>, task: DownloadTask) -> Self {
+        Self { batch, task: Some(task) }
+    }
+
+    fn task(&self) -> &DownloadTask {
+        self.task.as_ref().expect("task slot already resolved")
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.batch.control.is_task_cancelled(self.task().original_index)
+    }
+
+    /// Record the final result for this task
+    fn finish(mut self, result: Result<VerifiedDownloadResult>) {
+        if let Some(task) = self.task.take() {
+            self.batch.record_outcome(&task, &result);
+            self.batch.finish(task.original_index, result);
+        }
+    }
+
+    /// Record the task as done without downloading, its file being known to be valid
+    fn finish_already_validated(self) {
+        let request = &self.task().request;
+        let download_result = DownloadResult::AlreadyExists {
+            size: request.expected_size,
+            file_path: request.destination.join(&request.filename),
+            validated: true,
+        };
+        self.finish(Ok(VerifiedDownloadResult {
+            download_result,
+            validation_result: ValidationResult::AlreadyValidated,
+        }));
+    }
+
+    /// Record the task as cancelled
+    fn cancel(self) {
+        let index = self.task().original_index;
+        debug!("Task {} cancelled", index);
+        self.finish(Err(cancelled_error(index)));
+    }
+
+    /// Put the task back on the queue for another attempt, unless it was cancelled
+    fn retry(mut self) {
+        if self.is_cancelled() {
+            self.cancel();
+            return;
+        }
+        if let Some(task) = self.task.take() {
+            self.batch.requeue(DownloadTask {
+                retry_count: task.retry_count + 1,
+                ..task
+            });
+        }
+    }
+}
+
+impl Drop for TaskSlot {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            warn!("Task {} was abandoned without a result", task.original_index);
+            self.batch.finish(task.original_index, Err(DownloadError::Configuration {
+                message: "Internal pipeline error: task abandoned without a result".to_string(),
+                field: None,
+                suggestion: Some("This indicates a bug in the pipeline logic".to_string()),
+            }));
+        }
+    }
+}
+
 /// Pipeline-based downloader with concurrent download and validation pools
 ///
 /// This architecture provides:
@@ -78,16 +477,14 @@ pub struct DownloadPipeline {
     download_pool: Arc<Semaphore>,
     /// Pool for validation operations
     validation_pool: ValidationPool,
-    /// Queue of pending download tasks
-    download_queue: Arc<Mutex<VecDeque<DownloadTask>>>,
-    /// Results map indexed by original task index
-    results: Arc<Mutex<HashMap<usize, Result<VerifiedDownloadResult>>>>,
+    /// Per-source and per-host concurrency limits from the configuration
+    limits: Arc<ConcurrencyLimits>,
+    /// Hash caches of the destination directories, shared across clones
+    hash_caches: Arc<HashCacheSet>,
     /// Configuration for downloads
     config: DownloadConfig,
     /// Maximum retry attempts per file
     max_retries: u32,
-    /// Total number of tasks expected (for completion detection)
-    total_tasks: Arc<Mutex<Option<usize>>>,
     /// Maximum concurrent downloads (stored for getter)
     max_concurrent_downloads: usize,
 }
@@ -98,11 +495,10 @@ impl DownloadPipeline {
         Self {
             download_pool: Arc::new(Semaphore::new(max_concurrent_downloads)),
             validation_pool: ValidationPool::new(config.max_concurrent_validations),
-            download_queue: Arc::new(Mutex::new(VecDeque::new())),
-            results: Arc::new(Mutex::new(HashMap::new())),
+            limits: Arc::new(ConcurrencyLimits::from_config(&config)),
+            hash_caches: Arc::new(HashCacheSet::default()),
             config,
             max_retries,
-            total_tasks: Arc::new(Mutex::new(None)),
             max_concurrent_downloads,
         }
     }
@@ -110,21 +506,23 @@ impl DownloadPipeline {
     /// Download a single file with direct validation (bypasses complex pipeline retry logic)
     pub async fn download(
         &self,
-        request: DownloadRequest,
+        mut request: DownloadRequest,
         progress_callback: Option<ProgressCallback>,
     ) -> Result<DownloadResult> {
         use tokio::fs;
 
+        self.attach_hash_cache(&mut request);
+
         // Perform download using dispatch
-        let download_result = dispatch_download(&request.source, &request, progress_callback.clone(), &self.config).await?;
+        let download_result = download_with_fallback(&request, progress_callback.clone(), &self.config, true).await?;
 
         // Handle validation directly without pipeline complexity
         match &download_result {
             DownloadResult::Downloaded { file_path, .. } |
             DownloadResult::Resumed { file_path, .. } => {
                 // Only validate if validation is configured
-                if request.validation.xxhash64_base64.is_some() || request.validation.expected_size.is_some() {
-                    match request.validation.validate_file(file_path, progress_callback).await {
+                if request.validation.xxhash64.is_some() || request.validation.expected_size.is_some() {
+                    match request.validation.validate_file_with_hash(file_path, download_result.streamed_hash(), progress_callback).await {
                         Ok(true) => Ok(download_result),
                         Ok(false) => {
                             // This shouldn't happen as validate_file returns Err for failures
@@ -149,7 +547,7 @@ impl DownloadPipeline {
             },
             DownloadResult::AlreadyExists { file_path, validated: false, .. } => {
                 // Need to validate existing file
-                if request.validation.xxhash64_base64.is_some() || request.validation.expected_size.is_some() {
+                if request.validation.xxhash64.is_some() || request.validation.expected_size.is_some() {
                     match request.validation.validate_file(file_path, progress_callback).await {
                         Ok(true) => Ok(download_result),
                         Ok(false) => Err(DownloadError::ValidationFailed {
@@ -178,9 +576,32 @@ impl DownloadPipeline {
         self.max_concurrent_downloads
     }
 
-    /// Create a mock metrics object for backward compatibility with tests
+    /// Shared bandwidth limiter; changing its limit affects downloads already running
+    pub fn bandwidth_limiter(&self) -> &crate::downloader::core::BandwidthLimiter {
+        &self.config.bandwidth_limiter
+    }
+
+    /// Metrics for this pipeline; only hash cache hits are tracked so far
     pub fn metrics(&self) -> crate::downloader::core::DownloadMetrics {
-        crate::downloader::core::DownloadMetrics::default()
+        let metrics = crate::downloader::core::DownloadMetrics::default();
+        let (hits, bytes) = self.hash_caches.hits();
+        metrics.cache_hits.store(hits, Ordering::Relaxed);
+        metrics.cache_hit_bytes.store(bytes, Ordering::Relaxed);
+        metrics
+    }
+
+    /// Report what downloading `requests` would do, without transferring anything
+    ///
+    /// See [`plan_downloads`](crate::downloader::plan::plan_downloads).
+    pub async fn plan(&self, requests: &[DownloadRequest]) -> crate::downloader::plan::DownloadPlan {
+        crate::downloader::plan::plan_downloads(requests, &self.config).await
+    }
+
+    /// Let a request's validation use the hash cache of its destination directory
+    fn attach_hash_cache(&self, request: &mut DownloadRequest) {
+        if self.config.use_hash_cache && request.validation.hash_cache.is_none() {
+            request.validation.hash_cache = self.hash_caches.cache_for(&request.destination);
+        }
     }
 
     /// Process a batch of download requests using the pipeline architecture
@@ -189,24 +610,86 @@ impl DownloadPipeline {
         requests: Vec<DownloadRequest>,
         progress_callback: Option<ProgressCallback>,
     ) -> Vec<Result<VerifiedDownloadResult>> {
+        self.start_batch(requests, progress_callback).wait().await
+    }
+
+    /// Start processing a batch in the background
+    ///
+    /// The returned handle can cancel, pause or resume the batch, and yields
+    /// the results once every task has finished.
+    pub fn start_batch(
+        &self,
+        requests: Vec<DownloadRequest>,
+        progress_callback: Option<ProgressCallback>,
+    ) -> BatchHandle {
+        self.start_batch_with_controller(BatchController::new(), requests, progress_callback)
+    }
+
+    /// Start processing a batch driven by an existing controller
+    ///
+    /// Useful when the controller has to be handed out before the batch starts,
+    /// e.g. to a Ctrl-C handler.
+    pub fn start_batch_with_controller(
+        &self,
+        controller: BatchController,
+        requests: Vec<DownloadRequest>,
+        progress_callback: Option<ProgressCallback>,
+    ) -> BatchHandle {
         let total_count = requests.len();
         debug!("Starting pipeline processing for {} files", total_count);
 
-        // Set total task count for completion detection
-        *self.total_tasks.lock().await = Some(total_count);
+        let mut destinations: Vec<PathBuf> = requests.iter().map(|request| request.destination.clone()).collect();
+        destinations.sort();
+        destinations.dedup();
+        let space_monitor = (self.config.min_free_space > 0).then(|| {
+            SpaceMonitor::new(controller.clone(), destinations, self.config.min_free_space, progress_callback.clone())
+        });
 
-        // Initialize download queue with all requests
-        {
-            let mut queue = self.download_queue.lock().await;
-            for (index, request) in requests.into_iter().enumerate() {
-                queue.push_back(DownloadTask {
-                    request,
-                    retry_count: 0,
-                    original_index: index,
-                });
-            }
-            info!("Queued {} download tasks", total_count);
+        // Initialize download queue with all requests, journaling the ones not
+        // already verified on the blocking pool before any worker starts
+        let journals = self.config.use_journal.then(JournalSet::default);
+        let journaling = journals.clone().map(|journals| {
+            let requests = requests.clone();
+            tokio::task::spawn_blocking(move || record_queued(&journals, &requests))
+        });
+        let mut queue = TaskQueue::new(self.config.scheduling, self.config.retry_priority_penalty);
+        for (index, mut request) in requests.into_iter().enumerate() {
+            self.attach_hash_cache(&mut request);
+            queue.push(DownloadTask {
+                request,
+                retry_count: 0,
+                original_index: index,
+            });
         }
+        let batch = Arc::new(BatchState::new(controller.clone(), queue, Arc::clone(&self.limits), journals, &self.config));
+        info!("Queued {} download tasks", total_count);
+
+        let pipeline = self.clone();
+        let join = tokio::spawn(async move {
+            if let Some(handle) = journaling
+                && let Err(e) = handle.await
+            {
+                warn!("Journal task panicked: {}", e);
+            }
+            pipeline.run_batch(batch, total_count, space_monitor, progress_callback).await
+        });
+        BatchHandle::new(controller, join)
+    }
+
+    /// Drive a batch until every task has a final result
+    async fn run_batch(
+        &self,
+        batch: Arc<BatchState>,
+        total_count: usize,
+        space_monitor: Option<SpaceMonitor>,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Vec<Result<VerifiedDownloadResult>> {
+
+        // Check free space before the first task starts, then keep watching it
+        let space_monitor = space_monitor.map(|mut monitor| {
+            monitor.check();
+            tokio::spawn(monitor.run(self.config.space_check_interval))
+        });
 
         // Spawn download workers (as many as permits available)
         let max_download_workers = self.download_pool.available_permits().min(total_count);
@@ -214,25 +697,40 @@ impl DownloadPipeline {
 
         for worker_id in 0..max_download_workers {
             let pipeline = self.clone();
+            let batch = Arc::clone(&batch);
             let callback = progress_callback.clone();
             let handle = tokio::spawn(async move {
-                pipeline.download_worker(worker_id, callback).await;
+                pipeline.download_worker(worker_id, batch, callback).await;
             });
             download_handles.push(handle);
         }
 
         debug!("Started {} download workers", max_download_workers);
 
-        // Wait for all tasks to complete (either success or max retries exceeded)
-        self.wait_for_completion(total_count).await;
+        // Wait for every task to report its final result (success or max retries exceeded)
+        batch.wait_until(BatchState::is_complete).await;
+        debug!("Pipeline completion detected for {} tasks", total_count);
+        if let Some(monitor) = space_monitor {
+            monitor.abort();
+        }
 
-        // Collect results and clean up
+        // Workers exit once the batch is complete
         for handle in download_handles {
-            let _ = handle.await; // Workers should have finished naturally
+            if let Err(e) = handle.await {
+                warn!("Download worker panicked: {}", e);
+            }
+        }
+
+        // Let in-flight validation and store tasks run to the end
+        let background = std::mem::take(&mut *batch.background.lock().unwrap());
+        for handle in background {
+            if let Err(e) = handle.await {
+                warn!("Background task panicked: {}", e);
+            }
         }
 
         // Extract final results in original order (consume the HashMap)
-        let mut results_map = self.results.lock().await;
+        let mut results_map = std::mem::take(&mut *batch.results.lock().unwrap());
         let mut final_results = Vec::with_capacity(total_count);
 
         for index in 0..total_count {
@@ -256,81 +754,92 @@ impl DownloadPipeline {
         final_results
     }
 
-    /// Wait for all tasks to complete
-    async fn wait_for_completion(&self, expected_count: usize) {
-        let mut check_interval = tokio::time::interval(std::time::Duration::from_millis(100));
-        let timeout = std::time::Instant::now() + std::time::Duration::from_secs(30); // 30 second timeout
+    /// Download worker that processes tasks from the download queue
+    ///
+    /// Workers stay alive until every task in the batch has a final result, since
+    /// a failed validation can put a task back on the queue at any time.
+    async fn download_worker(&self, worker_id: usize, batch: Arc<BatchState>, progress_callback: Option<ProgressCallback>) {
+        debug!("Download worker {} started", worker_id);
 
         loop {
-            check_interval.tick().await;
-
-            let results_count = self.results.lock().await.len();
-            let queue_count = self.download_queue.lock().await.len();
-
-            if results_count == expected_count {
-                debug!("Pipeline completion detected: {} results, {} queued", results_count, queue_count);
+            // Get next download task, waiting for retries, a resume or a free
+            // source slot while the batch is unfinished
+            let mut next = None;
+            batch.wait_until(|batch| {
+                next = batch.pop_task();
+                next.is_some() || batch.is_complete()
+            }).await;
+            let Some((task, source_permits)) = next else {
+                debug!("Download worker {}: batch complete, exiting", worker_id);
                 break;
+            };
+            let slot = TaskSlot::new(Arc::clone(&batch), task);
+            if slot.is_cancelled() {
+                slot.cancel();
+                drop(source_permits);
+                batch.control.changed().notify_waiters();
+                continue;
             }
 
-            // Timeout logic to prevent infinite waiting
-            if std::time::Instant::now() > timeout {
-                warn!("Pipeline completion timeout reached. Results: {}, Queue: {}, Expected: {}",
-                      results_count, queue_count, expected_count);
-                break;
+            // Skip files the journal shows were validated by an earlier run and not
+            // touched since, and files the archive store already holds
+            if batch.is_verified(slot.task()).await || batch.link_from_store(slot.task()).await {
+                debug!("Download worker {}: task {} needs no download", worker_id, slot.task().original_index);
+                slot.finish_already_validated();
+                drop(source_permits);
+                batch.control.changed().notify_waiters();
+                continue;
             }
-        }
-    }
+            batch.record(slot.task(), JournalState::Downloading).await;
 
-    /// Download worker that processes tasks from the download queue
-    async fn download_worker(&self, worker_id: usize, progress_callback: Option<ProgressCallback>) {
-        debug!("Download worker {} started", worker_id);
+            debug!("Download worker {} processing task {} (retry {})",
+                   worker_id, slot.task().original_index, slot.task().retry_count);
 
-        loop {
-            // Get next download task
-            let task = {
-                let mut queue = self.download_queue.lock().await;
-                match queue.pop_front() {
-                    Some(task) => task,
-                    None => {
-                        debug!("Download worker {}: no more tasks, exiting", worker_id);
-                        break; // No more tasks
-                    }
-                }
+            // Perform download, abandoning it if the task is cancelled. Dropping the
+            // download future leaves its .part file in place for a later resume.
+            let token = batch.control.task_token(slot.task().original_index);
+            let request = &slot.task().request;
+            let outcome = tokio::select! {
+                biased;
+                _ = token.cancelled() => None,
+                result = async {
+                    // Hold a download permit only while downloading
+                    let _permit = self.download_pool.acquire().await.unwrap();
+                    let last_attempt = slot.task().retry_count >= self.max_retries;
+                    download_with_fallback(request, progress_callback.clone(), &self.config, last_attempt).await
+                } => Some(result),
             };
 
-            debug!("Download worker {} processing task {} (retry {})",
-                   worker_id, task.original_index, task.retry_count);
-
-            // Acquire download permit
-            let _permit = self.download_pool.acquire().await.unwrap();
+            // Free the source and host slots and let idle workers pick up tasks they were blocking
+            drop(source_permits);
+            batch.control.changed().notify_waiters();
 
-            // Perform download
-            match dispatch_download(&task.request.source, &task.request, progress_callback.clone(), &self.config).await {
-                Ok(download_result) => {
-                    debug!("Download worker {} completed task {} successfully", worker_id, task.original_index);
-                    // Release download permit immediately
-                    drop(_permit);
+            match outcome {
+                None => {
+                    debug!("Download worker {} abandoned task {} after cancellation", worker_id, slot.task().original_index);
+                    slot.cancel();
+                }
+                Some(Ok(download_result)) => {
+                    debug!("Download worker {} completed task {} successfully", worker_id, slot.task().original_index);
+                    if matches!(download_result, DownloadResult::Downloaded { .. } | DownloadResult::Resumed { .. }) {
+                        batch.record(slot.task(), JournalState::Downloaded).await;
+                    }
 
                     // Queue for validation (this spawns async task)
-                    self.queue_for_validation(task, download_result, progress_callback.clone()).await;
+                    self.queue_for_validation(slot, download_result, progress_callback.clone());
                 }
-                Err(download_error) => {
-                    debug!("Download worker {} failed task {}: {}", worker_id, task.original_index, download_error);
-                    drop(_permit);
+                Some(Err(download_error)) => {
+                    debug!("Download worker {} failed task {}: {}", worker_id, slot.task().original_index, download_error);
 
-                    if task.retry_count < self.max_retries {
+                    if slot.task().retry_count < self.max_retries {
                         // Re-queue for download retry
-                        let retry_task = DownloadTask {
-                            retry_count: task.retry_count + 1,
-                            ..task
-                        };
                         warn!("Re-queueing task {} for retry {} due to download error",
-                              retry_task.original_index, retry_task.retry_count);
-                        self.download_queue.lock().await.push_back(retry_task);
+                              slot.task().original_index, slot.task().retry_count + 1);
+                        slot.retry();
                     } else {
                         // Permanent failure - max retries exceeded
-                        warn!("Task {} failed permanently after {} retries", task.original_index, task.retry_count);
-                        self.results.lock().await.insert(task.original_index, Err(download_error));
+                        warn!("Task {} failed permanently after {} retries", slot.task().original_index, slot.task().retry_count);
+                        slot.finish(Err(download_error));
                     }
                 }
             }
@@ -340,22 +849,21 @@ impl DownloadPipeline {
     }
 
     /// Queue a completed download for validation
-    async fn queue_for_validation(
+    fn queue_for_validation(
         &self,
-        task: DownloadTask,
+        slot: TaskSlot,
         download_result: DownloadResult,
         progress_callback: Option<ProgressCallback>,
     ) {
+        let index = slot.task().original_index;
+
         // Handle already validated files first
         if let DownloadResult::AlreadyExists { validated: true, .. } = &download_result {
-            debug!("Task {} file already exists and was validated, skipping validation", task.original_index);
-            self.results.lock().await.insert(
-                task.original_index,
-                Ok(VerifiedDownloadResult {
-                    download_result,
-                    validation_result: ValidationResult::AlreadyValidated,
-                })
-            );
+            debug!("Task {} file already exists and was validated, skipping validation", index);
+            slot.finish(Ok(VerifiedDownloadResult {
+                download_result,
+                validation_result: ValidationResult::AlreadyValidated,
+            }));
             return;
         }
 
@@ -366,127 +874,116 @@ impl DownloadPipeline {
             DownloadResult::AlreadyExists { file_path, .. } => file_path.clone(),
             DownloadResult::DownloadedPendingValidation { .. } => {
                 // This variant already has async validation in progress, handle differently
-                debug!("Task {} already has validation in progress", task.original_index);
-                self.results.lock().await.insert(
-                    task.original_index,
-                    Ok(VerifiedDownloadResult {
-                        download_result,
-                        validation_result: ValidationResult::Skipped, // Will be handled by existing async validation
-                    })
-                );
+                debug!("Task {} already has validation in progress", index);
+                slot.finish(Ok(VerifiedDownloadResult {
+                    download_result,
+                    validation_result: ValidationResult::Skipped, // Will be handled by existing async validation
+                }));
                 return;
             }
             DownloadResult::Skipped { .. } => {
                 // No validation needed for skipped files
-                debug!("Task {} was skipped, no validation needed", task.original_index);
-                self.results.lock().await.insert(
-                    task.original_index,
-                    Ok(VerifiedDownloadResult {
-                        download_result,
-                        validation_result: ValidationResult::Skipped,
-                    })
-                );
+                debug!("Task {} was skipped, no validation needed", index);
+                slot.finish(Ok(VerifiedDownloadResult {
+                    download_result,
+                    validation_result: ValidationResult::Skipped,
+                }));
                 return;
             }
         };
 
         // Check if validation is needed
-        if task.request.validation.xxhash64_base64.is_none() && task.request.validation.expected_size.is_none() {
+        let request = &slot.task().request;
+        if request.validation.xxhash64.is_none() && request.validation.expected_size.is_none() {
             // No validation configured
-            debug!("Task {} has no validation configured", task.original_index);
-            self.results.lock().await.insert(
-                task.original_index,
-                Ok(VerifiedDownloadResult {
-                    download_result,
-                    validation_result: ValidationResult::Skipped,
-                })
-            );
+            debug!("Task {} has no validation configured", index);
+            slot.finish(Ok(VerifiedDownloadResult {
+                download_result,
+                validation_result: ValidationResult::Skipped,
+            }));
             return;
         }
 
-        debug!("Starting validation for task {}", task.original_index);
+        debug!("Starting validation for task {}", index);
 
         // Start async validation
         let validation_handle = self.validation_pool.validate_async(
-            task.request.validation.clone(),
+            request.validation.clone(),
             file_path,
-            task.request.source.description(),
-            task.request.clone(),
+            download_result.streamed_hash(),
+            request.source.description(),
+            request.clone(),
             progress_callback.clone(),
         );
 
-        // Spawn task to handle validation completion
+        // Spawn task to handle validation completion; the batch awaits it before returning
         let pipeline = self.clone();
-        tokio::spawn(async move {
+        let batch = Arc::clone(&slot.batch);
+        let handle = tokio::spawn(async move {
             match validation_handle.task_handle.await {
                 Ok(validation_result) => {
                     match validation_result {
                         Ok(true) => {
                             // Validation succeeded
-                            debug!("Validation succeeded for task {}", task.original_index);
-                            pipeline.results.lock().await.insert(
-                                task.original_index,
-                                Ok(VerifiedDownloadResult {
-                                    download_result,
-                                    validation_result: ValidationResult::Valid,
-                                })
-                            );
+                            debug!("Validation succeeded for task {}", index);
+                            slot.finish(Ok(VerifiedDownloadResult {
+                                download_result,
+                                validation_result: ValidationResult::Valid,
+                            }));
                         }
                         Ok(false) => {
                             // Validation failed - this shouldn't happen as validate_file returns Err for failures
-                            warn!("Validation returned false for task {} (unexpected)", task.original_index);
-                            pipeline.handle_validation_failure(task, download_result, DownloadError::ValidationFailed {
+                            warn!("Validation returned false for task {} (unexpected)", index);
+                            pipeline.handle_validation_failure(slot, download_result, DownloadError::ValidationFailed {
                                 file: validation_handle.file_path,
                                 validation_type: ValidationType::Size,
                                 expected: "valid file".to_string(),
                                 actual: "invalid file".to_string(),
                                 suggestion: "Check file integrity or download again".to_string(),
-                            }).await;
+                            });
                         }
                         Err(validation_error) => {
                             // Validation failed with specific error
-                            debug!("Validation failed for task {}: {}", task.original_index, validation_error);
-                            pipeline.handle_validation_failure(task, download_result, validation_error).await;
+                            debug!("Validation failed for task {}: {}", index, validation_error);
+                            pipeline.handle_validation_failure(slot, download_result, validation_error);
                         }
                     }
                 }
                 Err(join_error) => {
                     // Validation task panicked
-                    warn!("Validation task panicked for task {}: {}", task.original_index, join_error);
+                    warn!("Validation task panicked for task {}: {}", index, join_error);
                     let validation_error = DownloadError::ValidationTaskFailed {
                         file: validation_handle.file_path,
                         reason: format!("Validation task panicked: {}", join_error),
                         source: Some(Box::new(join_error) as Box<dyn std::error::Error + Send + Sync>),
                     };
-                    pipeline.handle_validation_failure(task, download_result, validation_error).await;
+                    pipeline.handle_validation_failure(slot, download_result, validation_error);
                 }
             }
         });
+        batch.background.lock().unwrap().push(handle);
     }
 
     /// Handle validation failure by either retrying or marking as permanent failure
-    async fn handle_validation_failure(
+    fn handle_validation_failure(
         &self,
-        task: DownloadTask,
+        slot: TaskSlot,
         _download_result: DownloadResult, // We'll discard this and re-download
         validation_error: DownloadError,
     ) {
+        let (index, retry_count) = (slot.task().original_index, slot.task().retry_count);
         debug!("Handling validation failure for task {}: retry_count={}, max_retries={}",
-               task.original_index, task.retry_count, self.max_retries);
+               index, retry_count, self.max_retries);
 
-        if task.retry_count < self.max_retries {
+        if retry_count < self.max_retries {
             // Re-queue for download retry (validation failure triggers full retry)
-            let retry_task = DownloadTask {
-                retry_count: task.retry_count + 1,
-                ..task
-            };
             warn!("Re-queueing task {} for retry {} due to validation failure",
-                  retry_task.original_index, retry_task.retry_count);
-            self.download_queue.lock().await.push_back(retry_task);
+                  index, retry_count + 1);
+            slot.retry();
         } else {
             // Max retries exceeded - mark as permanent validation failure
             warn!("Task {} failed permanently after {} retries due to validation failure",
-                  task.original_index, task.retry_count);
+                  index, retry_count);
 
             let result = Ok(VerifiedDownloadResult {
                 download_result: DownloadResult::Skipped {
@@ -495,8 +992,28 @@ impl DownloadPipeline {
                 validation_result: ValidationResult::Invalid(validation_error),
             });
 
-            debug!("Inserting final result for task {}: {:?}", task.original_index, result);
-            self.results.lock().await.insert(task.original_index, result);
+            debug!("Inserting final result for task {}: {:?}", index, result);
+            slot.finish(result);
+        }
+    }
+}
+
+/// Journal for a request's destination directory, if the request has a hash to key it by
+fn journal_for(journals: &JournalSet, request: &DownloadRequest) -> Option<Arc<DownloadJournal>> {
+    if request.expected_hash.is_empty() {
+        return None;
+    }
+    journals.journal_for(&request.destination)
+}
+
+/// Journal every request not already verified as queued
+fn record_queued(journals: &JournalSet, requests: &[DownloadRequest]) {
+    for request in requests {
+        if let Some(journal) = journal_for(journals, request)
+            && !journal.is_verified(&request.expected_hash, &request.filename)
+            && let Err(e) = journal.record(request.expected_hash, &request.filename, JournalState::Queued)
+        {
+            warn!("Failed to update download journal for {}: {}", request.filename, e);
         }
     }
 }
@@ -507,11 +1024,10 @@ impl Clone for DownloadPipeline {
         Self {
             download_pool: Arc::clone(&self.download_pool),
             validation_pool: ValidationPool::new(self.config.max_concurrent_validations), // Create new validation pool
-            download_queue: Arc::clone(&self.download_queue),
-            results: Arc::clone(&self.results),
+            limits: Arc::clone(&self.limits),
+            hash_caches: Arc::clone(&self.hash_caches),
             config: self.config.clone(),
             max_retries: self.max_retries,
-            total_tasks: Arc::clone(&self.total_tasks),
             max_concurrent_downloads: self.max_concurrent_downloads,
         }
     }
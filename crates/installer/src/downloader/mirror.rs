@@ -0,0 +1,104 @@
+//! Hash-keyed mirror fallback
+//!
+//! Wabbajack-style mirrors serve archives by their hash rather than by where
+//! they were originally published, so an archive whose source is gone (a dead
+//! URL, a file deleted from Nexus) can still be fetched as long as its hash is
+//! known. [`MirrorResolver`] holds the mirrors to try once the source itself
+//! has failed, after the URL mirrors an [`HttpSource`] may list for its own file.
+//! Either way, the result records the mirror the archive came from.
+
+use tracing::{debug, info};
+
+use crate::downloader::core::{DownloadConfig, DownloadRequest, DownloadResult, ProgressCallback, Result};
+use crate::downloader::sources::HttpSource;
+use crate::hash::Hash;
+
+/// Mirrors serving archives by hash, tried in the order they were added
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorResolver {
+    mirrors: Vec<String>,
+}
+
+impl MirrorResolver {
+    /// Create a resolver without mirrors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mirror serving archives at `{base_url}/{hash}`, with the hash in
+    /// hex as Wabbajack's `ToHex` produces
+    pub fn with_mirror<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.mirrors.push(base_url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Base URLs of the mirrors, in order
+    pub fn mirrors(&self) -> &[String] {
+        &self.mirrors
+    }
+
+    /// True if there are no mirrors to fall back to
+    pub fn is_empty(&self) -> bool {
+        self.mirrors.is_empty()
+    }
+
+    /// URLs the archive with `hash` would be fetched from, in order
+    pub fn urls_for(&self, hash: &Hash) -> Vec<String> {
+        self.mirrors.iter().map(|mirror| format!("{}/{}", mirror, hash.to_hex())).collect()
+    }
+
+    /// Download `request` from the first mirror that serves its hash
+    ///
+    /// The result records the URL it was served from. Returns the last mirror's
+    /// error if none serves it, or `None` if there was nothing to try.
+    pub async fn download(
+        &self,
+        request: &DownloadRequest,
+        progress_callback: Option<ProgressCallback>,
+        config: &DownloadConfig,
+    ) -> Option<Result<DownloadResult>> {
+        if request.expected_hash.is_empty() {
+            return None;
+        }
+
+        let mut last_error = None;
+        for url in self.urls_for(&request.expected_hash) {
+            debug!("Trying mirror {} for {}", url, request.filename);
+            match HttpSource::new(url.clone()).download(request, progress_callback.clone(), config).await {
+                Ok(DownloadResult::Downloaded { size, file_path, hash, .. }) => {
+                    info!("Downloaded {} from mirror {}", request.filename, url);
+                    return Some(Ok(DownloadResult::Downloaded { size, file_path, hash, mirror: Some(url) }));
+                }
+                Ok(DownloadResult::Resumed { size, file_path, hash, .. }) => {
+                    info!("Resumed {} from mirror {}", request.filename, url);
+                    return Some(Ok(DownloadResult::Resumed { size, file_path, hash, mirror: Some(url) }));
+                }
+                Ok(result) => return Some(Ok(result)),
+                Err(e) => {
+                    debug!("Mirror {} failed for {}: {}", url, request.filename, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        last_error.map(Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls_are_keyed_by_hex_hash() {
+        let resolver = MirrorResolver::new()
+            .with_mirror("https://mirror.example.com/")
+            .with_mirror("http://localhost:8080/archives");
+        let hash = Hash::from_base64("eSIyd+KOG3s=").unwrap();
+
+        assert_eq!(resolver.urls_for(&hash), vec![
+            format!("https://mirror.example.com/{}", hash.to_hex()),
+            format!("http://localhost:8080/archives/{}", hash.to_hex()),
+        ]);
+        assert!(MirrorResolver::new().urls_for(&hash).is_empty());
+    }
+}
@@ -6,28 +6,40 @@
 pub mod core;
 pub mod sources;
 pub mod api;
+pub mod control;
+pub(crate) mod scheduler;
+pub mod store;
+pub mod space;
+pub mod plan;
+pub mod mirror;
 pub mod r#lib;
 
 // Re-export main types for convenience
 pub use r#lib::DownloadPipeline;
+pub use control::{BatchController, BatchHandle};
+pub use store::{ArchiveStore, StoredArchive, LinkMethod};
+pub use space::{available_space, check_space};
+pub use mirror::MirrorResolver;
+pub use plan::{plan_downloads, DownloadPlan, PlannedArchive, ArchiveStatus, PlanTotals, DEFAULT_PLAN_SPEED};
 pub use core::{
     DownloadRequest, DownloadResult, DownloadMetadata,
     ProgressCallback, ProgressEvent, ProgressReporter, IntoProgressCallback,
     ConsoleProgressReporter, NullProgressReporter, CompositeProgressReporter,
     FileValidation, ValidationHandle, ValidationPool,
     DownloadError, Result, ErrorSeverity, FileOperation, ValidationType, ErrorContext,
-    DownloadConfig, DownloadMetrics, DownloadMetricsSnapshot, BatchDownloadResult,
+    DownloadConfig, SchedulingStrategy, BandwidthLimiter, NetworkPolicy, IpPreference, DownloadMetrics, DownloadMetricsSnapshot, BatchDownloadResult,
+    DownloadJournal, JournalEntry, JournalState, HashCache,
 };
 
 // Re-export source types
 pub use sources::{
-    DownloadSource, HttpSource, NexusSource, GameFileSource, ManualSource,
+    DownloadSource, SourceKind, HttpSource, NexusSource, GameFileSource, ManualSource,
     ArchiveSource, WabbajackCDNSource, UnknownSource
 };
 
 // Re-export auth types and functions
 pub use api::{NexusAPI, UserValidation, NexusMod, NexusFile, NexusDownloadLink};
-pub use sources::nexus::initialize_nexus_api;
+pub use sources::nexus::{initialize_nexus_api, initialize_nexus_api_with};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,348 @@
+//! Dry-run download planning
+//!
+//! [`plan_downloads`] looks at what is already on disk for a batch and reports
+//! what running it would do, without transferring any data or writing to the
+//! destination: which archives are already valid, which still have to be
+//! hashed, which resume from a `.part` file, which are fetched from which
+//! source, and which need the user. The resulting [`DownloadPlan`] is
+//! serializable, so the CLI and the GUI can show it as is.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::downloader::core::files::create_temp_path;
+use crate::downloader::core::hash_cache::HashCache;
+use crate::downloader::core::journal::DownloadJournal;
+use crate::downloader::core::partial::bytes_written;
+use crate::downloader::core::{DownloadConfig, DownloadRequest};
+use crate::downloader::sources::{DownloadSource, SourceKind};
+
+/// Download speed a plan assumes without a bandwidth limit, in bytes per second
+pub const DEFAULT_PLAN_SPEED: u64 = 10 * 1024 * 1024;
+
+/// What running a batch would do with one archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ArchiveStatus {
+    /// On disk and known to be valid from the journal or the hash cache
+    Valid,
+    /// Held by the archive store, so it is linked instead of downloaded
+    InStore,
+    /// On disk with the expected size, but it has to be hashed before it is trusted
+    Unverified,
+    /// A `.part` file already holds `downloaded` bytes
+    Resumable { downloaded: u64 },
+    /// Has to be downloaded in full
+    Download,
+    /// Has to be downloaded by the user
+    Manual { instructions: String, url: Option<String> },
+    /// No downloader handles the source
+    Unsupported { reason: String },
+}
+
+/// One archive of a [`DownloadPlan`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedArchive {
+    pub filename: String,
+    pub source: SourceKind,
+    /// Expected size in bytes
+    pub size: u64,
+    #[serde(flatten)]
+    pub status: ArchiveStatus,
+}
+
+impl PlannedArchive {
+    /// Bytes this archive still has to transfer
+    pub fn remaining_bytes(&self) -> u64 {
+        match self.status {
+            ArchiveStatus::Download => self.size,
+            ArchiveStatus::Resumable { downloaded } => self.size.saturating_sub(downloaded),
+            _ => 0,
+        }
+    }
+}
+
+/// Number of archives in one category, and their bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanTotals {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+impl PlanTotals {
+    fn add(&mut self, bytes: u64) {
+        self.count += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// What a batch would do, archive by archive and in total
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadPlan {
+    /// Every archive, in request order
+    pub archives: Vec<PlannedArchive>,
+    /// Archives already valid on disk, by size
+    pub valid: PlanTotals,
+    /// Archives linked from the archive store, by size
+    pub in_store: PlanTotals,
+    /// Archives on disk that have to be hashed, by size
+    pub unverified: PlanTotals,
+    /// Archives resumed from a `.part` file, by bytes still to transfer
+    pub resumable: PlanTotals,
+    /// Archives downloaded in full, by size
+    pub download: PlanTotals,
+    /// Archives the user has to download, by size
+    pub manual: PlanTotals,
+    /// Archives no downloader handles, by size
+    pub unsupported: PlanTotals,
+    /// Archives to resume or download per source, by bytes still to transfer
+    pub by_source: BTreeMap<SourceKind, PlanTotals>,
+    /// Bytes still to transfer over the network; game files are copied locally and not counted
+    pub bytes_to_download: u64,
+    /// Bytes copied from the game directory
+    pub bytes_to_copy: u64,
+    /// Nexus API requests the batch would make: one to validate the API key,
+    /// plus one download link per Nexus archive to resume or download
+    pub nexus_api_calls: usize,
+    /// Download speed the estimate assumes, in bytes per second
+    pub assumed_speed: Option<u64>,
+    /// Estimated time to transfer [`bytes_to_download`](Self::bytes_to_download) at the assumed speed
+    pub estimated_seconds: Option<u64>,
+}
+
+impl DownloadPlan {
+    /// Build a plan and its totals from classified archives
+    pub fn from_archives(archives: Vec<PlannedArchive>) -> Self {
+        let mut plan = Self::default();
+        let mut nexus_links = 0;
+        for archive in &archives {
+            let remaining = archive.remaining_bytes();
+            match archive.status {
+                ArchiveStatus::Valid => plan.valid.add(archive.size),
+                ArchiveStatus::InStore => plan.in_store.add(archive.size),
+                ArchiveStatus::Unverified => plan.unverified.add(archive.size),
+                ArchiveStatus::Resumable { .. } => plan.resumable.add(remaining),
+                ArchiveStatus::Download => plan.download.add(remaining),
+                ArchiveStatus::Manual { .. } => plan.manual.add(archive.size),
+                ArchiveStatus::Unsupported { .. } => plan.unsupported.add(archive.size),
+            }
+            if !matches!(archive.status, ArchiveStatus::Resumable { .. } | ArchiveStatus::Download) {
+                continue;
+            }
+
+            plan.by_source.entry(archive.source).or_default().add(remaining);
+            match archive.source {
+                SourceKind::GameFile => plan.bytes_to_copy += remaining,
+                SourceKind::Nexus => {
+                    nexus_links += 1;
+                    plan.bytes_to_download += remaining;
+                }
+                _ => plan.bytes_to_download += remaining,
+            }
+        }
+        if nexus_links > 0 {
+            plan.nexus_api_calls = nexus_links + 1;
+        }
+        plan.archives = archives;
+        plan
+    }
+
+    /// Estimate the transfer time at `bytes_per_second`
+    pub fn with_speed(mut self, bytes_per_second: u64) -> Self {
+        self.assumed_speed = Some(bytes_per_second);
+        self.estimated_seconds = (bytes_per_second > 0)
+            .then(|| self.bytes_to_download.div_ceil(bytes_per_second));
+        self
+    }
+
+    /// True if some archives cannot be downloaded without the user
+    pub fn needs_user_action(&self) -> bool {
+        self.manual.count > 0 || self.unsupported.count > 0
+    }
+}
+
+/// Classify every request the way a batch with `config` would handle it
+///
+/// Nothing is downloaded, hashed or written: journals and hash caches are only
+/// read, and files without a cached hash are reported as unverified.
+///
+/// The transfer time is estimated at the bandwidth limit of `config`, or at
+/// [`DEFAULT_PLAN_SPEED`] without one; [`DownloadPlan::with_speed`] replaces
+/// the estimate with one at a known speed.
+pub async fn plan_downloads(requests: &[DownloadRequest], config: &DownloadConfig) -> DownloadPlan {
+    let mut lookups = Lookups::default();
+    let mut archives = Vec::with_capacity(requests.len());
+    for request in requests {
+        let status = classify(request, config, &mut lookups).await;
+        archives.push(PlannedArchive {
+            filename: request.filename.clone(),
+            source: request.source.kind(),
+            size: request.expected_size,
+            status,
+        });
+    }
+
+    DownloadPlan::from_archives(archives)
+        .with_speed(config.bandwidth_limiter.limit().unwrap_or(DEFAULT_PLAN_SPEED))
+}
+
+async fn classify(request: &DownloadRequest, config: &DownloadConfig, lookups: &mut Lookups) -> ArchiveStatus {
+    let hash = &request.expected_hash;
+    let dest_path = request.destination.join(&request.filename);
+
+    if config.use_journal
+        && !hash.is_empty()
+        && lookups.journal(&request.destination).is_some_and(|journal| journal.is_verified(hash, &request.filename))
+    {
+        return ArchiveStatus::Valid;
+    }
+    if !hash.is_empty() && config.archive_store.as_ref().is_some_and(|store| store.contains(hash)) {
+        return ArchiveStatus::InStore;
+    }
+
+    if let Ok(metadata) = tokio::fs::metadata(&dest_path).await
+        && metadata.len() == request.expected_size
+    {
+        if hash.is_empty() {
+            return ArchiveStatus::Valid;
+        }
+        let cached = if config.use_hash_cache {
+            lookups.hash_cache(&request.destination).and_then(|cache| cache.get(&dest_path, &metadata))
+        } else {
+            None
+        };
+        match cached {
+            Some(cached) if cached == *hash => return ArchiveStatus::Valid,
+            // A file known to have the wrong contents is downloaded again
+            Some(_) => {}
+            None => return ArchiveStatus::Unverified,
+        }
+    }
+
+    match &request.source {
+        DownloadSource::Manual(manual) => {
+            return ArchiveStatus::Manual {
+                instructions: manual.instructions.clone(),
+                url: manual.url.clone(),
+            };
+        }
+        DownloadSource::Archive(_) => {
+            return ArchiveStatus::Unsupported {
+                reason: "Archive extraction is not implemented".to_string(),
+            };
+        }
+        DownloadSource::Unknown(unknown) => {
+            return ArchiveStatus::Unsupported {
+                reason: format!("Unsupported downloader: '{}'", unknown.downloader_name()),
+            };
+        }
+        _ => {}
+    }
+
+    if config.allow_resume
+        && let Some(downloaded) = bytes_written(&create_temp_path(&dest_path)).await
+    {
+        return ArchiveStatus::Resumable { downloaded };
+    }
+    ArchiveStatus::Download
+}
+
+/// Journals and hash caches of the destination directories, loaded read-only
+/// so planning never creates, compacts or appends to them
+#[derive(Default)]
+struct Lookups {
+    journals: HashMap<PathBuf, Option<DownloadJournal>>,
+    hash_caches: HashMap<PathBuf, Option<HashCache>>,
+}
+
+impl Lookups {
+    fn journal(&mut self, directory: &Path) -> Option<&DownloadJournal> {
+        self.journals
+            .entry(directory.to_path_buf())
+            .or_insert_with(|| DownloadJournal::load(directory).ok())
+            .as_ref()
+    }
+
+    fn hash_cache(&mut self, directory: &Path) -> Option<&HashCache> {
+        self.hash_caches
+            .entry(directory.to_path_buf())
+            .or_insert_with(|| HashCache::load(directory).ok())
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::core::journal::JOURNAL_FILE_NAME;
+    use crate::downloader::sources::{ManualSource, NexusSource, UnknownSource};
+    use crate::hash::Hash;
+
+    #[tokio::test]
+    async fn test_plan_classifies_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let http = |filename: &str, content: &[u8]| {
+            DownloadRequest::new_http("http://example.com/file", dir.path(), filename, 1000, Hash::of_bytes(content))
+        };
+
+        // Cached with the expected hash
+        let valid = b"v".repeat(1000);
+        std::fs::write(dir.path().join("valid.7z"), &valid).unwrap();
+        let cache = HashCache::open(dir.path()).unwrap();
+        let path = dir.path().join("valid.7z");
+        cache.insert(&path, &std::fs::metadata(&path).unwrap(), Hash::of_bytes(&valid)).unwrap();
+        drop(cache);
+
+        std::fs::write(dir.path().join("unverified.7z"), vec![0; 1000]).unwrap();
+        std::fs::write(dir.path().join("partial.part"), vec![0; 400]).unwrap();
+        // A file of the wrong size is downloaded again
+        std::fs::write(dir.path().join("truncated.7z"), vec![0; 10]).unwrap();
+
+        let mut manual = http("manual.7z", b"manual");
+        manual.source = DownloadSource::Manual(ManualSource::new("Log in first").with_url("https://example.com"));
+        let mut unknown = http("unknown.7z", b"unknown");
+        unknown.source = DownloadSource::Unknown(UnknownSource::new("MegaDownloader+State, Wabbajack.Lib", None, None));
+        let mut nexus = http("nexus.7z", b"nexus");
+        nexus.source = DownloadSource::Nexus(NexusSource::new(1, 2, "skyrimspecialedition".to_string()));
+
+        let requests = vec![
+            http("valid.7z", &valid),
+            http("unverified.7z", b"unverified"),
+            http("partial.7z", b"partial"),
+            http("truncated.7z", b"truncated"),
+            manual,
+            unknown,
+            nexus,
+        ];
+        let config = DownloadConfig::default().with_journal().with_hash_cache().with_bandwidth_limit(100);
+        let plan = plan_downloads(&requests, &config).await;
+
+        let statuses: Vec<_> = plan.archives.iter().map(|archive| archive.status.clone()).collect();
+        assert_eq!(statuses, vec![
+            ArchiveStatus::Valid,
+            ArchiveStatus::Unverified,
+            ArchiveStatus::Resumable { downloaded: 400 },
+            ArchiveStatus::Download,
+            ArchiveStatus::Manual { instructions: "Log in first".to_string(), url: Some("https://example.com".to_string()) },
+            ArchiveStatus::Unsupported { reason: "Unsupported downloader: 'MegaDownloader+State'".to_string() },
+            ArchiveStatus::Download,
+        ]);
+
+        assert_eq!(plan.resumable, PlanTotals { count: 1, bytes: 600 });
+        assert_eq!(plan.download, PlanTotals { count: 2, bytes: 2000 });
+        assert_eq!(plan.by_source[&SourceKind::Http], PlanTotals { count: 2, bytes: 1600 });
+        assert_eq!(plan.bytes_to_download, 2600);
+        assert_eq!(plan.nexus_api_calls, 2);
+        assert_eq!(plan.estimated_seconds, Some(26));
+        assert!(plan.needs_user_action());
+
+        // Planning must not create anything in the destination
+        assert!(!dir.path().join(JOURNAL_FILE_NAME).exists());
+
+        // Without a bandwidth limit the estimate assumes the default speed
+        let plan = plan_downloads(&requests, &DownloadConfig::default()).await;
+        assert_eq!(plan.assumed_speed, Some(DEFAULT_PLAN_SPEED));
+        assert_eq!(plan.estimated_seconds, Some(1));
+    }
+}
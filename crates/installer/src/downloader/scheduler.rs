@@ -0,0 +1,362 @@
+//! Task scheduling for the download pipeline
+//!
+//! [`TaskQueue`] decides which queued task a free download worker picks up
+//! next: lowest effective priority first, with ties broken by the configured
+//! [`SchedulingStrategy`] and finally by submission order. Tasks whose source
+//! or host is at its [`ConcurrencyLimits`] are skipped until a slot frees up.
+//! Tasks are grouped by the slots they need, so a blocked source is skipped
+//! without looking at each of its tasks.
+
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::downloader::core::{DownloadConfig, DownloadSource, SchedulingStrategy};
+use crate::downloader::lib::DownloadTask;
+use crate::downloader::sources::SourceKind;
+
+/// Key identifying where a download comes from, used to spread load across sources
+pub(crate) fn source_key(source: &DownloadSource) -> String {
+    match source.host() {
+        Some(host) => format!("http:{}", host),
+        None => format!("{:?}", source.kind()),
+    }
+}
+
+/// Per-source-kind and per-host concurrency limits, shared by all batches of a pipeline
+pub(crate) struct ConcurrencyLimits {
+    by_kind: HashMap<SourceKind, Arc<Semaphore>>,
+    host_limits: HashMap<String, usize>,
+    default_host_limit: Option<usize>,
+    /// Host semaphores, created the first time a host is seen
+    by_host: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// Slots held by a running download; released on drop
+pub(crate) struct SourcePermits {
+    _kind: Option<OwnedSemaphorePermit>,
+    _host: Option<OwnedSemaphorePermit>,
+}
+
+impl ConcurrencyLimits {
+    pub(crate) fn from_config(config: &DownloadConfig) -> Self {
+        Self {
+            by_kind: config.source_limits.iter()
+                .map(|(kind, limit)| (*kind, Arc::new(Semaphore::new((*limit).max(1)))))
+                .collect(),
+            host_limits: config.host_limits.iter()
+                .map(|(host, limit)| (host.to_lowercase(), *limit))
+                .collect(),
+            default_host_limit: config.default_host_limit,
+            by_host: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Claim a slot for the source's kind and host, or `None` if either is at its limit
+    pub(crate) fn try_acquire(&self, source: &DownloadSource) -> Option<SourcePermits> {
+        let kind = match self.by_kind.get(&source.kind()) {
+            Some(semaphore) => Some(Arc::clone(semaphore).try_acquire_owned().ok()?),
+            None => None,
+        };
+
+        let host = match source.host().and_then(|host| self.host_semaphore(host)) {
+            Some(semaphore) => Some(semaphore.try_acquire_owned().ok()?),
+            None => None,
+        };
+
+        Some(SourcePermits { _kind: kind, _host: host })
+    }
+
+    fn host_semaphore(&self, host: String) -> Option<Arc<Semaphore>> {
+        let limit = self.host_limits.get(&host).copied().or(self.default_host_limit)?;
+        let mut by_host = self.by_host.lock().unwrap();
+        Some(Arc::clone(by_host.entry(host).or_insert_with(|| Arc::new(Semaphore::new(limit.max(1))))))
+    }
+}
+
+/// Tasks with the same source kind and host, which always compete for the same slots
+type SlotGroup = (SourceKind, Option<String>);
+
+/// Rank of a queued task: effective priority, strategy tie-breaker and submission order
+type Rank = (u64, u64, u64);
+
+struct QueuedTask {
+    /// Rank within its group; round-robin scheduling leaves the tie-breaker at
+    /// zero and fills in the source's served count when comparing groups
+    rank: Rank,
+    source: String,
+    task: DownloadTask,
+}
+
+impl PartialEq for QueuedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank
+    }
+}
+
+impl Eq for QueuedTask {}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedTask {
+    /// Reversed, so the lowest rank is at the top of a [`BinaryHeap`]
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.rank.cmp(&self.rank)
+    }
+}
+
+/// Priority queue of download tasks
+///
+/// Tasks are kept in one heap per [`SlotGroup`]. Picking a task compares only
+/// the head of each group, and a group whose head cannot get a slot is
+/// skipped as a whole.
+pub(crate) struct TaskQueue {
+    strategy: SchedulingStrategy,
+    retry_penalty: u32,
+    groups: HashMap<SlotGroup, BinaryHeap<QueuedTask>>,
+    len: usize,
+    next_seq: u64,
+    /// Tasks handed out per source, for round-robin scheduling
+    served: HashMap<String, u64>,
+}
+
+impl TaskQueue {
+    pub(crate) fn new(strategy: SchedulingStrategy, retry_penalty: u32) -> Self {
+        Self {
+            strategy,
+            retry_penalty,
+            groups: HashMap::new(),
+            len: 0,
+            next_seq: 0,
+            served: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, task: DownloadTask) {
+        let source = source_key(&task.request.source);
+        let group = (task.request.source.kind(), task.request.source.host());
+        let rank = self.rank(&task, self.next_seq);
+        self.groups.entry(group).or_default().push(QueuedTask { rank, source, task });
+        self.next_seq += 1;
+        self.len += 1;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Take the highest-ranked task whose source and host have a free slot
+    pub(crate) fn pop_available(&mut self, limits: &ConcurrencyLimits) -> Option<(DownloadTask, SourcePermits)> {
+        let mut heads: Vec<(&SlotGroup, &QueuedTask, Rank)> = self.groups.iter()
+            .filter_map(|(group, heap)| heap.peek().map(|head| (group, head, self.current_rank(head))))
+            .collect();
+        heads.sort_unstable_by_key(|&(_, _, rank)| rank);
+
+        let (group, permits) = heads.into_iter().find_map(|(group, head, _)| {
+            limits.try_acquire(&head.task.request.source).map(|permits| (group.clone(), permits))
+        })?;
+        let queued = self.groups.get_mut(&group)?.pop()?;
+        Some((self.take(&group, queued), permits))
+    }
+
+    /// Take any task, regardless of scheduling order and slots
+    pub(crate) fn pop_any(&mut self) -> Option<DownloadTask> {
+        let group = self.groups.keys().next()?.clone();
+        let queued = self.groups.get_mut(&group)?.pop()?;
+        Some(self.take(&group, queued))
+    }
+
+    /// Take every task matching `predicate` in one pass over the queue
+    pub(crate) fn remove_all(&mut self, mut predicate: impl FnMut(&DownloadTask) -> bool) -> Vec<DownloadTask> {
+        let groups: Vec<SlotGroup> = self.groups.iter()
+            .filter(|(_, heap)| heap.iter().any(|queued| predicate(&queued.task)))
+            .map(|(group, _)| group.clone())
+            .collect();
+
+        let mut removed = Vec::new();
+        for group in groups {
+            let Some(heap) = self.groups.get_mut(&group) else {
+                continue;
+            };
+            let (matching, kept): (Vec<_>, Vec<_>) = std::mem::take(heap).into_vec()
+                .into_iter()
+                .partition(|queued| predicate(&queued.task));
+            *heap = kept.into();
+            for queued in matching {
+                removed.push(self.take(&group, queued));
+            }
+        }
+        removed
+    }
+
+    /// Account for a task removed from `group`
+    fn take(&mut self, group: &SlotGroup, queued: QueuedTask) -> DownloadTask {
+        if self.groups.get(group).is_some_and(BinaryHeap::is_empty) {
+            self.groups.remove(group);
+        }
+        self.len -= 1;
+        *self.served.entry(queued.source).or_default() += 1;
+        queued.task
+    }
+
+    fn rank(&self, task: &DownloadTask, seq: u64) -> Rank {
+        let request = &task.request;
+        let priority = request.priority as u64
+            + task.retry_count as u64 * self.retry_penalty as u64;
+
+        let tie_breaker = match self.strategy {
+            SchedulingStrategy::Fifo | SchedulingStrategy::RoundRobinBySource => 0,
+            SchedulingStrategy::SmallestFirst => request.expected_size,
+            SchedulingStrategy::LargestFirst => u64::MAX - request.expected_size,
+        };
+
+        (priority, tie_breaker, seq)
+    }
+
+    /// Rank of a group's head among all groups, which for round-robin depends
+    /// on how many tasks its source has been handed so far
+    fn current_rank(&self, queued: &QueuedTask) -> Rank {
+        let (priority, tie_breaker, seq) = queued.rank;
+        match self.strategy {
+            SchedulingStrategy::RoundRobinBySource => {
+                (priority, self.served.get(&queued.source).copied().unwrap_or(0), seq)
+            }
+            _ => (priority, tie_breaker, seq),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::DownloadRequest;
+    use crate::hash::Hash;
+
+    fn task(index: usize, url: &str, size: u64, priority: u32) -> DownloadTask {
+        DownloadTask {
+            request: DownloadRequest::new_http(url, "/tmp", format!("file-{}", index), size, Hash::EMPTY)
+                .with_priority(priority),
+            retry_count: 0,
+            original_index: index,
+        }
+    }
+
+    fn pop(queue: &mut TaskQueue) -> Option<DownloadTask> {
+        let unlimited = ConcurrencyLimits::from_config(&DownloadConfig::default());
+        queue.pop_available(&unlimited).map(|(task, _)| task)
+    }
+
+    fn drain(mut queue: TaskQueue) -> Vec<usize> {
+        std::iter::from_fn(|| pop(&mut queue)).map(|t| t.original_index).collect()
+    }
+
+    fn queue_with(strategy: SchedulingStrategy, tasks: Vec<DownloadTask>) -> TaskQueue {
+        let mut queue = TaskQueue::new(strategy, 1);
+        for task in tasks {
+            queue.push(task);
+        }
+        queue
+    }
+
+    #[test]
+    fn test_priority_then_strategy() {
+        let tasks = || vec![
+            task(0, "https://a.com/0", 30, 1),
+            task(1, "https://a.com/1", 10, 1),
+            task(2, "https://a.com/2", 20, 1),
+            task(3, "https://a.com/3", 99, 0),
+        ];
+
+        assert_eq!(drain(queue_with(SchedulingStrategy::Fifo, tasks())), vec![3, 0, 1, 2]);
+        assert_eq!(drain(queue_with(SchedulingStrategy::SmallestFirst, tasks())), vec![3, 1, 2, 0]);
+        assert_eq!(drain(queue_with(SchedulingStrategy::LargestFirst, tasks())), vec![3, 0, 2, 1]);
+    }
+
+    #[test]
+    fn test_order_spans_sources() {
+        let tasks = || vec![
+            task(0, "https://a.com/0", 30, 1),
+            task(1, "https://b.com/1", 10, 0),
+            task(2, "https://a.com/2", 20, 0),
+            task(3, "https://c.com/3", 40, 1),
+        ];
+
+        assert_eq!(drain(queue_with(SchedulingStrategy::Fifo, tasks())), vec![1, 2, 0, 3]);
+        assert_eq!(drain(queue_with(SchedulingStrategy::LargestFirst, tasks())), vec![2, 1, 3, 0]);
+    }
+
+    #[test]
+    fn test_round_robin_by_source() {
+        let queue = queue_with(SchedulingStrategy::RoundRobinBySource, vec![
+            task(0, "https://a.com/0", 1, 0),
+            task(1, "https://a.com/1", 1, 0),
+            task(2, "https://a.com/2", 1, 0),
+            task(3, "https://b.com/3", 1, 0),
+            task(4, "https://b.com/4", 1, 0),
+        ]);
+
+        assert_eq!(drain(queue), vec![0, 3, 1, 4, 2]);
+    }
+
+    #[test]
+    fn test_concurrency_limits() {
+        let config = DownloadConfig::default()
+            .with_host_limit("A.com", 1)
+            .with_source_limit(SourceKind::Nexus, 2);
+        let limits = ConcurrencyLimits::from_config(&config);
+        let mut queue = queue_with(SchedulingStrategy::Fifo, vec![
+            task(0, "https://a.com/0", 1, 0),
+            task(1, "https://a.com/1", 1, 0),
+            task(2, "https://b.com/2", 1, 0),
+        ]);
+
+        let (first, permit) = queue.pop_available(&limits).unwrap();
+        assert_eq!(first.original_index, 0);
+
+        // a.com is busy, so b.com goes next and then nothing is available
+        let (second, _other) = queue.pop_available(&limits).unwrap();
+        assert_eq!(second.original_index, 2);
+        assert!(queue.pop_available(&limits).is_none());
+
+        drop(permit);
+        assert_eq!(queue.pop_available(&limits).unwrap().0.original_index, 1);
+    }
+
+    #[test]
+    fn test_retries_are_deprioritized() {
+        let mut queue = queue_with(SchedulingStrategy::Fifo, vec![
+            task(0, "https://a.com/0", 1, 0),
+            task(1, "https://a.com/1", 1, 2),
+        ]);
+
+        let mut retried = pop(&mut queue).unwrap();
+        retried.retry_count = 1;
+        queue.push(retried);
+        queue.push(task(2, "https://a.com/2", 1, 0));
+
+        // The retry (effective priority 1) goes after fresh work but before lower-priority tasks
+        assert_eq!(drain(queue), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_remove_all_keeps_order_of_the_rest() {
+        let mut queue = queue_with(SchedulingStrategy::Fifo, (0..6)
+            .map(|i| task(i, &format!("https://{}.com/{}", if i % 2 == 0 { "a" } else { "b" }, i), 1, 0))
+            .collect());
+
+        let mut removed: Vec<usize> = queue.remove_all(|t| t.original_index % 3 == 0).iter().map(|t| t.original_index).collect();
+        removed.sort();
+        assert_eq!(removed, vec![0, 3]);
+        assert_eq!(queue.len(), 4);
+
+        assert!(queue.pop_any().is_some());
+        assert_eq!(queue.len(), 3);
+        let rest = drain(queue);
+        assert_eq!(rest.len(), 3);
+        assert!(rest.is_sorted());
+    }
+}
@@ -4,15 +4,16 @@ use std::path::Path;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use tracing::debug;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use crate::hash::Hash;
 
 use crate::downloader::core::{
     DownloadRequest, DownloadResult, ProgressCallback, Result,
-    DownloadError, ProgressEvent, files::check_existing_file
+    DownloadError, ProgressEvent, BandwidthLimiter, files::check_existing_file
 };
 
 /// Raw GameFile archive state from JSON parsing
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GameFileArchiveState {
     #[serde(rename = "Game")]
     pub game: String,
@@ -21,7 +22,7 @@ pub struct GameFileArchiveState {
     #[serde(rename = "GameVersion")]
     pub game_version: String,
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
 }
 
 /// Game file copy source
@@ -33,6 +34,8 @@ pub struct GameFileSource {
     pub file_path: String,
     /// Expected game version
     pub game_version: String,
+    /// Hash of the game file as recorded in the modlist
+    pub hash: Hash,
 }
 
 impl GameFileSource {
@@ -40,7 +43,7 @@ impl GameFileSource {
         &self,
         request: &DownloadRequest,
         progress_callback: Option<ProgressCallback>,
-        _config: &crate::downloader::core::config::DownloadConfig,
+        config: &crate::downloader::core::config::DownloadConfig,
     ) -> Result<DownloadResult> {
         let filename = request.get_filename()?;
         let dest_path = request.destination.join(&filename);
@@ -67,12 +70,14 @@ impl GameFileSource {
         }
 
         // Copy the file
-        let size = self.copy_file_with_progress(&source_path, &dest_path, progress_callback.clone()).await?;
+        let size = self.copy_file_with_progress(&source_path, &dest_path, progress_callback.clone(), &config.bandwidth_limiter).await?;
 
         // Return result with file path for centralized validation
         Ok(DownloadResult::Downloaded {
             size,
-            file_path: dest_path
+            file_path: dest_path,
+            hash: None,
+            mirror: None,
         })
     }
 
@@ -135,7 +140,7 @@ impl GameFileSource {
         #[cfg(unix)]
         {
             if let Ok(home) = std::env::var("HOME") {
-                paths.push(std::path::PathBuf::from(home).join(".steam").join("steam"));
+                paths.push(std::path::PathBuf::from(&home).join(".steam").join("steam"));
                 paths.push(std::path::PathBuf::from(home).join(".local").join("share").join("Steam"));
             }
         }
@@ -197,12 +202,13 @@ impl GameFileSource {
         ))
     }
 
-    /// Copy a file from source to destination with progress reporting
+    /// Copy a file from source to destination with progress reporting and bandwidth throttling
     async fn copy_file_with_progress(
         &self,
         source_path: &Path,
         dest_path: &Path,
         progress_callback: Option<ProgressCallback>,
+        limiter: &BandwidthLimiter,
     ) -> Result<u64> {
         // Get source file size
         let source_metadata = fs::metadata(source_path).await?;
@@ -241,6 +247,7 @@ impl GameFileSource {
                 break; // EOF
             }
 
+            limiter.consume(bytes_read).await;
             dest_file.write_all(&buffer[..bytes_read]).await?;
             copied += bytes_read as u64;
 
@@ -292,7 +299,13 @@ impl GameFileSource {
             game: game.into(),
             file_path: file_path.into(),
             game_version: game_version.into(),
+            hash: Hash::EMPTY,
         }
     }
+
+    pub fn with_hash(mut self, hash: Hash) -> Self {
+        self.hash = hash;
+        self
+    }
 }
 
@@ -2,8 +2,8 @@
 
 use std::collections::HashMap;
 use std::path::Path;
-use tracing::debug;
-use serde::Deserialize;
+use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
 
 use crate::downloader::core::{
     DownloadRequest, DownloadResult, ProgressCallback, Result,
@@ -11,9 +11,10 @@ use crate::downloader::core::{
 };
 use crate::downloader::core::http::HttpClient;
 use crate::downloader::core::files::check_existing_file;
+use crate::hash::Hash;
 
 /// Raw HTTP archive state from JSON parsing
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HttpArchiveState {
     #[serde(rename = "Url")]
     pub url: String,
@@ -50,12 +51,19 @@ impl HttpSource {
         }
 
         // Download the file using centralized logic
-        let size = self.download_with_mirrors(&dest_path, progress_callback.clone(), Some(request.expected_size), config).await?;
+        let ((size, hash), mirror) = self.download_with_mirrors(
+            &dest_path,
+            progress_callback.clone(),
+            Some(request.expected_size),
+            config,
+        ).await?;
 
         // Return result with file path for centralized validation
         Ok(DownloadResult::Downloaded {
             size,
-            file_path: dest_path
+            file_path: dest_path,
+            hash,
+            mirror,
         })
     }
 
@@ -63,13 +71,16 @@ impl HttpSource {
 
 impl HttpSource {
     /// Download with mirror fallback support
+    ///
+    /// Returns the size and streamed hash and, if the primary URL failed, the
+    /// mirror URL that served the file.
     async fn download_with_mirrors(
         &self,
         dest_path: &Path,
         progress_callback: Option<ProgressCallback>,
         expected_size: Option<u64>,
         config: &crate::downloader::core::config::DownloadConfig,
-    ) -> Result<u64> {
+    ) -> Result<((u64, Option<Hash>), Option<String>)> {
         debug!("Download: {} to {}", self.url, dest_path.display());
 
         // Create HTTP client with appropriate timeout
@@ -82,7 +93,7 @@ impl HttpSource {
         let primary_result = http_client.download_with_retry(&self.url, dest_path, expected_size, progress_callback.clone(), config).await;
 
         match primary_result {
-            Ok(size) => return Ok(size),
+            Ok(downloaded) => Ok((downloaded, None)),
             Err(e) => {
                 // Report warning through progress callback
                 if let Some(ref callback) = progress_callback {
@@ -98,7 +109,10 @@ impl HttpSource {
                     let mirror_result = http_client.download_with_retry(mirror_url, dest_path, expected_size, progress_callback.clone(), config).await;
 
                     match mirror_result {
-                        Ok(size) => return Ok(size),
+                        Ok(downloaded) => {
+                            info!("Downloaded {} from mirror {}", dest_path.display(), mirror_url);
+                            return Ok((downloaded, Some(mirror_url.clone())));
+                        }
                         Err(mirror_error) => {
                             // Report mirror failure warning
                             if let Some(ref callback) = progress_callback {
@@ -112,7 +126,7 @@ impl HttpSource {
                     }
                 }
                 // If all URLs failed, return the original error
-                return Err(e);
+                Err(e)
             }
         }
     }
@@ -3,7 +3,7 @@
 //! This module contains individual download source types and their implementations.
 //! Each source type is defined in its own file along with its implementation.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 // Individual source type modules
 pub mod unknown;
@@ -48,8 +48,44 @@ pub enum DownloadSource {
     Unknown(UnknownSource),
 }
 
+/// The kind of a [`DownloadSource`], without its details
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SourceKind {
+    Http,
+    Nexus,
+    GameFile,
+    Manual,
+    Archive,
+    WabbajackCDN,
+    Unknown,
+}
 
 impl DownloadSource {
+    /// The kind of this source
+    pub fn kind(&self) -> SourceKind {
+        match self {
+            DownloadSource::Http(_) => SourceKind::Http,
+            DownloadSource::Nexus(_) => SourceKind::Nexus,
+            DownloadSource::GameFile(_) => SourceKind::GameFile,
+            DownloadSource::Manual(_) => SourceKind::Manual,
+            DownloadSource::Archive(_) => SourceKind::Archive,
+            DownloadSource::WabbajackCDN(_) => SourceKind::WabbajackCDN,
+            DownloadSource::Unknown(_) => SourceKind::Unknown,
+        }
+    }
+
+    /// Lowercase hostname this source downloads from, if known up front
+    ///
+    /// Nexus downloads resolve their CDN host at download time, so they have none.
+    pub fn host(&self) -> Option<String> {
+        let url = match self {
+            DownloadSource::Http(http) => &http.url,
+            DownloadSource::WabbajackCDN(cdn) => &cdn.url,
+            _ => return None,
+        };
+        url::Url::parse(url).ok()?.host_str().map(str::to_lowercase)
+    }
+
     /// Get a human-readable description of this download source
     pub fn description(&self) -> String {
         match self {
@@ -69,7 +105,7 @@ impl DownloadSource {
                 format!("WabbajackCDN download from {}", wabbajack_cdn.url)
             },
             DownloadSource::Unknown(unknown) => {
-                format!("Unknown download source: {}", unknown.source_type)
+                format!("Unknown download source: {}", unknown.downloader_name())
             },
         }
     }
@@ -118,7 +154,7 @@ impl From<crate::parse_wabbajack::parser::ArchiveState> for DownloadSource {
 
             ArchiveState::Nexus(nexus_state) => {
                 let author_str = nexus_state.author.as_deref().unwrap_or("Unknown").to_string();
-                let nexus_source = NexusSource::new(nexus_state.mod_id, nexus_state.file_id, nexus_state.game_name)
+                let mut nexus_source = NexusSource::new(nexus_state.mod_id, nexus_state.file_id, nexus_state.game_name)
                     .with_metadata(
                         nexus_state.name,
                         author_str,
@@ -126,12 +162,14 @@ impl From<crate::parse_wabbajack::parser::ArchiveState> for DownloadSource {
                         nexus_state.description,
                         nexus_state.is_nsfw
                     );
+                nexus_source.image_url = nexus_state.image_url;
 
                 DownloadSource::Nexus(nexus_source)
             },
 
             ArchiveState::GameFile(gamefile_state) => {
-                let gamefile_source = GameFileSource::new(&gamefile_state.game, &gamefile_state.game_file, &gamefile_state.game_version);
+                let gamefile_source = GameFileSource::new(&gamefile_state.game, &gamefile_state.game_file, &gamefile_state.game_version)
+                    .with_hash(gamefile_state.hash);
                 DownloadSource::GameFile(gamefile_source)
             },
 
@@ -140,14 +178,83 @@ impl From<crate::parse_wabbajack::parser::ArchiveState> for DownloadSource {
                 DownloadSource::WabbajackCDN(wabbajack_cdn_source)
             },
 
-            ArchiveState::Unknown => {
-                let unknown_source = UnknownSource::new(
-                    "Unknown Downloader Type".to_string(),
-                    None, // Archive name would need to be passed in context
-                    None, // Meta would need to be passed in context
-                );
+            ArchiveState::Unknown { type_name, raw } => {
+                // Archive name and meta are filled in by the enclosing Archive
+                let unknown_source = UnknownSource::new(type_name, None, None)
+                    .with_raw_state(raw);
                 DownloadSource::Unknown(unknown_source)
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Convert a download source back into the modlist state it came from
+///
+/// Used when writing modlists. Archive extraction sources have no modlist
+/// representation and are rejected.
+impl TryFrom<&DownloadSource> for crate::parse_wabbajack::parser::ArchiveState {
+    type Error = crate::parse_wabbajack::parser::ParseError;
+
+    fn try_from(source: &DownloadSource) -> std::result::Result<Self, Self::Error> {
+        use crate::parse_wabbajack::parser::{ArchiveState, ParseError};
+
+        let state = match source {
+            DownloadSource::Http(http) => {
+                // Sort headers so the output is stable across runs
+                let mut headers: Vec<String> = http.headers.iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                headers.sort();
+                ArchiveState::Http(HttpArchiveState { url: http.url.clone(), headers })
+            },
+            DownloadSource::Nexus(nexus) => ArchiveState::Nexus(NexusArchiveState {
+                mod_id: nexus.mod_id,
+                file_id: nexus.file_id,
+                game_name: nexus.game_name.clone(),
+                name: nexus.mod_name.clone(),
+                author: Some(nexus.author.clone()),
+                version: nexus.version.clone(),
+                description: nexus.description.clone(),
+                is_nsfw: nexus.is_nsfw,
+                image_url: nexus.image_url.clone(),
+            }),
+            DownloadSource::GameFile(game) => ArchiveState::GameFile(GameFileArchiveState {
+                game: game.game.clone(),
+                game_file: game.file_path.clone(),
+                game_version: game.game_version.clone(),
+                hash: game.hash,
+            }),
+            DownloadSource::WabbajackCDN(cdn) => ArchiveState::WabbajackCDN(WabbajackCDNArchiveState {
+                url: cdn.url.clone(),
+            }),
+            DownloadSource::Manual(manual) => ArchiveState::Unknown {
+                type_name: ArchiveState::MANUAL_TYPE.to_string(),
+                raw: serde_json::json!({
+                    "$type": ArchiveState::MANUAL_TYPE,
+                    "Prompt": manual.instructions,
+                    "Url": manual.url,
+                }),
+            },
+            DownloadSource::Unknown(unknown) => ArchiveState::Unknown {
+                type_name: unknown.source_type.clone(),
+                raw: unknown.raw_state.clone()
+                    .unwrap_or_else(|| serde_json::json!({ "$type": unknown.source_type })),
+            },
+            DownloadSource::Archive(_) => {
+                return Err(ParseError::UnsupportedDownloaderType(
+                    "archive extraction sources cannot be written to a modlist".to_string(),
+                ));
+            },
+        };
+
+        Ok(state)
+    }
+}
+
+impl Serialize for DownloadSource {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let state = crate::parse_wabbajack::parser::ArchiveState::try_from(self)
+            .map_err(serde::ser::Error::custom)?;
+        state.serialize(serializer)
+    }
+}
@@ -2,18 +2,18 @@
 
 use tracing::{debug, info};
 use once_cell::sync::OnceCell;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::downloader::api::nexus_api::NexusAPI;
 use crate::downloader::core::{
     DownloadRequest, DownloadResult, ProgressCallback, Result,
-    DownloadError, ProgressEvent
+    DownloadError, ProgressEvent, DownloadConfig
 };
 use crate::downloader::core::http::HttpClient;
 use crate::downloader::core::files::check_existing_file;
 
 /// Raw Nexus archive state from JSON parsing
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NexusArchiveState {
     #[serde(rename = "ModID")]
     pub mod_id: u32,
@@ -40,12 +40,18 @@ static NEXUS_API: OnceCell<NexusAPI> = OnceCell::new();
 
 /// Initialize the global Nexus authentication client
 pub async fn initialize_nexus_api() -> Result<()> {
+    initialize_nexus_api_with(&DownloadConfig::default()).await
+}
+
+/// Initialize the global Nexus authentication client with the user agent and
+/// network policy of `config`
+pub async fn initialize_nexus_api_with(config: &DownloadConfig) -> Result<()> {
     // Load environment variables if .env file exists
     if let Ok(_) = dotenv::dotenv() {
         debug!("Loaded environment variables from .env file");
     }
 
-    let api = NexusAPI::new()?;
+    let api = NexusAPI::from_config(config)?;
 
     // Validate the API key on initialization
     match api.validate_user().await {
@@ -96,6 +102,8 @@ pub struct NexusSource {
     pub description: String,
     /// Whether the mod is marked NSFW
     pub is_nsfw: bool,
+    /// Preview image URL
+    pub image_url: Option<String>,
 }
 
 impl NexusSource {
@@ -159,12 +167,14 @@ impl NexusSource {
          let http_client = HttpClient::with_timeout(config, timeout)?;
 
          // Use HttpClient's built-in retry logic
-         let final_size = http_client.download_with_retry(&download_link.uri, &dest_path, expected_size, progress_callback.clone(), config).await?;
+         let (final_size, hash) = http_client.download_with_retry(&download_link.uri, &dest_path, expected_size, progress_callback.clone(), config).await?;
 
         // Return result with file path for centralized validation
         Ok(DownloadResult::Downloaded {
             size: final_size,
-            file_path: dest_path
+            file_path: dest_path,
+            hash,
+            mirror: None,
         })
     }
 
@@ -181,6 +191,7 @@ impl NexusSource {
             version: String::new(),
             description: String::new(),
             is_nsfw: false,
+            image_url: None,
         }
     }
 
@@ -199,4 +210,9 @@ impl NexusSource {
         self.is_nsfw = is_nsfw;
         self
     }
+
+    pub fn with_image_url<S: Into<String>>(mut self, image_url: S) -> Self {
+        self.image_url = Some(image_url.into());
+        self
+    }
 }
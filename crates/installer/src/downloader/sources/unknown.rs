@@ -9,6 +9,8 @@ pub struct UnknownSource {
     pub archive_name: Option<String>,
     /// Meta information from the modlist
     pub meta: Option<String>,
+    /// The original state object, kept verbatim for reporting and later support
+    pub raw_state: Option<serde_json::Value>,
 }
 
 impl UnknownSource {
@@ -22,11 +24,28 @@ impl UnknownSource {
             source_type: source_type.into(),
             archive_name,
             meta,
+            raw_state: None,
+        }
+    }
+
+    /// Attach the original state JSON
+    pub fn with_raw_state(mut self, raw_state: serde_json::Value) -> Self {
+        self.raw_state = Some(raw_state);
+        self
+    }
+
+    /// Downloader name without the assembly suffix
+    ///
+    /// `"MegaDownloader+State, Wabbajack.Lib"` becomes `"MegaDownloader+State"`.
+    pub fn downloader_name(&self) -> &str {
+        match self.source_type.split_once(',') {
+            Some((name, _)) => name.trim(),
+            None => self.source_type.trim(),
         }
     }
 
     pub async fn download(&self, _request: &DownloadRequest, _progress_callback: Option<ProgressCallback>, _config: &crate::downloader::core::config::DownloadConfig) -> Result<DownloadResult> {
-        let mut reason = format!("Unknown download type: '{}'", self.source_type);
+        let mut reason = format!("Unsupported downloader: '{}'", self.downloader_name());
 
         if let Some(ref name) = self.archive_name {
             reason.push_str(&format!(" (Archive: '{}')", name));
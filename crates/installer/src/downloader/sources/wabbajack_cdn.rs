@@ -1,21 +1,29 @@
 //! WabbajackCDN download source implementation
 
 use flate2::read::GzDecoder;
+use futures::StreamExt;
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::io::Read;
 use std::path::Path;
 use tokio::fs;
 use tokio::io::{AsyncWriteExt, AsyncSeekExt};
 use tracing::debug;
+use xxhash_rust::xxh64::Xxh64;
 
 use crate::downloader::core::{
-    DownloadRequest, DownloadResult, ProgressCallback, Result,
-    DownloadError, ProgressEvent, files::check_existing_file
+    DownloadRequest, DownloadResult, ProgressCallback, Result, DownloadConfig,
+    DownloadError, ProgressEvent, BandwidthLimiter, ValidationType,
+    files::{check_existing_file, create_temp_path, atomic_rename},
+    partial::{self, Segment, SegmentPlan},
 };
+use crate::hash::{Hash, HashState};
+
+/// Largest part kept in memory after writing, to feed the whole-file hash
+const MAX_RETAINED_PART_BYTES: u64 = 64 * 1024 * 1024;
 
 /// Raw WabbajackCDN archive state from JSON parsing
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WabbajackCDNArchiveState {
     #[serde(rename = "Url")]
     pub url: String,
@@ -48,7 +56,7 @@ struct PartDefinition {
     #[serde(rename = "Size")]
     size: u64,
     #[serde(rename = "Hash")]
-    _hash: String,
+    hash: Hash,
     #[serde(rename = "Offset")]
     offset: u64,
 }
@@ -58,7 +66,7 @@ impl WabbajackCDNSource {
         &self,
         request: &DownloadRequest,
         progress_callback: Option<ProgressCallback>,
-        _config: &crate::downloader::core::config::DownloadConfig,
+        config: &DownloadConfig,
     ) -> Result<DownloadResult> {
         let filename = request.get_filename()?;
         let dest_path = request.destination.join(&filename);
@@ -76,12 +84,19 @@ impl WabbajackCDNSource {
         }
 
         // Download the chunked file
-        let final_size = self.download_chunked_file(&dest_path, progress_callback.clone(), Some(request.expected_size)).await?;
+        let (final_size, hash) = self.download_chunked_file(
+            &dest_path,
+            progress_callback.clone(),
+            Some(request.expected_size),
+            config,
+        ).await?;
 
         // Return result with file path for centralized validation
         Ok(DownloadResult::Downloaded {
             size: final_size,
-            file_path: dest_path
+            file_path: dest_path,
+            hash,
+            mirror: None,
         })
     }
 
@@ -112,16 +127,14 @@ impl WabbajackCDNSource {
     }
 
     /// Create HTTP client for WabbajackCDN requests
-    fn create_client(&self) -> Client {
-        Client::builder()
-            .user_agent("Unifier/1.0")
+    fn create_client(config: &DownloadConfig) -> Result<Client> {
+        config.network.client_builder(&config.user_agent)?
             .build()
-            .expect("Failed to create HTTP client")
+            .map_err(|e| DownloadError::Legacy(format!("Failed to create HTTP client: {}", e)))
     }
 
     /// Create HTTP request with proper headers
-    fn create_request(&self, url: &str) -> Result<reqwest::RequestBuilder> {
-        let client = self.create_client();
+    fn create_request(&self, client: &Client, url: &str) -> Result<reqwest::RequestBuilder> {
         let remapped_url = self.remap_domain(url)?;
         let parsed_url = url::Url::parse(&remapped_url)?;
 
@@ -136,9 +149,9 @@ impl WabbajackCDNSource {
     }
 
     /// Download and parse the file definition
-    async fn get_file_definition(&self) -> Result<FileDefinition> {
+    async fn get_file_definition(&self, client: &Client) -> Result<FileDefinition> {
         let definition_url = format!("{}/definition.json.gz", self.url);
-        let request = self.create_request(&definition_url)?;
+        let request = self.create_request(client, &definition_url)?;
 
         let response = request.send().await?;
         if !response.status().is_success() {
@@ -162,69 +175,256 @@ impl WabbajackCDNSource {
         Ok(definition)
     }
 
-    /// Download a single part
-    async fn download_part(&self, part: &PartDefinition) -> Result<Vec<u8>> {
+    /// Download one part straight to its offset in `temp_path`, verifying its hash
+    ///
+    /// With `retain`, returns the part's bytes for the whole-file hash if it is
+    /// small enough to keep in memory; otherwise the hash reads it back from disk.
+    async fn download_part(
+        &self,
+        client: &Client,
+        part: &PartDefinition,
+        temp_path: &Path,
+        limiter: &BandwidthLimiter,
+        retain: bool,
+    ) -> Result<Option<Vec<u8>>> {
         let part_url = format!("{}/parts/{}", self.url, part.index);
         debug!("Downloading part {} from URL: {}", part.index, part_url);
-        let request = self.create_request(&part_url)?;
+        let request = self.create_request(client, &part_url)?;
+
+        let response = request.send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| DownloadError::HttpRequest { url: part_url.clone(), source: e })?;
+
+        let mut file = fs::OpenOptions::new().write(true).open(temp_path).await?;
+        file.seek(tokio::io::SeekFrom::Start(part.offset)).await?;
+
+        let mut hasher = Xxh64::new(0);
+        let mut retained = (retain && part.size <= MAX_RETAINED_PART_BYTES).then(|| Vec::with_capacity(part.size as usize));
+        let mut received = 0u64;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            if received + chunk.len() as u64 > part.size {
+                return Err(DownloadError::IncompleteTransfer {
+                    url: part_url,
+                    expected: part.size,
+                    received: received + chunk.len() as u64,
+                });
+            }
+            limiter.consume(chunk.len()).await;
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+            if let Some(ref mut retained) = retained {
+                retained.extend_from_slice(&chunk);
+            }
+            received += chunk.len() as u64;
+        }
 
-        let response = request.send().await?;
-        if !response.status().is_success() {
-            return Err(DownloadError::Legacy(
-                format!("HTTP error {}: Failed to download part {} from {}",
-                       response.status(), part.index, part_url)
-            ));
+        if received != part.size {
+            return Err(DownloadError::IncompleteTransfer { url: part_url, expected: part.size, received });
+        }
+        let actual = Hash::from_hasher(&hasher);
+        if !part.hash.is_empty() && actual != part.hash {
+            return Err(DownloadError::ValidationFailed {
+                file: temp_path.to_path_buf(),
+                validation_type: ValidationType::XxHash64,
+                expected: part.hash.to_string(),
+                actual: actual.to_string(),
+                suggestion: format!("Part {} was corrupted in transit and will be downloaded again", part.index),
+            });
         }
 
-        let data = response.bytes().await?;
-        Ok(data.to_vec())
+        file.sync_data().await?;
+        Ok(retained)
+    }
+
+    /// Download a part, retrying it on its own when it fails or arrives corrupted
+    async fn download_part_with_retry(
+        &self,
+        client: &Client,
+        part: &PartDefinition,
+        temp_path: &Path,
+        retain: bool,
+        progress_callback: Option<ProgressCallback>,
+        config: &DownloadConfig,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut attempt = 0;
+        loop {
+            match self.download_part(client, part, temp_path, &config.bandwidth_limiter, retain).await {
+                Ok(retained) => return Ok(retained),
+                Err(e) if attempt < config.max_retries
+                    && (e.is_recoverable() || matches!(e, DownloadError::ValidationFailed { .. })) =>
+                {
+                    attempt += 1;
+                    debug!("Part {} of {} failed ({}), retry {}", part.index, self.url, e, attempt);
+                    if let Some(ref callback) = progress_callback {
+                        callback(ProgressEvent::RetryAttempt {
+                            url: format!("{}/parts/{}", self.url, part.index),
+                            attempt,
+                            max_attempts: config.max_retries,
+                        });
+                    }
+                    tokio::time::sleep(config.get_retry_delay(attempt - 1)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 
-    /// Download all parts and assemble the final file
+    /// Download all parts concurrently into a .part file and move it into place
+    ///
+    /// Finished parts are recorded in a sidecar next to the .part file, so an
+    /// interrupted download only fetches the parts it is missing. Parts are also
+    /// fed into the hash of the whole file as they finish, which is returned with
+    /// the size so validation does not read the file again.
     async fn download_chunked_file(
         &self,
         dest_path: &Path,
         progress_callback: Option<ProgressCallback>,
-        expected_size: Option<u64>
-    ) -> Result<u64> {
+        expected_size: Option<u64>,
+        config: &DownloadConfig,
+    ) -> Result<(u64, Option<Hash>)> {
         // Get file definition
-        let definition = self.get_file_definition().await?;
+        let client = Self::create_client(config)?;
+        let definition = self.get_file_definition(&client).await?;
 
         // Use expected size if provided, otherwise use definition size
         let total_size = expected_size.unwrap_or(definition.size);
 
-        // Create output file
-        let mut output_file = fs::File::create(dest_path).await?;
-
-        // Download parts in sequence
-        let mut downloaded_bytes = 0u64;
+        let temp_path = create_temp_path(dest_path);
+        let mut plan = self.load_part_plan(&definition, &temp_path, total_size, config).await;
 
-        for part in &definition.parts {
-            let part_data = self.download_part(part).await?;
-
-            // Write part to output file at correct offset
-            output_file.seek(tokio::io::SeekFrom::Start(part.offset)).await?;
-            output_file.write_all(&part_data).await?;
+        // The running hash only works if the parts tile the file in order
+        let hashing = parts_are_contiguous(&definition.parts, total_size);
+        if !hashing {
+            plan.hash_state = None;
+        }
 
-            downloaded_bytes += part.size;
+        // Preallocate the .part file; parts are written at their offsets
+        let file = fs::OpenOptions::new().create(true).write(true).truncate(false).open(&temp_path).await?;
+        file.set_len(total_size).await?;
+        drop(file);
+        plan.save(&temp_path).await?;
+
+        let started = std::time::Instant::now();
+        let resumed_from = plan.downloaded();
+        let mut downloaded_bytes = resumed_from;
+        let pending: Vec<usize> = (0..definition.parts.len())
+            .filter(|&i| !plan.segments[i].is_complete())
+            .collect();
+        debug!("Downloading {} of {} parts of {}", pending.len(), definition.parts.len(), self.url);
+
+        // Parts are fetched concurrently but handed over in order, so the hash can follow them
+        let mut parts = futures::stream::iter(pending)
+            .map(|i| {
+                let (client, temp_path, callback) = (&client, &temp_path, progress_callback.clone());
+                let part = &definition.parts[i];
+                async move {
+                    self.download_part_with_retry(client, part, temp_path, hashing, callback, config).await.map(|retained| (i, retained))
+                }
+            })
+            .buffered(config.max_concurrent_parts.max(1));
+
+        while let Some(finished) = parts.next().await {
+            let (i, retained) = finished?;
+            let segment = &mut plan.segments[i];
+            segment.done = segment.end - segment.start;
+            if hashing {
+                plan.hash_state = extend_hash(plan.hash_state.take(), &temp_path, &definition.parts[i], retained).await;
+            }
+            plan.save(&temp_path).await?;
+            downloaded_bytes += definition.parts[i].size;
 
             // Report progress
             if let Some(ref callback) = progress_callback {
+                let elapsed = started.elapsed().as_secs_f64();
                 callback(ProgressEvent::DownloadProgress {
                     url: self.url.clone(),
                     downloaded: downloaded_bytes,
                     total: Some(total_size),
-                    speed_bps: 0.0, // TODO: calculate actual speed
+                    speed_bps: if elapsed > 0.0 { (downloaded_bytes - resumed_from) as f64 / elapsed } else { 0.0 },
                 });
             }
         }
+        drop(parts);
+
+        // Trailing parts finished by an earlier run have not been hashed yet
+        let hash_state = match plan.hash_state.take() {
+            Some(state) if hashing => partial::catch_up_hash(Some(state), &temp_path, total_size).await
+                .inspect_err(|e| debug!("Cannot hash {}, validation will read it: {}", temp_path.display(), e))
+                .ok(),
+            _ => None,
+        };
+
+        SegmentPlan::remove(&temp_path).await?;
+        atomic_rename(&temp_path, dest_path).await?;
+        Ok((total_size, hash_state.as_ref().map(HashState::finish)))
+    }
 
-        output_file.flush().await?;
-        output_file.sync_all().await?; // Ensure file is fully written to disk before validation
-        Ok(total_size)
+    /// The part-completion record of an earlier attempt, if it matches `definition`,
+    /// or a fresh one
+    async fn load_part_plan(
+        &self,
+        definition: &FileDefinition,
+        temp_path: &Path,
+        total_size: u64,
+        config: &DownloadConfig,
+    ) -> SegmentPlan {
+        let fresh = SegmentPlan {
+            total_size,
+            segments: definition.parts.iter()
+                .map(|part| Segment { start: part.offset, end: part.offset + part.size, done: 0 })
+                .collect(),
+            hash_state: None,
+            validators: Default::default(),
+        };
+
+        if config.allow_resume && temp_path.exists() {
+            match SegmentPlan::load(temp_path).await {
+                Some(saved) if saved.total_size == total_size
+                    && saved.segments.len() == fresh.segments.len()
+                    && saved.segments.iter().zip(&fresh.segments).all(|(a, b)| a.start == b.start && a.end == b.end) =>
+                {
+                    debug!("Resuming {} with {} of {} parts done", self.url,
+                           saved.segments.iter().filter(|s| s.is_complete()).count(), saved.segments.len());
+                    return saved;
+                }
+                _ => debug!("Discarding partial download of {}", self.url),
+            }
+        }
+        fresh
     }
+}
 
+/// True if `parts` cover `total_size` bytes back to back, in order
+fn parts_are_contiguous(parts: &[PartDefinition], total_size: u64) -> bool {
+    let mut offset = 0;
+    for part in parts {
+        if part.offset != offset {
+            return false;
+        }
+        offset += part.size;
+    }
+    offset == total_size
+}
 
+/// Extend the hash of a .part file with a finished part
+///
+/// Whatever lies between the bytes the state has seen and the part, such as parts
+/// finished by an earlier run, is read from disk, as is the part itself if it was
+/// not kept in memory. Returns `None` if the file cannot be read.
+async fn extend_hash(state: Option<HashState>, temp_path: &Path, part: &PartDefinition, retained: Option<Vec<u8>>) -> Option<HashState> {
+    let extended = match retained {
+        Some(data) => partial::catch_up_hash(state, temp_path, part.offset).await
+            .map(|mut state| {
+                state.update(&data);
+                state
+            }),
+        None => partial::catch_up_hash(state, temp_path, part.offset + part.size).await,
+    };
+    extended
+        .inspect_err(|e| debug!("Cannot hash {}, validation will read it: {}", temp_path.display(), e))
+        .ok()
 }
 
 impl WabbajackCDNSource {
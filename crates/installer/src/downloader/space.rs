@@ -0,0 +1,204 @@
+//! Disk space checks
+//!
+//! [`check_space`] compares what a batch still has to download with the free
+//! space of each destination before it starts. While a batch runs,
+//! [`SpaceMonitor`] pauses it when free space drops below the configured
+//! reserve and resumes it once space has been freed, instead of letting every
+//! remaining download fail with an I/O error.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::downloader::control::BatchController;
+use crate::downloader::core::files::create_temp_path;
+use crate::downloader::core::partial::bytes_written;
+use crate::downloader::core::{DownloadError, DownloadRequest, ProgressCallback, ProgressEvent, Result};
+use crate::downloader::store::ArchiveStore;
+
+/// Free bytes on the filesystem holding `path`
+///
+/// `path` does not need to exist yet; its nearest existing ancestor is checked.
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    let existing = path.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("."));
+    fs4::available_space(existing)
+}
+
+/// Bytes `request` still needs on disk
+///
+/// A file that already has the expected size and archives linked from the
+/// store need nothing; a `.part` file counts for what it already holds.
+pub async fn missing_bytes(request: &DownloadRequest, store: Option<&ArchiveStore>) -> u64 {
+    let dest_path = request.destination.join(&request.filename);
+    if tokio::fs::metadata(&dest_path).await.is_ok_and(|metadata| metadata.len() == request.expected_size) {
+        return 0;
+    }
+    if store.is_some_and(|store| !request.expected_hash.is_empty() && store.contains(&request.expected_hash)) {
+        return 0;
+    }
+
+    let written = bytes_written(&create_temp_path(&dest_path)).await.unwrap_or(0);
+    request.expected_size.saturating_sub(written)
+}
+
+/// Check that every destination has room for what `requests` still need,
+/// plus `reserve` bytes
+///
+/// Destinations are checked separately, so several directories on one
+/// filesystem are each compared with its full free space.
+pub async fn check_space(requests: &[DownloadRequest], reserve: u64, store: Option<&ArchiveStore>) -> Result<()> {
+    let mut needed: BTreeMap<&Path, u64> = BTreeMap::new();
+    for request in requests {
+        *needed.entry(&request.destination).or_default() += missing_bytes(request, store).await;
+    }
+
+    for (destination, missing) in needed {
+        let available = available_space(destination)
+            .map_err(|source| DownloadError::FileSystem {
+                path: destination.to_path_buf(),
+                operation: crate::downloader::core::FileOperation::Metadata,
+                source,
+            })?;
+        let required = missing.saturating_add(reserve);
+        debug!("{} needs {} bytes plus {} reserve, {} available", destination.display(), missing, reserve, available);
+        if available < required {
+            return Err(DownloadError::InsufficientSpace {
+                required,
+                available,
+                shortage: required - available,
+                path: destination.to_path_buf(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Pauses a batch while a destination is short of space
+pub(crate) struct SpaceMonitor {
+    controller: BatchController,
+    directories: Vec<PathBuf>,
+    reserve: u64,
+    progress_callback: Option<ProgressCallback>,
+    /// Destination found short of space, while it stays short
+    low: Option<PathBuf>,
+    /// Whether the monitor paused the batch, so it does not resume a pause made by the user
+    paused: bool,
+}
+
+impl SpaceMonitor {
+    pub(crate) fn new(
+        controller: BatchController,
+        directories: Vec<PathBuf>,
+        reserve: u64,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Self {
+        Self { controller, directories, reserve, progress_callback, low: None, paused: false }
+    }
+
+    /// Check every destination once, pausing or resuming the batch as needed
+    pub(crate) fn check(&mut self) {
+        let short = self.directories.iter().find_map(|directory| match available_space(directory) {
+            Ok(available) if available < self.reserve => Some((directory.clone(), available)),
+            Ok(_) => None,
+            Err(e) => {
+                debug!("Cannot read free space of {}: {}", directory.display(), e);
+                None
+            }
+        });
+
+        match (short, self.low.take()) {
+            (Some((directory, available)), None) => {
+                warn!("Only {} bytes free on {}, pausing downloads", available, directory.display());
+                if !self.controller.is_paused() {
+                    self.controller.pause();
+                    self.paused = true;
+                }
+                self.report(ProgressEvent::DiskSpaceLow {
+                    path: directory.display().to_string(),
+                    available,
+                    reserve: self.reserve,
+                });
+                self.low = Some(directory);
+            }
+            (Some(_), Some(directory)) => self.low = Some(directory),
+            (None, Some(directory)) => {
+                let available = available_space(&directory).unwrap_or(0);
+                debug!("{} bytes free on {} again", available, directory.display());
+                if std::mem::take(&mut self.paused) {
+                    self.controller.resume();
+                }
+                self.report(ProgressEvent::DiskSpaceRecovered {
+                    path: directory.display().to_string(),
+                    available,
+                });
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Keep checking every `interval` until the task is aborted
+    pub(crate) async fn run(mut self, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            self.check();
+        }
+    }
+
+    fn report(&self, event: ProgressEvent) {
+        if let Some(ref callback) = self.progress_callback {
+            callback(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::core::partial::SegmentPlan;
+    use crate::hash::Hash;
+
+    #[tokio::test]
+    async fn test_missing_bytes_counts_partial_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let request = |filename: &str| {
+            DownloadRequest::new_http("http://example.com/file", dir.path(), filename, 1000, Hash::of_bytes(b"file"))
+        };
+
+        assert_eq!(missing_bytes(&request("fresh.7z"), None).await, 1000);
+
+        std::fs::write(dir.path().join("done.7z"), vec![0; 1000]).unwrap();
+        assert_eq!(missing_bytes(&request("done.7z"), None).await, 0);
+
+        std::fs::write(dir.path().join("half.part"), vec![0; 400]).unwrap();
+        assert_eq!(missing_bytes(&request("half.7z"), None).await, 600);
+
+        // A preallocated segmented file only counts the bytes its plan has written
+        let part_path = dir.path().join("segmented.part");
+        std::fs::write(&part_path, vec![0; 1000]).unwrap();
+        let mut plan = SegmentPlan::new(1000, 2);
+        plan.mark_prefix_done(300);
+        plan.save(&part_path).await.unwrap();
+        assert_eq!(missing_bytes(&request("segmented.7z"), None).await, 700);
+    }
+
+    #[tokio::test]
+    async fn test_check_space_reports_shortage() {
+        let dir = tempfile::tempdir().unwrap();
+        let requests = vec![DownloadRequest::new_http(
+            "http://example.com/file", dir.path(), "file.7z", 1000, Hash::of_bytes(b"file"),
+        )];
+
+        assert!(check_space(&requests, 0, None).await.is_ok());
+        match check_space(&requests, u64::MAX, None).await {
+            Err(DownloadError::InsufficientSpace { required, shortage, path, .. }) => {
+                assert_eq!(required, u64::MAX);
+                assert!(shortage > 0);
+                assert_eq!(path, dir.path());
+            }
+            other => panic!("Expected InsufficientSpace, got {:?}", other),
+        }
+    }
+}
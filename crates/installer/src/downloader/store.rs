@@ -0,0 +1,279 @@
+//! Content-addressed archive store
+//!
+//! Modlists often share many archives. With an [`ArchiveStore`] configured,
+//! each archive is kept once under its xxHash64 in a shared directory and
+//! hardlinked into every modlist's downloads directory, falling back to a copy
+//! when the store lives on another filesystem. The store also records which
+//! modlists reference each archive.
+//!
+//! Only hardlinks and plain copies are made; reflinks are not requested
+//! explicitly. The copy goes through [`std::fs::copy`], which copy-on-write
+//! filesystems such as APFS, Btrfs or XFS may still turn into a clone.
+//!
+//! Layout of the store directory:
+//!
+//! ```text
+//! objects/<first two hex digits>/<hex hash>
+//! references.json
+//! ```
+//!
+//! Hardlinked archives share their contents with the store, so they must not
+//! be modified in place.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+use crate::hash::Hash;
+
+/// File in the store root mapping archive hashes to the modlists that use them
+const REFERENCES_FILE_NAME: &str = "references.json";
+
+/// An archive held by the store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredArchive {
+    pub hash: Hash,
+    pub size: u64,
+    /// Modlists referencing the archive, in name order
+    pub references: Vec<String>,
+}
+
+/// How an archive was placed at its destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMethod {
+    HardLink,
+    Copy,
+}
+
+struct StoreState {
+    root: PathBuf,
+    references: Mutex<BTreeMap<Hash, BTreeSet<String>>>,
+}
+
+/// Shared, content-addressed store for downloaded archives
+///
+/// Cloning yields a handle to the same store.
+#[derive(Clone)]
+pub struct ArchiveStore {
+    state: Arc<StoreState>,
+}
+
+impl std::fmt::Debug for ArchiveStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveStore")
+            .field("root", &self.state.root)
+            .finish()
+    }
+}
+
+impl ArchiveStore {
+    /// Open (or create) the store rooted at `root`
+    pub fn open<P: Into<PathBuf>>(root: P) -> std::io::Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(root.join("objects"))?;
+
+        let references_path = root.join(REFERENCES_FILE_NAME);
+        let references = if references_path.exists() {
+            serde_json::from_slice(&std::fs::read(&references_path)?)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            state: Arc::new(StoreState {
+                root,
+                references: Mutex::new(references),
+            }),
+        })
+    }
+
+    /// Root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.state.root
+    }
+
+    /// Where the archive with `hash` is (or would be) kept
+    pub fn object_path(&self, hash: &Hash) -> PathBuf {
+        let hex = hash.to_hex();
+        self.state.root.join("objects").join(&hex[..2]).join(hex)
+    }
+
+    /// True if the store holds the archive with `hash`
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.object_path(hash).is_file()
+    }
+
+    /// Add a validated file to the store under `hash`, unless it is already there
+    ///
+    /// The file itself is left in place; the store gets a hardlink to it, or a copy.
+    pub fn insert(&self, hash: &Hash, file: &Path) -> std::io::Result<Option<LinkMethod>> {
+        let object = self.object_path(hash);
+        if object.is_file() {
+            return Ok(None);
+        }
+        std::fs::create_dir_all(object.parent().unwrap())?;
+        link_or_copy(file, &object).map(Some)
+    }
+
+    /// Place the stored archive with `hash` at `destination`, replacing any file there
+    pub fn link_into(&self, hash: &Hash, destination: &Path) -> std::io::Result<LinkMethod> {
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        link_or_copy(&self.object_path(hash), destination)
+    }
+
+    /// Drop the stored archive with `hash`, e.g. one found damaged; its references are kept
+    pub fn remove(&self, hash: &Hash) -> std::io::Result<()> {
+        match std::fs::remove_file(self.object_path(hash)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that `modlist` uses the archive with `hash`
+    pub fn add_reference(&self, hash: &Hash, modlist: &str) -> std::io::Result<()> {
+        let mut references = self.state.references.lock().unwrap();
+        if references.entry(*hash).or_default().insert(modlist.to_string()) {
+            self.save_references(&references)?;
+        }
+        Ok(())
+    }
+
+    /// Record that `modlist` no longer uses the archive with `hash`
+    pub fn remove_reference(&self, hash: &Hash, modlist: &str) -> std::io::Result<()> {
+        let mut references = self.state.references.lock().unwrap();
+        let Some(modlists) = references.get_mut(hash) else {
+            return Ok(());
+        };
+        if modlists.remove(modlist) {
+            if modlists.is_empty() {
+                references.remove(hash);
+            }
+            self.save_references(&references)?;
+        }
+        Ok(())
+    }
+
+    /// Modlists referencing the archive with `hash`, in name order
+    pub fn references(&self, hash: &Hash) -> Vec<String> {
+        self.state.references.lock().unwrap()
+            .get(hash)
+            .map(|modlists| modlists.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Hashes of the archives `modlist` references
+    pub fn archives_referenced_by(&self, modlist: &str) -> Vec<Hash> {
+        self.state.references.lock().unwrap()
+            .iter()
+            .filter(|(_, modlists)| modlists.contains(modlist))
+            .map(|(hash, _)| *hash)
+            .collect()
+    }
+
+    /// Every archive in the store with the modlists referencing it
+    ///
+    /// Archives with no references are included so they can be cleaned up.
+    pub fn archives(&self) -> std::io::Result<Vec<StoredArchive>> {
+        let references = self.state.references.lock().unwrap();
+        let mut archives = Vec::new();
+
+        for prefix in std::fs::read_dir(self.state.root.join("objects"))? {
+            let prefix = prefix?;
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+            for object in std::fs::read_dir(prefix.path())? {
+                let object = object?;
+                let Some(hash) = object.file_name().to_str().and_then(|name| Hash::from_hex(name).ok()) else {
+                    continue;
+                };
+                archives.push(StoredArchive {
+                    hash,
+                    size: object.metadata()?.len(),
+                    references: references.get(&hash)
+                        .map(|modlists| modlists.iter().cloned().collect())
+                        .unwrap_or_default(),
+                });
+            }
+        }
+
+        archives.sort_by_key(|archive| archive.hash.to_hex());
+        Ok(archives)
+    }
+
+    fn save_references(&self, references: &BTreeMap<Hash, BTreeSet<String>>) -> std::io::Result<()> {
+        let mut temp = tempfile::NamedTempFile::new_in(&self.state.root)?;
+        serde_json::to_writer_pretty(&mut temp, references)?;
+        temp.write_all(b"\n")?;
+        temp.persist(self.state.root.join(REFERENCES_FILE_NAME)).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+/// Hardlink `source` to `target` if possible, copying otherwise, and atomically
+/// replace whatever was at `target`
+fn link_or_copy(source: &Path, target: &Path) -> std::io::Result<LinkMethod> {
+    let mut staging = target.as_os_str().to_owned();
+    staging.push(".store-tmp");
+    let staging = PathBuf::from(staging);
+    let _ = std::fs::remove_file(&staging);
+
+    let method = match std::fs::hard_link(source, &staging) {
+        Ok(()) => LinkMethod::HardLink,
+        Err(e) => {
+            debug!("Hardlinking {} failed ({}), copying instead", source.display(), e);
+            std::fs::copy(source, &staging)?;
+            LinkMethod::Copy
+        }
+    };
+
+    if let Err(e) = std::fs::rename(&staging, target) {
+        let _ = std::fs::remove_file(&staging);
+        return Err(e);
+    }
+    Ok(method)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_link_and_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArchiveStore::open(dir.path().join("store")).unwrap();
+        let hash = Hash::of_bytes(b"shared archive");
+
+        let downloaded = dir.path().join("first").join("shared.7z");
+        std::fs::create_dir_all(downloaded.parent().unwrap()).unwrap();
+        std::fs::write(&downloaded, b"shared archive").unwrap();
+
+        assert!(!store.contains(&hash));
+        assert!(store.insert(&hash, &downloaded).unwrap().is_some());
+        assert!(store.contains(&hash));
+        assert_eq!(store.insert(&hash, &downloaded).unwrap(), None);
+
+        let linked = dir.path().join("second").join("shared.7z");
+        store.link_into(&hash, &linked).unwrap();
+        assert_eq!(std::fs::read(&linked).unwrap(), b"shared archive");
+
+        store.add_reference(&hash, "First List").unwrap();
+        store.add_reference(&hash, "Second List").unwrap();
+        store.remove_reference(&hash, "First List").unwrap();
+
+        // References survive reopening the store
+        let store = ArchiveStore::open(dir.path().join("store")).unwrap();
+        assert_eq!(store.references(&hash), vec!["Second List".to_string()]);
+        assert_eq!(store.archives_referenced_by("Second List"), vec![hash]);
+        assert!(store.archives_referenced_by("First List").is_empty());
+        assert_eq!(store.archives().unwrap(), vec![StoredArchive {
+            hash,
+            size: 14,
+            references: vec!["Second List".to_string()],
+        }]);
+    }
+}
@@ -2,9 +2,11 @@
 
 use super::*;
 use crate::downloader::{
-    core::{ErrorSeverity, FileOperation, ValidationType, IntoProgressCallback, NullProgressReporter, ConsoleProgressReporter, CompositeProgressReporter},
+    core::{ErrorSeverity, FileOperation, ValidationType, ValidationResult, VerifiedDownloadResult, IntoProgressCallback, NullProgressReporter, ConsoleProgressReporter, CompositeProgressReporter},
 };
 use crate::downloader::sources::DownloadSource;
+use crate::downloader::{ArchiveStore, DownloadJournal, HashCache, MirrorResolver, UnknownSource};
+use crate::hash::Hash;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tempfile::{tempdir, TempDir};
@@ -57,6 +59,8 @@ impl ProgressCapture {
                 ProgressEvent::RetryAttempt { .. } => event_type == "retry_attempt",
                 ProgressEvent::Warning { .. } => event_type == "warning",
                 ProgressEvent::Error { .. } => event_type == "error",
+                ProgressEvent::DiskSpaceLow { .. } => event_type == "disk_space_low",
+                ProgressEvent::DiskSpaceRecovered { .. } => event_type == "disk_space_recovered",
             })
             .count()
     }
@@ -70,11 +74,18 @@ async fn create_test_file(content: &[u8]) -> (TempDir, PathBuf) {
     (temp_dir, file_path)
 }
 
-/// Calculate xxHash64 of data and return as base64 string (matching Wabbajack format)
-fn calculate_xxhash64_base64(data: &[u8]) -> String {
+/// Calculate xxHash64 of data via its base64 form (matching Wabbajack format)
+fn calculate_xxhash64(data: &[u8]) -> Hash {
     let hash = xxhash_rust::xxh64::xxh64(data, 0);
     let bytes = hash.to_le_bytes();
     base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &bytes)
+        .parse()
+        .unwrap()
+}
+
+/// Placeholder hash for requests whose content is never validated
+fn test_hash() -> Hash {
+    calculate_xxhash64(b"test")
 }
 
 #[cfg(test)]
@@ -84,10 +95,10 @@ mod file_validation_tests {
     #[tokio::test]
     async fn test_file_validation_xxhash64_success() {
         let test_data = b"Hello, World!";
-        let expected_hash = calculate_xxhash64_base64(test_data);
+        let expected_hash = calculate_xxhash64(test_data);
         let (_temp_dir, file_path) = create_test_file(test_data).await;
 
-        let validation = FileValidation::new(expected_hash, test_data.len() as u64);
+        let validation = FileValidation::new(Some(expected_hash), test_data.len() as u64);
         let progress = ProgressCapture::new();
 
         let result = validation
@@ -106,7 +117,7 @@ mod file_validation_tests {
         let wrong_hash = "AAAAAAAAAA8="; // Intentionally wrong base64 xxhash64
         let (_temp_dir, file_path) = create_test_file(test_data).await;
 
-        let validation = FileValidation::new(wrong_hash.to_string(), test_data.len() as u64);
+        let validation = FileValidation::new(Some(wrong_hash.parse().unwrap()), test_data.len() as u64);
 
         let result = validation.validate_file(&file_path, None).await;
 
@@ -120,7 +131,7 @@ mod file_validation_tests {
         let expected_size = test_data.len() as u64;
         let (_temp_dir, file_path) = create_test_file(test_data).await;
 
-        let validation = FileValidation::new(String::new(), expected_size);
+        let validation = FileValidation::new(None, expected_size);
 
         let result = validation.validate_file(&file_path, None).await;
 
@@ -135,12 +146,12 @@ mod file_validation_tests {
     #[tokio::test]
     async fn test_file_validation_multiple_hashes_success() {
         let test_data = b"Hello, World!";
-        let expected_xxhash64_base64 = calculate_xxhash64_base64(test_data);
+        let expected_xxhash64_base64 = calculate_xxhash64(test_data);
         let expected_size = test_data.len() as u64;
 
         let (_temp_dir, file_path) = create_test_file(test_data).await;
 
-        let validation = FileValidation::new(expected_xxhash64_base64, expected_size);
+        let validation = FileValidation::new(Some(expected_xxhash64_base64), expected_size);
 
         let result = validation.validate_file(&file_path, None).await;
 
@@ -148,9 +159,51 @@ mod file_validation_tests {
         assert!(result.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_file_validation_uses_hash_cache() {
+        let test_data = b"Hello, World!";
+        let expected_hash = calculate_xxhash64(test_data);
+        let (temp_dir, file_path) = create_test_file(test_data).await;
+
+        let cache = HashCache::open(temp_dir.path()).unwrap();
+        let validation = FileValidation::new(Some(expected_hash), test_data.len() as u64)
+            .with_hash_cache(cache.clone());
+        assert!(validation.validate_file(&file_path, None).await.unwrap());
+        assert_eq!(cache.len(), 1);
+
+        // Same size and modification time: the cached hash is trusted without reading the file
+        let modified = std::fs::metadata(&file_path).unwrap().modified().unwrap();
+        std::fs::write(&file_path, b"Hello, Earth!").unwrap();
+        std::fs::File::options().write(true).open(&file_path).unwrap().set_modified(modified).unwrap();
+        assert!(validation.validate_file(&file_path, None).await.unwrap());
+        assert_eq!(cache.hits().0, 1);
+
+        // Any other modification time forces a rehash
+        std::fs::File::options().write(true).open(&file_path).unwrap()
+            .set_modified(modified + std::time::Duration::from_secs(1)).unwrap();
+        assert!(!validation.validate_file(&file_path, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_file_validation_trusts_streamed_hash() {
+        let test_data = b"Hello, World!";
+        let expected_hash = calculate_xxhash64(test_data);
+        let (temp_dir, file_path) = create_test_file(b"Hello, Earth!").await;
+
+        // The file is not read, so only the streamed hash decides
+        let cache = HashCache::open(temp_dir.path()).unwrap();
+        let validation = FileValidation::new(Some(expected_hash), test_data.len() as u64)
+            .with_hash_cache(cache.clone());
+        assert!(validation.validate_file_with_hash(&file_path, Some(expected_hash), None).await.unwrap());
+        assert!(!validation.validate_file_with_hash(&file_path, Some(calculate_xxhash64(b"other")), None).await.unwrap());
+
+        // A passing file is remembered in the cache
+        assert_eq!(cache.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_file_validation_nonexistent_file() {
-        let validation = FileValidation::new("AAAAAAAAAA8=".to_string(), 1024);
+        let validation = FileValidation::new(Some("AAAAAAAAAA8=".parse().unwrap()), 1024);
         let fake_path = PathBuf::from("nonexistent_file.txt");
 
         let result = validation.validate_file(&fake_path, None).await;
@@ -169,7 +222,7 @@ mod download_request_tests {
 
     #[test]
     fn test_download_request_creation() {
-        let request: DownloadRequest = DownloadRequest::new_http("https://example.com/file.txt", "/tmp", "file.txt", 1024, "dGVzdA==".to_string());
+        let request: DownloadRequest = DownloadRequest::new_http("https://example.com/file.txt", "/tmp", "file.txt", 1024, test_hash());
 
         // In the new architecture, we test via description since we can't directly access URLs from trait objects
         assert!(request.get_description().contains("https://example.com/file.txt"));
@@ -185,7 +238,7 @@ mod download_request_tests {
 
         let http_source = HttpSource::new("https://example.com/file.txt")
             .with_mirror("https://mirror.example.com/file.txt");
-        let request = DownloadRequest::new(DownloadSource::Http(http_source), "/tmp", "file.txt", 1024, "dGVzdA==".to_string());
+        let request = DownloadRequest::new(DownloadSource::Http(http_source), "/tmp", "file.txt", 1024, test_hash());
 
         // Test that the request was created successfully
         assert!(request.get_description().contains("https://example.com/file.txt"));
@@ -193,23 +246,23 @@ mod download_request_tests {
 
     #[test]
     fn test_download_request_with_validation() {
-        let validation = FileValidation::new("dGVzdA==".to_string(), 1024);
-        let request = DownloadRequest::new_http("https://example.com/file.txt", "/tmp", "file.txt", 1024, "dGVzdA==".to_string());
+        let validation = FileValidation::new(Some(test_hash()), 1024);
+        let request = DownloadRequest::new_http("https://example.com/file.txt", "/tmp", "file.txt", 1024, test_hash());
 
-        assert_eq!(request.validation.xxhash64_base64, validation.xxhash64_base64);
+        assert_eq!(request.validation.xxhash64, validation.xxhash64);
     }
 
     #[test]
     fn test_download_request_get_filename_from_url() {
         // Test that get_filename returns the explicit filename passed to the constructor
-        let request = DownloadRequest::new_http("https://example.com/path/file.txt", "/tmp", "file.txt", 1024, "dGVzdA==".to_string());
+        let request = DownloadRequest::new_http("https://example.com/path/file.txt", "/tmp", "file.txt", 1024, test_hash());
         let filename = request.get_filename().unwrap();
         assert_eq!(filename, "file.txt"); // Returns the explicit filename
     }
 
     #[test]
     fn test_download_request_get_filename_explicit() {
-        let request = DownloadRequest::new_http("https://example.com/path/", "/tmp", "custom_name.txt", 1024, "dGVzdA==".to_string())
+        let request = DownloadRequest::new_http("https://example.com/path/", "/tmp", "custom_name.txt", 1024, test_hash())
 ;
         let filename = request.get_filename().unwrap();
         assert_eq!(filename, "custom_name.txt");
@@ -217,7 +270,7 @@ mod download_request_tests {
 
     #[test]
     fn test_download_request_get_filename_fallback() {
-        let request = DownloadRequest::new_http("https://example.com/", "/tmp", "downloaded_file", 1024, "dGVzdA==".to_string());
+        let request = DownloadRequest::new_http("https://example.com/", "/tmp", "downloaded_file", 1024, test_hash());
         let filename = request.get_filename().unwrap();
         assert_eq!(filename, "downloaded_file");
     }
@@ -268,7 +321,7 @@ mod http_downloader_tests {
         let temp_dir = tempdir().unwrap();
         let url = format!("{}/test-file.txt", mock_server.uri());
 
-        let expected_hash = calculate_xxhash64_base64(test_content);
+        let expected_hash = calculate_xxhash64(test_content);
         let request = DownloadRequest::new_http(url, temp_dir.path(), "test-file.txt", test_content.len() as u64, expected_hash);
 
         let config = DownloadConfig::default();
@@ -309,7 +362,7 @@ mod http_downloader_tests {
         // Pre-create the file
         tokio::fs::write(&file_path, test_content).await.unwrap();
 
-        let expected_hash = calculate_xxhash64_base64(test_content);
+        let expected_hash = calculate_xxhash64(test_content);
         let request = DownloadRequest::new_http("https://example.com/file.txt", temp_dir.path(), "existing-file.txt", test_content.len() as u64, expected_hash);
 
         let config = DownloadConfig::default();
@@ -353,7 +406,7 @@ mod http_downloader_tests {
         let url = format!("{}/test-file.txt", mock_server.uri());
 
         // Use wrong hash to force validation failure
-        let request = DownloadRequest::new_http(url, temp_dir.path(), "test-file.txt", test_content.len() as u64, "AAAAAAAAAA8=".to_string());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "test-file.txt", test_content.len() as u64, "AAAAAAAAAA8=".parse().unwrap());
 
         let config = DownloadConfig::default();
         let downloader = DownloadPipeline::new(config, 2, 3);
@@ -373,6 +426,330 @@ mod http_downloader_tests {
         assert!(!file_path.exists());
     }
 
+    /// Serves byte ranges of `content`, cutting the first response for `truncate_from` short
+    struct RangeResponder {
+        content: Vec<u8>,
+        truncate_from: Option<u64>,
+        ranges: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl wiremock::Respond for RangeResponder {
+        fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+            let Some(range) = request.headers.get("range").and_then(|value| value.to_str().ok()) else {
+                return ResponseTemplate::new(200).set_body_bytes(self.content.clone());
+            };
+            let first_request = {
+                let mut ranges = self.ranges.lock().unwrap();
+                ranges.push(range.to_string());
+                ranges.iter().filter(|r| *r == range).count() == 1
+            };
+
+            let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+            let start: usize = start.parse().unwrap();
+            let end: usize = end.parse::<usize>().unwrap() + 1;
+            let end = if first_request && self.truncate_from == Some(start as u64) {
+                start + (end - start) / 2
+            } else {
+                end
+            };
+            ResponseTemplate::new(206)
+                .set_body_bytes(self.content[start..end].to_vec())
+                .append_header("content-range", format!("bytes {}-{}/{}", start, end - 1, self.content.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_segmented_download_retries_segment() {
+        let mock_server = setup_mock_server().await;
+        let test_content: Vec<u8> = (0..64 * 1024u32).map(|i| (i * 31 % 251) as u8).collect();
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+
+        Mock::given(method("HEAD"))
+            .and(path("/segmented.bin"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("accept-ranges", "bytes")
+                    .append_header("content-length", test_content.len().to_string())
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/segmented.bin"))
+            .respond_with(RangeResponder {
+                content: test_content.clone(),
+                truncate_from: Some(32 * 1024),
+                ranges: Arc::clone(&ranges),
+            })
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let url = format!("{}/segmented.bin", mock_server.uri());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "segmented.bin", test_content.len() as u64, calculate_xxhash64(&test_content));
+
+        let mut config = DownloadConfig::default();
+        config.min_segment_size = 8 * 1024;
+        config.retry_delay = std::time::Duration::from_millis(10);
+        let downloader = DownloadPipeline::new(config, 2, 3);
+
+        let result = downloader.download(request, None).await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(std::fs::read(temp_dir.path().join("segmented.bin")).unwrap(), test_content);
+        assert!(!temp_dir.path().join("segmented.part").exists());
+        assert!(!temp_dir.path().join("segmented.part.segments").exists());
+
+        // Four ranges, plus a retry of the cut-short third one that picks up where it stopped
+        let ranges = ranges.lock().unwrap();
+        assert_eq!(ranges.len(), 5);
+        assert!(ranges.contains(&"bytes=40960-49151".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_segmented_download_falls_back_without_ranges() {
+        let mock_server = setup_mock_server().await;
+        let test_content: Vec<u8> = (0..32 * 1024u32).map(|i| (i % 256) as u8).collect();
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+
+        Mock::given(method("HEAD"))
+            .and(path("/plain.bin"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("content-length", test_content.len().to_string())
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/plain.bin"))
+            .respond_with(RangeResponder {
+                content: test_content.clone(),
+                truncate_from: None,
+                ranges: Arc::clone(&ranges),
+            })
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let url = format!("{}/plain.bin", mock_server.uri());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "plain.bin", test_content.len() as u64, calculate_xxhash64(&test_content));
+
+        let mut config = DownloadConfig::default();
+        config.min_segment_size = 8 * 1024;
+        let downloader = DownloadPipeline::new(config, 2, 3);
+
+        assert!(downloader.download(request, None).await.is_ok());
+        assert_eq!(std::fs::read(temp_dir.path().join("plain.bin")).unwrap(), test_content);
+        assert!(ranges.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_streamed_hash_continues_after_resume() {
+        let mock_server = setup_mock_server().await;
+        let test_content: Vec<u8> = (0..3000u32).map(|i| (i * 13 % 256) as u8).collect();
+
+        Mock::given(method("GET"))
+            .and(path("/resumed.bin"))
+            .and(wiremock::matchers::header("range", "bytes=1000-"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .set_body_bytes(test_content[1000..].to_vec())
+                    .append_header("content-range", format!("bytes 1000-2999/{}", test_content.len()))
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // An earlier attempt wrote 1000 bytes but only checkpointed the hash of 600
+        let temp_dir = tempdir().unwrap();
+        let part_path = temp_dir.path().join("resumed.part");
+        std::fs::write(&part_path, &test_content[..1000]).unwrap();
+        let mut saved = crate::hash::HashState::new();
+        saved.update(&test_content[..600]);
+        crate::downloader::core::partial::save_hash_state(&saved, &part_path).await.unwrap();
+
+        let url = format!("{}/resumed.bin", mock_server.uri());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "resumed.bin", test_content.len() as u64, calculate_xxhash64(&test_content));
+        let downloader = DownloadPipeline::new(DownloadConfig::default(), 2, 3);
+
+        let result = downloader.download(request, None).await.unwrap();
+        assert_eq!(std::fs::read(temp_dir.path().join("resumed.bin")).unwrap(), test_content);
+        assert!(!crate::downloader::core::partial::hash_state_path(&part_path).exists());
+
+        // Validation compared the streamed hash instead of reading the file
+        assert_eq!(result.streamed_hash(), Some(calculate_xxhash64(&test_content)));
+    }
+
+    #[tokio::test]
+    async fn test_segmented_download_is_hashed_as_segments_finish() {
+        use crate::downloader::core::partial::SegmentPlan;
+
+        let mock_server = setup_mock_server().await;
+        let test_content: Vec<u8> = (0..64 * 1024u32).map(|i| (i * 29 % 251) as u8).collect();
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+
+        Mock::given(method("GET"))
+            .and(path("/hashed.bin"))
+            .respond_with(RangeResponder {
+                content: test_content.clone(),
+                truncate_from: None,
+                ranges: Arc::clone(&ranges),
+            })
+            .mount(&mock_server)
+            .await;
+
+        // An earlier run finished and hashed the first range
+        let temp_dir = tempdir().unwrap();
+        let part_path = temp_dir.path().join("hashed.part");
+        let mut partial = test_content[..16 * 1024].to_vec();
+        partial.resize(test_content.len(), 0);
+        std::fs::write(&part_path, &partial).unwrap();
+        let mut plan = SegmentPlan::new(test_content.len() as u64, 4);
+        plan.mark_prefix_done(16 * 1024);
+        let mut state = crate::hash::HashState::new();
+        state.update(&test_content[..16 * 1024]);
+        plan.hash_state = Some(state);
+        plan.save(&part_path).await.unwrap();
+
+        let url = format!("{}/hashed.bin", mock_server.uri());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "hashed.bin", test_content.len() as u64, calculate_xxhash64(&test_content));
+        let mut config = DownloadConfig::default();
+        config.min_segment_size = 8 * 1024;
+        let downloader = DownloadPipeline::new(config, 2, 3);
+
+        let result = downloader.download(request, None).await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(std::fs::read(temp_dir.path().join("hashed.bin")).unwrap(), test_content);
+        assert_eq!(ranges.lock().unwrap().len(), 3);
+
+        // Validation compared the hash built from the segments instead of reading the file
+        assert_eq!(result.unwrap().streamed_hash(), Some(calculate_xxhash64(&test_content)));
+    }
+
+    #[tokio::test]
+    async fn test_resume_restarts_when_remote_file_changed() {
+        let mock_server = setup_mock_server().await;
+        let test_content: Vec<u8> = (0..3000u32).map(|i| (i * 17 % 256) as u8).collect();
+
+        // The server has a new version, so it answers the conditional range request in full
+        Mock::given(method("GET"))
+            .and(path("/changed.bin"))
+            .and(wiremock::matchers::header("range", "bytes=1000-"))
+            .and(wiremock::matchers::header("if-range", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(test_content.clone())
+                    .append_header("etag", "\"v2\"")
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let part_path = temp_dir.path().join("changed.part");
+        std::fs::write(&part_path, vec![0xAA; 1000]).unwrap();
+        let validators = crate::downloader::core::partial::ResumeValidators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+        };
+        validators.save(&part_path).await.unwrap();
+
+        let url = format!("{}/changed.bin", mock_server.uri());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "changed.bin", test_content.len() as u64, calculate_xxhash64(&test_content));
+        let downloader = DownloadPipeline::new(DownloadConfig::default(), 2, 3);
+
+        let result = downloader.download(request, None).await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(std::fs::read(temp_dir.path().join("changed.bin")).unwrap(), test_content);
+        assert!(!crate::downloader::core::partial::ResumeValidators::sidecar_path(&part_path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_segmented_resume_restarts_when_remote_file_changed() {
+        use crate::downloader::core::partial::{ResumeValidators, SegmentPlan};
+
+        let mock_server = setup_mock_server().await;
+        let test_content: Vec<u8> = (0..64 * 1024u32).map(|i| (i * 23 % 251) as u8).collect();
+
+        // The server has a new version, so it answers the conditional range requests in full
+        Mock::given(method("GET"))
+            .and(path("/changed.bin"))
+            .and(wiremock::matchers::header("if-range", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(test_content.clone())
+                    .append_header("etag", "\"v2\"")
+            )
+            .expect(1..)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/changed.bin"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(test_content.clone())
+                    .append_header("etag", "\"v2\"")
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // An earlier run fetched the first range of the old version
+        let temp_dir = tempdir().unwrap();
+        let part_path = temp_dir.path().join("changed.part");
+        std::fs::write(&part_path, vec![0xAA; test_content.len()]).unwrap();
+        let mut plan = SegmentPlan::new(test_content.len() as u64, 4);
+        plan.mark_prefix_done(16 * 1024);
+        plan.validators = ResumeValidators { etag: Some("\"v1\"".to_string()), last_modified: None };
+        plan.save(&part_path).await.unwrap();
+
+        let url = format!("{}/changed.bin", mock_server.uri());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "changed.bin", test_content.len() as u64, calculate_xxhash64(&test_content));
+        let mut config = DownloadConfig::default();
+        config.min_segment_size = 8 * 1024;
+        let downloader = DownloadPipeline::new(config, 2, 3);
+
+        let result = downloader.download(request, None).await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(std::fs::read(temp_dir.path().join("changed.bin")).unwrap(), test_content);
+        assert!(!SegmentPlan::sidecar_path(&part_path).exists());
+        assert!(!part_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_resume_restarts_on_wrong_content_range() {
+        let mock_server = setup_mock_server().await;
+        let test_content: Vec<u8> = (0..3000u32).map(|i| (i * 19 % 256) as u8).collect();
+
+        // A broken server answers the range request from the wrong offset
+        Mock::given(method("GET"))
+            .and(path("/shifted.bin"))
+            .and(wiremock::matchers::header("range", "bytes=1000-"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .set_body_bytes(test_content[500..].to_vec())
+                    .append_header("content-range", format!("bytes 500-2999/{}", test_content.len()))
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/shifted.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(test_content.clone()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("shifted.part"), &test_content[..1000]).unwrap();
+
+        let url = format!("{}/shifted.bin", mock_server.uri());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "shifted.bin", test_content.len() as u64, calculate_xxhash64(&test_content));
+        let downloader = DownloadPipeline::new(DownloadConfig::default(), 2, 3);
+
+        let result = downloader.download(request, None).await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(std::fs::read(temp_dir.path().join("shifted.bin")).unwrap(), test_content);
+    }
+
     #[tokio::test]
     async fn test_http_downloader_server_error() {
         let mock_server = setup_mock_server().await;
@@ -392,7 +769,7 @@ mod http_downloader_tests {
         let temp_dir = tempdir().unwrap();
         let url = format!("{}/error-file.txt", mock_server.uri());
 
-        let request = DownloadRequest::new_http(url, temp_dir.path(), "error-file.txt", 1024, "dGVzdA==".to_string());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "error-file.txt", 1024, test_hash());
 
         let config = DownloadConfig::default();
         let downloader = DownloadPipeline::new(config,2,3);
@@ -442,7 +819,7 @@ mod enhanced_downloader_tests {
         let (_mock_server, url) = setup_mock_server_with_content(test_content).await;
 
         let temp_dir = tempdir().unwrap();
-        let expected_hash = calculate_xxhash64_base64(test_content);
+        let expected_hash = calculate_xxhash64(test_content);
         let request = DownloadRequest::new_http(url, temp_dir.path(), "enhanced-test.txt", test_content.len() as u64, expected_hash);
 
         let config = DownloadConfig::default();
@@ -472,7 +849,6 @@ mod enhanced_downloader_tests {
     }
 
     #[tokio::test]
-    #[ignore] // TODO: Fix mirror fallback in new architecture
     async fn test_mirror_fallback() {
         let test_content = b"Hello from mirror!";
 
@@ -496,11 +872,12 @@ mod enhanced_downloader_tests {
         // Create HttpSource with mirror directly
         use crate::downloader::sources::HttpSource;
         let http_source = HttpSource::new(primary_url)
-            .with_mirror(mirror_url);
-        let request = DownloadRequest::new(DownloadSource::Http(http_source), temp_dir.path(), "test-file.txt", test_content.len() as u64, "dGVzdA==".to_string());
+            .with_mirror(mirror_url.clone());
+        let request = DownloadRequest::new(DownloadSource::Http(http_source), temp_dir.path(), "test-file.txt", test_content.len() as u64, calculate_xxhash64(test_content));
 
         let mut config = DownloadConfig::default();
         config.max_retries = 2; // Reduce retries for faster test
+        config.retry_delay = std::time::Duration::from_millis(10);
 
         let downloader = DownloadPipeline::new(config, 2, 3);
         let progress = ProgressCapture::new();
@@ -509,10 +886,11 @@ mod enhanced_downloader_tests {
             .download(request, Some(progress.get_callback()))
             .await;
 
-        assert!(result.is_ok());
+        assert!(result.is_ok(), "{:?}", result.err());
         match result.unwrap() {
-            DownloadResult::Downloaded { size , ..} => {
+            DownloadResult::Downloaded { size, mirror, .. } => {
                 assert_eq!(size, test_content.len() as u64);
+                assert_eq!(mirror, Some(mirror_url));
             }
             _ => panic!("Expected Downloaded result"),
         }
@@ -523,6 +901,54 @@ mod enhanced_downloader_tests {
         assert_eq!(downloaded_content, test_content);
     }
 
+    #[tokio::test]
+    async fn test_hash_mirror_fallback() {
+        let test_content = b"Archive whose source is gone";
+        let expected_hash = calculate_xxhash64(test_content);
+
+        // The dead link gets every pipeline attempt before the mirrors are tried
+        let primary_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(3)
+            .mount(&primary_server)
+            .await;
+
+        // Local stand-in for a Wabbajack mirror, serving the archive by hash
+        let mirror_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/archives/{}", expected_hash.to_hex())))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(test_content.as_slice()))
+            .expect(2)
+            .mount(&mirror_server)
+            .await;
+        let mirror_url = format!("{}/archives/{}", mirror_server.uri(), expected_hash.to_hex());
+
+        let temp_dir = tempdir().unwrap();
+        let dead_link = DownloadRequest::new_http(
+            format!("{}/dead.7z", primary_server.uri()), temp_dir.path(), "dead.7z",
+            test_content.len() as u64, expected_hash,
+        );
+        let mut unsupported = dead_link.clone();
+        unsupported.filename = "unsupported.7z".to_string();
+        unsupported.source = DownloadSource::Unknown(UnknownSource::new("MegaDownloader+State, Wabbajack.Lib", None, None));
+
+        let resolver = MirrorResolver::new().with_mirror(format!("{}/archives", mirror_server.uri()));
+        let mut config = DownloadConfig::default().with_mirror_resolver(resolver);
+        config.retry_delay = std::time::Duration::from_millis(10);
+        let results = DownloadPipeline::new(config, 2, 2).process_batch(vec![dead_link, unsupported], None).await;
+
+        for (result, filename) in results.into_iter().zip(["dead.7z", "unsupported.7z"]) {
+            let result = result.unwrap();
+            assert!(matches!(result.validation_result, ValidationResult::Valid));
+            match result.download_result {
+                DownloadResult::Downloaded { mirror, .. } => assert_eq!(mirror.as_deref(), Some(mirror_url.as_str())),
+                other => panic!("Expected Downloaded result, got {:?}", other),
+            }
+            assert_eq!(std::fs::read(temp_dir.path().join(filename)).unwrap(), test_content);
+        }
+    }
+
     #[tokio::test]
     async fn test_batch_download() {
         let test_content_1 = b"File 1 content";
@@ -533,8 +959,8 @@ mod enhanced_downloader_tests {
 
         let temp_dir = tempdir().unwrap();
 
-        let expected_hash_1 = calculate_xxhash64_base64(test_content_1);
-        let expected_hash_2 = calculate_xxhash64_base64(test_content_2);
+        let expected_hash_1 = calculate_xxhash64(test_content_1);
+        let expected_hash_2 = calculate_xxhash64(test_content_2);
         let requests = vec![
             DownloadRequest::new_http(url1, temp_dir.path(), "file1.txt", test_content_1.len() as u64, expected_hash_1),
             DownloadRequest::new_http(url2, temp_dir.path(), "file2.txt", test_content_2.len() as u64, expected_hash_2),
@@ -569,6 +995,286 @@ mod enhanced_downloader_tests {
         assert!(progress.count_events_of_type("download_complete") >= 2);
     }
 
+    /// Serves `content` after `delay`, recording when each request arrived
+    struct DelayedResponder {
+        content: Vec<u8>,
+        delay: std::time::Duration,
+        arrivals: Arc<Mutex<Vec<std::time::Instant>>>,
+    }
+
+    impl wiremock::Respond for DelayedResponder {
+        fn respond(&self, _request: &wiremock::Request) -> ResponseTemplate {
+            self.arrivals.lock().unwrap().push(std::time::Instant::now());
+            ResponseTemplate::new(200)
+                .set_body_bytes(self.content.clone())
+                .set_delay(self.delay)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_with_host_limit() {
+        let test_content = b"Host limited";
+        let delay = std::time::Duration::from_millis(200);
+        let arrivals = Arc::new(Mutex::new(Vec::new()));
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/limited.txt"))
+            .respond_with(DelayedResponder {
+                content: test_content.to_vec(),
+                delay,
+                arrivals: Arc::clone(&arrivals),
+            })
+            .expect(4)
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let url = format!("{}/limited.txt", mock_server.uri());
+        let expected_hash = calculate_xxhash64(test_content);
+        let requests = (0..4)
+            .map(|i| DownloadRequest::new_http(url.clone(), temp_dir.path(), format!("limited-{}.txt", i), test_content.len() as u64, expected_hash))
+            .collect();
+
+        // Every request goes to the same host, so they must run one at a time
+        let config = DownloadConfig::default().with_host_limit("127.0.0.1", 1);
+        let downloader = DownloadPipeline::new(config, 4, 3);
+        let results = downloader.process_batch(requests, None).await;
+
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|r| r.is_ok()));
+
+        // A request still being answered when another arrives would overlap with it
+        let arrivals = arrivals.lock().unwrap();
+        let peak = arrivals.iter()
+            .map(|&arrival| arrivals.iter().filter(|&&other| other <= arrival && arrival - other < delay).count())
+            .max();
+        assert_eq!(peak, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_journal_skips_verified_files() {
+        let test_content = b"Journaled content";
+        let (_mock_server, url) = setup_mock_server_with_content(test_content).await;
+
+        let temp_dir = tempdir().unwrap();
+        let expected_hash = calculate_xxhash64(test_content);
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "journaled.txt", test_content.len() as u64, expected_hash);
+        let downloader = DownloadPipeline::new(DownloadConfig::default().with_journal(), 2, 3);
+
+        let results = downloader.process_batch(vec![request.clone()], None).await;
+        assert!(matches!(&results[0], Ok(VerifiedDownloadResult { validation_result: ValidationResult::Valid, .. })));
+        let journal = DownloadJournal::open(temp_dir.path()).unwrap();
+        assert!(journal.is_verified(&expected_hash, "journaled.txt"));
+
+        // A restarted batch trusts the journal instead of hashing the file again
+        let progress = ProgressCapture::new();
+        let results = downloader.process_batch(vec![request.clone()], Some(progress.get_callback())).await;
+        assert!(matches!(&results[0], Ok(VerifiedDownloadResult { validation_result: ValidationResult::AlreadyValidated, .. })));
+        assert_eq!(progress.count_events_of_type("validation_started"), 0);
+
+        // Once the file changes the journal entry no longer applies
+        std::fs::write(temp_dir.path().join("journaled.txt"), b"Tampered").unwrap();
+        let results = downloader.process_batch(vec![request], None).await;
+        assert!(matches!(&results[0], Ok(VerifiedDownloadResult { validation_result: ValidationResult::Valid, .. })));
+        assert_eq!(std::fs::read(temp_dir.path().join("journaled.txt")).unwrap(), test_content);
+    }
+
+    #[tokio::test]
+    async fn test_archive_store_shares_downloads() {
+        let test_content = b"Archive shared by two modlists";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/shared.7z"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(test_content.as_slice()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let url = format!("{}/shared.7z", mock_server.uri());
+
+        let temp_dir = tempdir().unwrap();
+        let store = ArchiveStore::open(temp_dir.path().join("store")).unwrap();
+        let expected_hash = calculate_xxhash64(test_content);
+
+        for modlist in ["First List", "Second List"] {
+            let destination = temp_dir.path().join(modlist);
+            let request = DownloadRequest::new_http(url.clone(), &destination, "shared.7z", test_content.len() as u64, expected_hash);
+            let config = DownloadConfig::default().with_archive_store(store.clone(), modlist);
+            let results = DownloadPipeline::new(config, 2, 3).process_batch(vec![request], None).await;
+
+            assert!(results[0].is_ok());
+            assert_eq!(std::fs::read(destination.join("shared.7z")).unwrap(), test_content);
+        }
+
+        // Only the first modlist downloaded the archive; the second was linked from the store
+        assert!(store.contains(&expected_hash));
+        assert_eq!(store.references(&expected_hash), vec!["First List".to_string(), "Second List".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_damaged_store_archive_is_downloaded_again() {
+        let test_content = b"Archive damaged in the store";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/damaged.7z"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(test_content.as_slice()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // The stored object has the right size but not the right contents
+        let temp_dir = tempdir().unwrap();
+        let store = ArchiveStore::open(temp_dir.path().join("store")).unwrap();
+        let expected_hash = calculate_xxhash64(test_content);
+        let object = store.object_path(&expected_hash);
+        std::fs::create_dir_all(object.parent().unwrap()).unwrap();
+        std::fs::write(&object, vec![0u8; test_content.len()]).unwrap();
+
+        let destination = temp_dir.path().join("downloads");
+        let request = DownloadRequest::new_http(format!("{}/damaged.7z", mock_server.uri()), &destination, "damaged.7z", test_content.len() as u64, expected_hash);
+        let config = DownloadConfig::default().with_archive_store(store.clone(), "List");
+        let results = DownloadPipeline::new(config, 2, 3).process_batch(vec![request], None).await;
+
+        assert!(matches!(results[0], Ok(VerifiedDownloadResult { validation_result: ValidationResult::Valid, .. })), "{:?}", results[0]);
+        assert_eq!(std::fs::read(destination.join("damaged.7z")).unwrap(), test_content);
+        assert_eq!(std::fs::read(&object).unwrap(), test_content);
+    }
+
+    #[tokio::test]
+    async fn test_batch_retries_after_validation_failure() {
+        // The queue is empty by the time validation fails, so the retry must
+        // still be picked up and the batch must finish without a timeout
+        let test_content = b"Content that never matches";
+        let (_mock_server, url) = setup_mock_server_with_content(test_content).await;
+
+        let temp_dir = tempdir().unwrap();
+        let requests = vec![
+            DownloadRequest::new_http(url, temp_dir.path(), "bad.txt", test_content.len() as u64, test_hash()),
+        ];
+
+        let downloader = DownloadPipeline::new(DownloadConfig::default(), 2, 2);
+        let progress = ProgressCapture::new();
+
+        let started = std::time::Instant::now();
+        let results = downloader
+            .process_batch(requests, Some(progress.get_callback()))
+            .await;
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        assert_eq!(results.len(), 1);
+        match &results[0] {
+            Ok(VerifiedDownloadResult { validation_result: ValidationResult::Invalid(_), .. }) => {}
+            other => panic!("Expected invalid validation result, got {:?}", other),
+        }
+        assert!(progress.count_events_of_type("validation_complete") >= 3);
+    }
+
+    #[tokio::test]
+    async fn test_hash_cache_hits_are_counted_apart_from_downloaded_bytes() {
+        let test_content = b"Cached content";
+        let (_mock_server, url) = setup_mock_server_with_content(test_content).await;
+
+        let temp_dir = tempdir().unwrap();
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "cached.txt", test_content.len() as u64, calculate_xxhash64(test_content));
+        let downloader = DownloadPipeline::new(DownloadConfig::default().with_hash_cache(), 2, 3);
+
+        // The first download records the hash, the second finds the file unchanged
+        downloader.download(request.clone(), None).await.unwrap();
+        let result = downloader.download(request, None).await.unwrap();
+        assert!(matches!(result, DownloadResult::AlreadyExists { validated: true, .. }));
+
+        let snapshot = downloader.metrics().snapshot();
+        assert_eq!(snapshot.cache_hits, 1);
+        assert_eq!(snapshot.cache_hit_bytes, test_content.len() as u64);
+        assert_eq!(snapshot.total_bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_cancel() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"slow".as_slice()).set_delay(std::time::Duration::from_secs(30)))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let requests = (0..3)
+            .map(|i| DownloadRequest::new_http(format!("{}/slow-{}.txt", mock_server.uri(), i), temp_dir.path(), format!("slow-{}.txt", i), 4, test_hash()))
+            .collect();
+
+        let downloader = DownloadPipeline::new(DownloadConfig::default(), 2, 3);
+        let handle = downloader.start_batch(requests, None);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        handle.cancel();
+
+        let results = tokio::time::timeout(std::time::Duration::from_secs(5), handle.wait())
+            .await
+            .expect("cancelled batch should finish promptly");
+
+        assert_eq!(results.len(), 3);
+        for result in results {
+            assert!(matches!(result, Err(DownloadError::Cancelled { .. })), "unexpected result: {:?}", result);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_pause_resume_and_cancel_task() {
+        let test_content = b"Paused content";
+        let (_mock_server, url) = setup_mock_server_with_content(test_content).await;
+
+        let temp_dir = tempdir().unwrap();
+        let expected_hash = calculate_xxhash64(test_content);
+        let requests = vec![
+            DownloadRequest::new_http(url.clone(), temp_dir.path(), "kept.txt", test_content.len() as u64, expected_hash),
+            DownloadRequest::new_http(url, temp_dir.path(), "dropped.txt", test_content.len() as u64, expected_hash),
+        ];
+
+        // Pause before starting so nothing runs until the task is cancelled
+        let controller = BatchController::new();
+        controller.pause();
+
+        let downloader = DownloadPipeline::new(DownloadConfig::default(), 2, 3);
+        let handle = downloader.start_batch_with_controller(controller.clone(), requests, None);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!temp_dir.path().join("kept.txt").exists());
+
+        handle.cancel_task(1);
+        handle.resume();
+        let results = handle.wait().await;
+
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(DownloadError::Cancelled { .. })));
+        assert!(temp_dir.path().join("kept.txt").exists());
+        assert!(!temp_dir.path().join("dropped.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_low_disk_space_pauses_batch() {
+        let test_content = b"Content that never fits";
+        let (_mock_server, url) = setup_mock_server_with_content(test_content).await;
+
+        let temp_dir = tempdir().unwrap();
+        let requests = vec![
+            DownloadRequest::new_http(url, temp_dir.path(), "big.txt", test_content.len() as u64, calculate_xxhash64(test_content)),
+        ];
+
+        // No filesystem has this much free, so the batch pauses before anything starts
+        let mut config = DownloadConfig::default().with_min_free_space(u64::MAX);
+        config.space_check_interval = std::time::Duration::from_millis(10);
+        let downloader = DownloadPipeline::new(config, 2, 3);
+        let progress = ProgressCapture::new();
+        let handle = downloader.start_batch(requests, Some(progress.get_callback()));
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(handle.controller().is_paused());
+        assert_eq!(progress.count_events_of_type("disk_space_low"), 1);
+        assert!(!temp_dir.path().join("big.txt").exists());
+
+        handle.cancel();
+        let results = handle.wait().await;
+        assert!(matches!(results[0], Err(DownloadError::Cancelled { .. })));
+    }
+
     #[tokio::test]
     #[ignore] // TODO: Implement retry logic in new architecture
     async fn test_enhanced_downloader_max_retries_exceeded() {
@@ -586,7 +1292,7 @@ mod enhanced_downloader_tests {
         let temp_dir = tempdir().unwrap();
         let url = format!("{}/test-file.txt", mock_server.uri());
 
-        let request = DownloadRequest::new_http(url, temp_dir.path(), "test-file.txt", 1024, "dGVzdA==".to_string());
+        let request = DownloadRequest::new_http(url, temp_dir.path(), "test-file.txt", 1024, test_hash());
 
         let mut config = DownloadConfig::default();
         config.max_retries = 2; // Small number for faster test
@@ -610,7 +1316,7 @@ mod integration_tests {
     #[tokio::test]
     async fn test_end_to_end_download_with_validation() {
         let test_content = b"Integration test content for validation";
-        let expected_xxhash64_base64 = calculate_xxhash64_base64(test_content);
+        let expected_xxhash64_base64 = calculate_xxhash64(test_content);
         let expected_size = test_content.len() as u64;
 
         let (_mock_server, url) = {
@@ -884,7 +1590,9 @@ mod metrics_tests {
 
         let snapshot = metrics.snapshot();
         assert_eq!(snapshot.cache_hits, 2);
-        assert_eq!(snapshot.total_bytes, 800);
+        assert_eq!(snapshot.cache_hit_bytes, 800);
+        assert_eq!(snapshot.total_bytes, 0);
+        assert_eq!(snapshot.average_size(), 400.0);
     }
 
     #[test]
@@ -940,7 +1648,7 @@ mod enhanced_integration_tests {
     async fn test_complete_download_workflow_with_enhanced_features() {
         let mock_server = setup_mock_server().await;
         let test_content = b"Integration test content with enhanced features!";
-        let expected_xxhash64_base64 = calculate_xxhash64_base64(test_content);
+        let expected_xxhash64_base64 = calculate_xxhash64(test_content);
 
         // Set up mock responses
         Mock::given(method("HEAD"))
@@ -996,3 +1704,147 @@ mod enhanced_integration_tests {
         assert!(snapshot.success_rate() > 0.0);
     }
 }
+
+#[cfg(test)]
+mod cdn_source_tests {
+    use super::*;
+    use crate::downloader::sources::WabbajackCDNSource;
+    use crate::downloader::core::partial::{Segment, SegmentPlan};
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const PART_SIZE: usize = 1000;
+
+    fn test_content() -> Vec<u8> {
+        (0..3 * PART_SIZE).map(|i| (i * 7 % 256) as u8).collect()
+    }
+
+    /// Serve a gzipped definition splitting `content` into parts of `PART_SIZE` bytes
+    async fn mount_definition(server: &MockServer, content: &[u8]) {
+        let parts: Vec<_> = content.chunks(PART_SIZE).enumerate()
+            .map(|(index, data)| serde_json::json!({
+                "Index": index,
+                "Size": data.len(),
+                "Hash": Hash::of_bytes(data).to_string(),
+                "Offset": index * PART_SIZE,
+            }))
+            .collect();
+        let definition = serde_json::json!({
+            "MungedName": "archive.7z",
+            "Hash": Hash::of_bytes(content).to_string(),
+            "Size": content.len(),
+            "Parts": parts,
+        });
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(definition.to_string().as_bytes()).unwrap();
+        Mock::given(method("GET"))
+            .and(path("/archive.7z/definition.json.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(encoder.finish().unwrap()))
+            .mount(server)
+            .await;
+    }
+
+    fn cdn_request(server: &MockServer, destination: &std::path::Path, content: &[u8]) -> DownloadRequest {
+        let source = DownloadSource::WabbajackCDN(WabbajackCDNSource::new(format!("{}/archive.7z", server.uri())));
+        DownloadRequest::new(source, destination, "archive.7z", content.len() as u64, Hash::of_bytes(content))
+    }
+
+    /// Corrupts the first response, then serves the real data
+    struct CorruptOnce {
+        data: Vec<u8>,
+        calls: AtomicUsize,
+    }
+
+    impl wiremock::Respond for CorruptOnce {
+        fn respond(&self, _request: &wiremock::Request) -> ResponseTemplate {
+            let mut data = self.data.clone();
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                data[0] ^= 0xff;
+            }
+            ResponseTemplate::new(200).set_body_bytes(data)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cdn_parts_are_verified_and_retried() {
+        let mock_server = MockServer::start().await;
+        let content = test_content();
+        mount_definition(&mock_server, &content).await;
+
+        for (index, data) in content.chunks(PART_SIZE).enumerate() {
+            let mock = Mock::given(method("GET")).and(path(format!("/archive.7z/parts/{}", index)));
+            if index == 1 {
+                mock.respond_with(CorruptOnce { data: data.to_vec(), calls: AtomicUsize::new(0) })
+                    .expect(2)
+                    .mount(&mock_server)
+                    .await;
+            } else {
+                mock.respond_with(ResponseTemplate::new(200).set_body_bytes(data.to_vec()))
+                    .expect(1)
+                    .mount(&mock_server)
+                    .await;
+            }
+        }
+
+        let temp_dir = tempdir().unwrap();
+        let mut config = DownloadConfig::default();
+        config.retry_delay = std::time::Duration::from_millis(10);
+        let downloader = DownloadPipeline::new(config, 2, 3);
+
+        let result = downloader.download(cdn_request(&mock_server, temp_dir.path(), &content), None).await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(std::fs::read(temp_dir.path().join("archive.7z")).unwrap(), content);
+        assert!(!temp_dir.path().join("archive.part").exists());
+        assert!(!SegmentPlan::sidecar_path(&temp_dir.path().join("archive.part")).exists());
+
+        // Parts were hashed as they finished, so validation did not read the file
+        assert_eq!(result.unwrap().streamed_hash(), Some(calculate_xxhash64(&content)));
+    }
+
+    #[tokio::test]
+    async fn test_cdn_download_resumes_finished_parts() {
+        let mock_server = MockServer::start().await;
+        let content = test_content();
+        mount_definition(&mock_server, &content).await;
+
+        for (index, data) in content.chunks(PART_SIZE).enumerate() {
+            Mock::given(method("GET"))
+                .and(path(format!("/archive.7z/parts/{}", index)))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(data.to_vec()))
+                .expect(if index == 0 { 0 } else { 1 })
+                .mount(&mock_server)
+                .await;
+        }
+
+        // An earlier run finished the first part and hashed it before it was interrupted
+        let temp_dir = tempdir().unwrap();
+        let part_path = temp_dir.path().join("archive.part");
+        let mut partial = content[..PART_SIZE].to_vec();
+        partial.resize(content.len(), 0);
+        std::fs::write(&part_path, &partial).unwrap();
+        let plan = SegmentPlan {
+            total_size: content.len() as u64,
+            segments: (0..3u64)
+                .map(|i| Segment {
+                    start: i * PART_SIZE as u64,
+                    end: (i + 1) * PART_SIZE as u64,
+                    done: if i == 0 { PART_SIZE as u64 } else { 0 },
+                })
+                .collect(),
+            hash_state: Some({
+                let mut state = crate::hash::HashState::new();
+                state.update(&content[..PART_SIZE]);
+                state
+            }),
+            validators: Default::default(),
+        };
+        plan.save(&part_path).await.unwrap();
+
+        let downloader = DownloadPipeline::new(DownloadConfig::default(), 2, 3);
+        let result = downloader.download(cdn_request(&mock_server, temp_dir.path(), &content), None).await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(std::fs::read(temp_dir.path().join("archive.7z")).unwrap(), content);
+        assert_eq!(result.unwrap().streamed_hash(), Some(calculate_xxhash64(&content)));
+    }
+}
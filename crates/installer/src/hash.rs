@@ -0,0 +1,348 @@
+//! xxHash64 values in Wabbajack format
+//!
+//! Wabbajack stores every hash as the little-endian bytes of an xxHash64
+//! digest (seed 0), encoded as standard base64 (`"rXDEtl7gdOU="`). [`Hash`]
+//! holds those eight bytes, so a malformed hash is rejected when a modlist is
+//! parsed rather than surfacing later as a validation mismatch.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use xxhash_rust::xxh64::{xxh64, Xxh64};
+
+use base64::Engine;
+
+/// Errors from parsing a [`Hash`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HashParseError {
+    #[error("Invalid base64 hash '{0}'")]
+    InvalidBase64(String),
+
+    #[error("Invalid hex hash '{0}'")]
+    InvalidHex(String),
+
+    #[error("Hash '{input}' is {len} bytes, expected 8")]
+    InvalidLength { input: String, len: usize },
+}
+
+/// An xxHash64 digest, stored as its little-endian bytes
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Hash(pub [u8; 8]);
+
+impl Hash {
+    /// The all-zero hash Wabbajack uses for "no hash"
+    pub const EMPTY: Hash = Hash([0; 8]);
+
+    /// Build a hash from a raw xxHash64 digest
+    pub const fn from_u64(value: u64) -> Self {
+        Self(value.to_le_bytes())
+    }
+
+    /// The raw xxHash64 digest
+    pub const fn to_u64(self) -> u64 {
+        u64::from_le_bytes(self.0)
+    }
+
+    /// Hash a byte slice with xxHash64 (seed 0)
+    pub fn of_bytes(data: &[u8]) -> Self {
+        Self::from_u64(xxh64(data, 0))
+    }
+
+    /// Finish an incremental hasher
+    pub fn from_hasher(hasher: &Xxh64) -> Self {
+        Self::from_u64(hasher.digest())
+    }
+
+    /// The little-endian bytes
+    pub const fn as_bytes(&self) -> &[u8; 8] {
+        &self.0
+    }
+
+    /// True for the all-zero hash
+    pub fn is_empty(&self) -> bool {
+        *self == Self::EMPTY
+    }
+
+    /// Hex encoding of the bytes, as Wabbajack's `ToHex` produces
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Parse the hex encoding produced by [`to_hex`](Self::to_hex)
+    pub fn from_hex(s: &str) -> Result<Self, HashParseError> {
+        let bytes = hex::decode(s).map_err(|_| HashParseError::InvalidHex(s.to_string()))?;
+        Self::from_slice(s, &bytes)
+    }
+
+    /// Base64 encoding, the format used in modlists
+    pub fn to_base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.0)
+    }
+
+    /// Parse the base64 format used in modlists
+    pub fn from_base64(s: &str) -> Result<Self, HashParseError> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(s)
+            .map_err(|_| HashParseError::InvalidBase64(s.to_string()))?;
+        Self::from_slice(s, &bytes)
+    }
+
+    fn from_slice(input: &str, bytes: &[u8]) -> Result<Self, HashParseError> {
+        let bytes: [u8; 8] = bytes.try_into().map_err(|_| HashParseError::InvalidLength {
+            input: input.to_string(),
+            len: bytes.len(),
+        })?;
+        Ok(Self(bytes))
+    }
+}
+
+impl From<u64> for Hash {
+    fn from(value: u64) -> Self {
+        Self::from_u64(value)
+    }
+}
+
+impl From<Hash> for u64 {
+    fn from(hash: Hash) -> Self {
+        hash.to_u64()
+    }
+}
+
+impl FromStr for Hash {
+    type Err = HashParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_base64(s)
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_base64())
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hash({})", self.to_base64())
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_base64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Incremental xxHash64 (seed 0) whose state can be saved and restored
+///
+/// Used for downloads that hash data as it arrives: saving the state next to a
+/// partial file lets an interrupted download continue hashing where it stopped
+/// instead of reading the file again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashState {
+    /// Bytes hashed so far
+    len: u64,
+    /// Accumulators, valid once at least one 32-byte stripe has been consumed
+    acc: [u64; 4],
+    /// Bytes not yet forming a full stripe
+    #[serde(with = "pending_bytes")]
+    pending: Vec<u8>,
+}
+
+const PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+const STRIPE: usize = 32;
+
+fn round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME_2)).rotate_left(31).wrapping_mul(PRIME_1)
+}
+
+fn merge_round(acc: u64, value: u64) -> u64 {
+    (acc ^ round(0, value)).wrapping_mul(PRIME_1).wrapping_add(PRIME_4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+impl HashState {
+    pub fn new() -> Self {
+        Self {
+            len: 0,
+            acc: [PRIME_1.wrapping_add(PRIME_2), PRIME_2, 0, 0u64.wrapping_sub(PRIME_1)],
+            pending: Vec::with_capacity(STRIPE),
+        }
+    }
+
+    /// Number of bytes hashed so far
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+
+        if !self.pending.is_empty() {
+            let take = (STRIPE - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() < STRIPE {
+                return;
+            }
+            let stripe = std::mem::take(&mut self.pending);
+            self.consume_stripe(&stripe);
+            self.pending = stripe;
+            self.pending.clear();
+        }
+
+        let mut stripes = data.chunks_exact(STRIPE);
+        for stripe in &mut stripes {
+            self.consume_stripe(stripe);
+        }
+        self.pending.extend_from_slice(stripes.remainder());
+    }
+
+    fn consume_stripe(&mut self, stripe: &[u8]) {
+        for (lane, acc) in self.acc.iter_mut().enumerate() {
+            *acc = round(*acc, read_u64(&stripe[lane * 8..]));
+        }
+    }
+
+    /// The hash of everything passed to [`update`](Self::update)
+    pub fn finish(&self) -> Hash {
+        let mut hash = if self.len >= STRIPE as u64 {
+            let [a, b, c, d] = self.acc;
+            let mut hash = a.rotate_left(1)
+                .wrapping_add(b.rotate_left(7))
+                .wrapping_add(c.rotate_left(12))
+                .wrapping_add(d.rotate_left(18));
+            for acc in self.acc {
+                hash = merge_round(hash, acc);
+            }
+            hash
+        } else {
+            PRIME_5
+        };
+        hash = hash.wrapping_add(self.len);
+
+        let mut rest = self.pending.as_slice();
+        while rest.len() >= 8 {
+            hash ^= round(0, read_u64(rest));
+            hash = hash.rotate_left(27).wrapping_mul(PRIME_1).wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^= u64::from(u32::from_le_bytes(rest[..4].try_into().unwrap())).wrapping_mul(PRIME_1);
+            hash = hash.rotate_left(23).wrapping_mul(PRIME_2).wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash ^= u64::from(byte).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^= hash >> 32;
+        Hash::from_u64(hash)
+    }
+}
+
+impl Default for HashState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stores the few pending bytes of a [`HashState`] as hex
+mod pending_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
+        if bytes.len() >= super::STRIPE {
+            return Err(serde::de::Error::custom("pending bytes must be shorter than a stripe"));
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_round_trip() {
+        let hash: Hash = "rXDEtl7gdOU=".parse().unwrap();
+        assert_eq!(hash.to_string(), "rXDEtl7gdOU=");
+        assert_eq!(Hash::from_u64(hash.to_u64()), hash);
+        assert_eq!(Hash::from_hex(&hash.to_hex()).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_matches_wabbajack_encoding() {
+        // Wabbajack writes the little-endian bytes of the digest
+        let data = b"Hello, World!";
+        let expected = base64::engine::general_purpose::STANDARD.encode(xxh64(data, 0).to_le_bytes());
+        assert_eq!(Hash::of_bytes(data).to_string(), expected);
+
+        let mut hasher = Xxh64::new(0);
+        hasher.update(data);
+        assert_eq!(Hash::from_hasher(&hasher), Hash::of_bytes(data));
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        assert!(matches!("not base64!".parse::<Hash>(), Err(HashParseError::InvalidBase64(_))));
+        assert!(matches!("dGVzdA==".parse::<Hash>(), Err(HashParseError::InvalidLength { len: 4, .. })));
+        assert!(matches!(Hash::from_hex("zz"), Err(HashParseError::InvalidHex(_))));
+        assert!(serde_json::from_str::<Hash>(r#""""#).is_err());
+    }
+
+    #[test]
+    fn test_hash_state_matches_xxh64() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 13 % 256) as u8).collect();
+        for len in [0, 1, 4, 7, 8, 31, 32, 33, 100, 1000] {
+            let data = &data[..len];
+            let mut state = HashState::new();
+            // Feed in uneven pieces, saving and restoring the state in between
+            for piece in data.chunks(13) {
+                state.update(piece);
+                state = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+            }
+            assert_eq!(state.len(), len as u64);
+            assert_eq!(state.finish(), Hash::of_bytes(data), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let hash = Hash::from_u64(0x0123_4567_89ab_cdef);
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(serde_json::from_str::<Hash>(&json).unwrap(), hash);
+        assert!(Hash::EMPTY.is_empty());
+        assert_eq!(Hash::EMPTY.to_string(), "AAAAAAAAAAA=");
+    }
+}
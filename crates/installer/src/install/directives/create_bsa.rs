@@ -3,6 +3,7 @@
 //! Handles building BSA/BA2 archive files from loose files.
 
 use serde::{Deserialize, Serialize};
+use crate::hash::Hash;
 use std::path::PathBuf;
 use crate::install::error::InstallError;
 
@@ -14,7 +15,7 @@ pub struct CreateBSADirective {
     pub to: String,
     /// Content hash of the target archive file
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Size in bytes of the target archive file
     #[serde(rename = "Size")]
     pub size: u64,
@@ -33,7 +34,7 @@ impl CreateBSADirective {
     /// Create a new CreateBSA directive
     pub fn new(
         to: String,
-        hash: String,
+        hash: Hash,
         size: u64,
         temp_id: String,
         state: serde_json::Value,
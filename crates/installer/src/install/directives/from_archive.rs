@@ -3,6 +3,8 @@
 //! Handles extracting files directly from downloaded archives.
 
 use serde::{Deserialize, Serialize};
+use super::ArchiveHashPath;
+use crate::hash::Hash;
 use std::path::PathBuf;
 use crate::install::error::InstallError;
 
@@ -14,18 +16,18 @@ pub struct FromArchiveDirective {
     pub to: String,
     /// Content hash of the target file
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Size in bytes of the target file
     #[serde(rename = "Size")]
     pub size: u64,
     /// Reference to file within an archive: [archive_hash, path, components...]
     #[serde(rename = "ArchiveHashPath")]
-    pub archive_hash_path: Vec<String>,
+    pub archive_hash_path: ArchiveHashPath,
 }
 
 impl FromArchiveDirective {
     /// Create a new FromArchive directive
-    pub fn new(to: String, hash: String, size: u64, archive_hash_path: Vec<String>) -> Self {
+    pub fn new(to: String, hash: Hash, size: u64, archive_hash_path: ArchiveHashPath) -> Self {
         Self {
             to,
             hash,
@@ -53,16 +55,12 @@ impl FromArchiveDirective {
     }
 
     /// Get the archive hash (first element of archive_hash_path)
-    pub fn archive_hash(&self) -> Option<&str> {
-        self.archive_hash_path.first().map(|s| s.as_str())
+    pub fn archive_hash(&self) -> Hash {
+        self.archive_hash_path.hash
     }
 
     /// Get the path within the archive (remaining elements)
     pub fn archive_path(&self) -> Vec<&str> {
-        if self.archive_hash_path.len() > 1 {
-            self.archive_hash_path[1..].iter().map(|s| s.as_str()).collect()
-        } else {
-            vec![]
-        }
+        self.archive_hash_path.path.iter().map(|s| s.as_str()).collect()
     }
 }
@@ -3,6 +3,7 @@
 //! Handles creating merged plugin files (like zEdit merges).
 
 use serde::{Deserialize, Serialize};
+use crate::hash::Hash;
 use std::path::PathBuf;
 use crate::install::error::InstallError;
 
@@ -11,7 +12,7 @@ use crate::install::error::InstallError;
 pub struct SourcePatch {
     /// Hash of the source file
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Path to the source file
     #[serde(rename = "RelativePath")]
     pub relative_path: String,
@@ -25,7 +26,7 @@ pub struct MergedPatchDirective {
     pub to: String,
     /// Content hash of the target file
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Size in bytes of the target file
     #[serde(rename = "Size")]
     pub size: u64,
@@ -41,7 +42,7 @@ impl MergedPatchDirective {
     /// Create a new MergedPatch directive
     pub fn new(
         to: String,
-        hash: String,
+        hash: Hash,
         size: u64,
         patch_id: String,
         sources: Vec<SourcePatch>,
@@ -3,7 +3,8 @@
 //! This module contains individual directive types and their implementations.
 //! Each directive type is defined in its own file along with its execute method.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::hash::Hash;
 
 // Individual directive type modules
 pub mod from_archive;
@@ -31,6 +32,50 @@ pub use archive_meta::ArchiveMetaDirective;
 pub use ignored_directly::IgnoredDirectlyDirective;
 pub use no_match::NoMatchDirective;
 
+/// Reference to a file inside an archive: the archive hash plus the path within it
+///
+/// Serialized as Wabbajack's `ArchiveHashPath` array, `["<archive hash>", "path", ...]`,
+/// where nested archives add one path element per level.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArchiveHashPath {
+    /// Hash of the archive the file is in
+    pub hash: Hash,
+    /// Path components within the archive
+    pub path: Vec<String>,
+}
+
+impl ArchiveHashPath {
+    pub fn new(hash: Hash, path: Vec<String>) -> Self {
+        Self { hash, path }
+    }
+}
+
+impl Serialize for ArchiveHashPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(Some(self.path.len() + 1))?;
+        seq.serialize_element(&self.hash)?;
+        for part in &self.path {
+            seq.serialize_element(part)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ArchiveHashPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let mut parts = Vec::<String>::deserialize(deserializer)?.into_iter();
+        let hash = parts.next()
+            .ok_or_else(|| D::Error::invalid_length(0, &"an archive hash followed by a path"))?
+            .parse()
+            .map_err(D::Error::custom)?;
+        Ok(Self { hash, path: parts.collect() })
+    }
+}
+
 /// Unified directive enum for type-safe directive processing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(from = "crate::parse_wabbajack::parser::Directive")]
@@ -67,19 +112,19 @@ impl Directive {
     }
 
     /// Get the content hash for any directive type
-    pub fn hash(&self) -> &str {
+    pub fn hash(&self) -> Hash {
         match self {
-            Directive::FromArchive(d) => &d.hash,
-            Directive::PatchedFromArchive(d) => &d.hash,
-            Directive::InlineFile(d) => &d.hash,
-            Directive::RemappedInlineFile(d) => &d.hash,
-            Directive::TransformedTexture(d) => &d.hash,
-            Directive::CreateBSA(d) => &d.hash,
-            Directive::MergedPatch(d) => &d.hash,
-            Directive::PropertyFile(d) => &d.hash,
-            Directive::ArchiveMeta(d) => &d.hash,
-            Directive::IgnoredDirectly(d) => &d.hash,
-            Directive::NoMatch(d) => &d.hash,
+            Directive::FromArchive(d) => d.hash,
+            Directive::PatchedFromArchive(d) => d.hash,
+            Directive::InlineFile(d) => d.hash,
+            Directive::RemappedInlineFile(d) => d.hash,
+            Directive::TransformedTexture(d) => d.hash,
+            Directive::CreateBSA(d) => d.hash,
+            Directive::MergedPatch(d) => d.hash,
+            Directive::PropertyFile(d) => d.hash,
+            Directive::ArchiveMeta(d) => d.hash,
+            Directive::IgnoredDirectly(d) => d.hash,
+            Directive::NoMatch(d) => d.hash,
         }
     }
 
@@ -3,6 +3,8 @@
 //! Handles extracting files from archives and applying binary patches.
 
 use serde::{Deserialize, Serialize};
+use super::ArchiveHashPath;
+use crate::hash::Hash;
 use std::path::PathBuf;
 use crate::install::error::InstallError;
 
@@ -14,16 +16,16 @@ pub struct PatchedFromArchiveDirective {
     pub to: String,
     /// Content hash of the target file (after patching)
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Size in bytes of the target file (after patching)
     #[serde(rename = "Size")]
     pub size: u64,
     /// Reference to file within an archive: [archive_hash, path, components...]
     #[serde(rename = "ArchiveHashPath")]
-    pub archive_hash_path: Vec<String>,
+    pub archive_hash_path: ArchiveHashPath,
     /// Hash of the source file (before patching)
     #[serde(rename = "FromHash")]
-    pub from_hash: String,
+    pub from_hash: Hash,
     /// Reference to the patch data in the modlist
     #[serde(rename = "PatchID")]
     pub patch_id: String,
@@ -33,10 +35,10 @@ impl PatchedFromArchiveDirective {
     /// Create a new PatchedFromArchive directive
     pub fn new(
         to: String,
-        hash: String,
+        hash: Hash,
         size: u64,
-        archive_hash_path: Vec<String>,
-        from_hash: String,
+        archive_hash_path: ArchiveHashPath,
+        from_hash: Hash,
         patch_id: String,
     ) -> Self {
         Self {
@@ -72,16 +74,12 @@ impl PatchedFromArchiveDirective {
     }
 
     /// Get the archive hash (first element of archive_hash_path)
-    pub fn archive_hash(&self) -> Option<&str> {
-        self.archive_hash_path.first().map(|s| s.as_str())
+    pub fn archive_hash(&self) -> Hash {
+        self.archive_hash_path.hash
     }
 
     /// Get the path within the archive (remaining elements)
     pub fn archive_path(&self) -> Vec<&str> {
-        if self.archive_hash_path.len() > 1 {
-            self.archive_hash_path[1..].iter().map(|s| s.as_str()).collect()
-        } else {
-            vec![]
-        }
+        self.archive_hash_path.path.iter().map(|s| s.as_str()).collect()
     }
 }
@@ -3,6 +3,7 @@
 //! Handles modlist metadata files (banner, readme).
 
 use serde::{Deserialize, Serialize};
+use crate::hash::Hash;
 use std::path::PathBuf;
 use crate::install::error::InstallError;
 
@@ -21,7 +22,7 @@ pub struct PropertyFileDirective {
     pub to: String,
     /// Content hash of the target file
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Size in bytes of the target file
     #[serde(rename = "Size")]
     pub size: u64,
@@ -37,7 +38,7 @@ impl PropertyFileDirective {
     /// Create a new PropertyFile directive
     pub fn new(
         to: String,
-        hash: String,
+        hash: Hash,
         size: u64,
         source_data_id: String,
         property_type: PropertyType,
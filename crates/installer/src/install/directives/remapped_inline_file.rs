@@ -3,6 +3,7 @@
 //! Handles writing embedded data with path placeholder replacement.
 
 use serde::{Deserialize, Serialize};
+use crate::hash::Hash;
 use std::path::PathBuf;
 use crate::install::error::InstallError;
 
@@ -14,7 +15,7 @@ pub struct RemappedInlineFileDirective {
     pub to: String,
     /// Content hash of the target file (after path remapping)
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Size in bytes of the target file (after path remapping)
     #[serde(rename = "Size")]
     pub size: u64,
@@ -25,7 +26,7 @@ pub struct RemappedInlineFileDirective {
 
 impl RemappedInlineFileDirective {
     /// Create a new RemappedInlineFile directive
-    pub fn new(to: String, hash: String, size: u64, source_data_id: String) -> Self {
+    pub fn new(to: String, hash: Hash, size: u64, source_data_id: String) -> Self {
         Self {
             to,
             hash,
@@ -3,6 +3,8 @@
 //! Handles extracting textures and applying format/compression changes.
 
 use serde::{Deserialize, Serialize};
+use super::ArchiveHashPath;
+use crate::hash::Hash;
 use std::path::PathBuf;
 use crate::install::error::InstallError;
 
@@ -14,13 +16,13 @@ pub struct TransformedTextureDirective {
     pub to: String,
     /// Content hash of the target file (after transformation)
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
     /// Size in bytes of the target file (after transformation)
     #[serde(rename = "Size")]
     pub size: u64,
     /// Reference to file within an archive: [archive_hash, path, components...]
     #[serde(rename = "ArchiveHashPath")]
-    pub archive_hash_path: Vec<String>,
+    pub archive_hash_path: ArchiveHashPath,
     /// Texture transformation parameters (complex object)
     #[serde(rename = "ImageState")]
     pub image_state: serde_json::Value,
@@ -30,9 +32,9 @@ impl TransformedTextureDirective {
     /// Create a new TransformedTexture directive
     pub fn new(
         to: String,
-        hash: String,
+        hash: Hash,
         size: u64,
-        archive_hash_path: Vec<String>,
+        archive_hash_path: ArchiveHashPath,
         image_state: serde_json::Value,
     ) -> Self {
         Self {
@@ -65,16 +67,12 @@ impl TransformedTextureDirective {
     }
 
     /// Get the archive hash (first element of archive_hash_path)
-    pub fn archive_hash(&self) -> Option<&str> {
-        self.archive_hash_path.first().map(|s| s.as_str())
+    pub fn archive_hash(&self) -> Hash {
+        self.archive_hash_path.hash
     }
 
     /// Get the path within the archive (remaining elements)
     pub fn archive_path(&self) -> Vec<&str> {
-        if self.archive_hash_path.len() > 1 {
-            self.archive_hash_path[1..].iter().map(|s| s.as_str()).collect()
-        } else {
-            vec![]
-        }
+        self.archive_hash_path.path.iter().map(|s| s.as_str()).collect()
     }
 }
@@ -6,5 +6,5 @@ pub mod directives;
 pub mod error;
 
 // Re-export commonly used types
-pub use directives::{Directive, FromArchiveDirective, PatchedFromArchiveDirective, InlineFileDirective};
+pub use directives::{Directive, ArchiveHashPath, FromArchiveDirective, PatchedFromArchiveDirective, InlineFileDirective};
 pub use error::InstallError;
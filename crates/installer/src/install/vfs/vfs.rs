@@ -32,9 +32,8 @@ use std::path::{Path, PathBuf};
 use std::rc::{Rc, Weak};
 use std::cell::RefCell;
 
-// Hash type - you'd define this based on your hashing implementation
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Hash(pub [u8; 8]); // xxHash64 is 8 bytes
+// Shared xxHash64 type used across the crate
+pub use crate::hash::Hash;
 
 #[derive(Debug, Clone)]
 pub struct VirtualFileNode {
@@ -3,11 +3,11 @@
 //! Provides a simple, fluent API for downloading entire modlists with sensible defaults.
 
 use std::path::PathBuf;
-use crate::parse_wabbajack::parser::WabbaModlist;
+use crate::parse_wabbajack::parser::{WabbaModlist, ParseError};
 use crate::{
     Result, DownloadError
 };
-use crate::downloader::{DownloadConfig, ProgressCallback};
+use crate::downloader::{BatchController, DownloadConfig, DownloadPlan, NetworkPolicy, ProgressCallback};
 use crate::downloader::lib::DownloadPipeline;
 use crate::integrations::progress::DashboardProgressReporter;
 use crate::IntoProgressCallback;
@@ -23,6 +23,16 @@ pub struct ModlistOptions {
     pub high_performance: bool,
     /// Custom timeout in seconds (default: 120)
     pub timeout_seconds: u64,
+    /// Cap on combined download throughput in bytes per second (default: unlimited)
+    pub bandwidth_limit: Option<u64>,
+    /// Shared archive store directory, so archives common to several modlists are kept once (default: none)
+    pub archive_store: Option<PathBuf>,
+    /// Bytes to keep free on the destination filesystem; downloads pause below it (default: 512 MiB)
+    pub min_free_space: u64,
+    /// Hash-keyed mirrors to fetch archives from when their source fails (default: none)
+    pub mirrors: Vec<String>,
+    /// Proxy, extra root certificates and address family for every connection (default: system settings)
+    pub network: NetworkPolicy,
 }
 
 impl Default for ModlistOptions {
@@ -31,6 +41,11 @@ impl Default for ModlistOptions {
             max_concurrent_downloads: std::thread::available_parallelism().unwrap().get(),
             high_performance: true,
             timeout_seconds: 120,
+            bandwidth_limit: None,
+            archive_store: None,
+            min_free_space: DownloadConfig::default().min_free_space,
+            mirrors: Vec::new(),
+            network: NetworkPolicy::default(),
         }
     }
 }
@@ -44,6 +59,8 @@ pub struct ModlistDownloadResult {
     pub failed_downloads: usize,
     /// Number of files that were skipped (already existed, etc.)
     pub skipped_downloads: usize,
+    /// Number of files left unfinished because the download was cancelled
+    pub cancelled_downloads: usize,
     /// Total bytes downloaded
     pub total_bytes_downloaded: u64,
     /// Time taken for the entire operation
@@ -60,19 +77,28 @@ pub struct ModlistDownloader {
     destination: PathBuf,
     options: ModlistOptions,
     progress_callback: Option<ProgressCallback>,
+    controller: BatchController,
 }
 
 impl ModlistDownloader {
-    /// Create a new builder for the given modlist file
+    /// Create a new builder for the given modlist file (`.wabbajack` archive or raw modlist JSON)
     pub fn new(modlist_path: &str, destination: &str, options: ModlistOptions, progress_callback: Option<ProgressCallback>) -> Self {
         Self {
             modlist_path: PathBuf::from(modlist_path),
             destination: PathBuf::from(destination),
             options,
             progress_callback,
+            controller: BatchController::new(),
         }
     }
 
+    /// Controller for cancelling or pausing the download once it has started
+    ///
+    /// Take this before calling [`download`](Self::download), e.g. to cancel from a Ctrl-C handler.
+    pub fn controller(&self) -> BatchController {
+        self.controller.clone()
+    }
+
     /// Use a built-in dashboard-style progress reporter
     pub fn with_dashboard_progress(mut self) -> Self {
         let reporter = DashboardProgressReporter::new();
@@ -93,27 +119,21 @@ impl ModlistDownloader {
     pub async fn download(self) -> Result<ModlistDownloadResult> {
         let start_time = std::time::Instant::now();
 
-        // Read and parse the modlist
-        let modlist_json = std::fs::read_to_string(&self.modlist_path)
-            .map_err(|e| DownloadError::FileSystem {
-                path: self.modlist_path.clone(),
-                operation: crate::downloader::core::FileOperation::Read,
-                source: e,
-            })?;
-
-        let manifest = WabbaModlist::parse(&modlist_json).unwrap();
-
+        let manifest = self.load_manifest()?;
         let download_requests = manifest.get_dl_requests(&self.destination).unwrap();
+        let config = self.config(&manifest)?;
         // Check if any download request is a NexusSource, and initialize Nexus API if needed
         let needs_nexus = download_requests.iter().any(|req| {
             matches!(&req.source, crate::parse_wabbajack::DownloadSource::Nexus(_))
         });
         if needs_nexus {
-            crate::initialize_nexus_api().await?;
+            crate::initialize_nexus_api_with(&config).await?;
         }
-        // Create download pipeline with appropriate configuration
+
+        // Refuse to start if the missing archives cannot fit, rather than failing halfway
+        crate::downloader::check_space(&download_requests, config.min_free_space, config.archive_store.as_ref()).await?;
         let pipeline = DownloadPipeline::new(
-            DownloadConfig::default(),
+            config,
             self.options.max_concurrent_downloads,
             3, // max_retries
         );
@@ -122,10 +142,11 @@ impl ModlistDownloader {
         let total_requests = download_requests.len();
 
         // Execute batch download
-        let results = pipeline.process_batch(
+        let results = pipeline.start_batch_with_controller(
+            self.controller,
             download_requests,
             self.progress_callback,
-        ).await;
+        ).wait().await;
 
         // Process results and collect statistics
         let mut successful_downloads = 0;
@@ -133,6 +154,7 @@ impl ModlistDownloader {
         let mut total_bytes_downloaded = 0;
         let mut error_messages = Vec::new();
         let mut skipped_downloads = 0;
+        let mut cancelled_downloads = 0;
 
         for result in results {
             match result {
@@ -169,6 +191,9 @@ impl ModlistDownloader {
                         }
                     }
                 }
+                Err(DownloadError::Cancelled { .. }) => {
+                    cancelled_downloads += 1;
+                }
                 Err(e) => {
                     failed_downloads += 1;
                     error_messages.push(e.to_string());
@@ -182,11 +207,116 @@ impl ModlistDownloader {
             successful_downloads,
             failed_downloads,
             skipped_downloads: skipped_downloads,
+            cancelled_downloads,
             total_bytes_downloaded,
             elapsed_time,
             total_requests,
             error_messages,
         })
     }
+
+    /// Report what [`download`](Self::download) would do, without downloading anything
+    ///
+    /// Nexus is not contacted; the plan only counts the API requests the download would make.
+    pub async fn plan(&self) -> Result<DownloadPlan> {
+        let manifest = self.load_manifest()?;
+        let download_requests = manifest.get_dl_requests(&self.destination).unwrap();
+        let config = self.config(&manifest)?;
+        Ok(crate::downloader::plan_downloads(&download_requests, &config).await)
+    }
+
+    /// Read and parse the modlist (either a .wabbajack archive or raw modlist JSON)
+    ///
+    /// Modlists built by an unsupported Wabbajack version are refused. Archives
+    /// from unsupported downloaders are not an error; the batch reports them as skipped.
+    fn load_manifest(&self) -> Result<WabbaModlist> {
+        let parse_error = |e| match e {
+            ParseError::Io(source) => DownloadError::FileSystem {
+                path: self.modlist_path.clone(),
+                operation: crate::downloader::core::FileOperation::Read,
+                source,
+            },
+            e @ ParseError::UnsupportedWabbajackVersion { .. } => DownloadError::Configuration {
+                message: e.to_string(),
+                field: Some("WabbajackVersion".to_string()),
+                suggestion: Some("Use a build of this modlist made with a supported Wabbajack version".to_string()),
+            },
+            other => DownloadError::Legacy(format!("Failed to parse modlist {}: {}", self.modlist_path.display(), other)),
+        };
+
+        let manifest = WabbaModlist::from_path(&self.modlist_path).map_err(parse_error)?;
+        match manifest.check_compatibility() {
+            Ok(()) | Err(ParseError::UnsupportedFeatures(_)) => Ok(manifest),
+            Err(e) => Err(parse_error(e)),
+        }
+    }
+
+    /// Download configuration for the options
+    fn config(&self, manifest: &WabbaModlist) -> Result<DownloadConfig> {
+        let mut config = DownloadConfig::default()
+            .with_journal()
+            .with_hash_cache()
+            .with_min_free_space(self.options.min_free_space)
+            .with_network_policy(self.options.network.clone());
+        if let Some(limit) = self.options.bandwidth_limit {
+            config = config.with_bandwidth_limit(limit);
+        }
+        if let Some(store_root) = &self.options.archive_store {
+            let store = crate::downloader::ArchiveStore::open(store_root)
+                .map_err(|source| DownloadError::FileSystem {
+                    path: store_root.clone(),
+                    operation: crate::downloader::core::FileOperation::CreateDir,
+                    source,
+                })?;
+            config = config.with_archive_store(store, manifest.name.clone());
+        }
+        if !self.options.mirrors.is_empty() {
+            let resolver = self.options.mirrors.iter()
+                .fold(crate::downloader::MirrorResolver::new(), |resolver, mirror| resolver.with_mirror(mirror.as_str()));
+            config = config.with_mirror_resolver(resolver);
+        }
+        Ok(config)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_modlist(dir: &std::path::Path, json: &str) -> String {
+        let path = dir.join("modlist.json");
+        std::fs::write(&path, json).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_wabbajack_version_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_modlist(dir.path(), r#"{"Archives": [], "Directives": [], "WabbajackVersion": "1.1.5.0"}"#);
+        let destination = dir.path().join("downloads");
+        let downloader = ModlistDownloader::new(&path, &destination.to_string_lossy(), ModlistOptions::default(), None);
+
+        match downloader.download().await {
+            Err(DownloadError::Configuration { message, .. }) => assert!(message.contains("1.1.5"), "{}", message),
+            other => panic!("Expected a configuration error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_downloaders_are_planned_as_unsupported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_modlist(dir.path(), r#"{
+            "Archives": [{
+                "Hash": "rXDEtl7gdOU=", "Meta": "", "Name": "a.7z", "Size": 1,
+                "State": {"$type": "MegaDownloader+State, Wabbajack.Lib", "Url": "https://mega.nz/x"}
+            }],
+            "Directives": [],
+            "WabbajackVersion": "3.7.0.0"
+        }"#);
+        let destination = dir.path().join("downloads");
+        let downloader = ModlistDownloader::new(&path, &destination.to_string_lossy(), ModlistOptions::default(), None);
+
+        let plan = downloader.plan().await.unwrap();
+        assert_eq!(plan.unsupported.count, 1);
+    }
+}
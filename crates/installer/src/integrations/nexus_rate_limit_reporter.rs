@@ -165,6 +165,14 @@ impl ProgressReporter for NexusRateLimitProgressReporter {
         }
     }
 
+    fn on_disk_space_low(&self, path: &str, available: u64, reserve: u64) {
+        self.dashboard.on_disk_space_low(path, available, reserve);
+    }
+
+    fn on_disk_space_recovered(&self, path: &str, available: u64) {
+        self.dashboard.on_disk_space_recovered(path, available);
+    }
+
     fn on_error(&self, url: &str, error: &str) {
         // Forward to dashboard
         self.dashboard.on_error(url, error);
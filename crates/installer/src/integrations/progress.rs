@@ -374,7 +374,7 @@ impl ProgressReporter for DashboardProgressReporter {
 
         // Extract actual algorithms from validation config
         let mut algorithms = Vec::new();
-        if validation.xxhash64_base64.is_some() {
+        if validation.xxhash64.is_some() {
             algorithms.push("XXHASH64".to_string());
         }
         if validation.expected_size.is_some() {
@@ -522,6 +522,21 @@ impl ProgressReporter for DashboardProgressReporter {
             reporter.update_display().await;
         });
     }
+
+    fn on_disk_space_low(&self, path: &str, available: u64, reserve: u64) {
+        // Shown for all styles: nothing new starts until space is freed
+        eprintln!("\n💾 LOW DISK SPACE: {} - {:.1} MB free, {:.1} MB reserve. Downloads paused until space is freed",
+            path, available as f64 / 1_048_576.0, reserve as f64 / 1_048_576.0);
+        io::stderr().flush().unwrap();
+    }
+
+    fn on_disk_space_recovered(&self, path: &str, available: u64) {
+        if !matches!(self.style, DashboardStyle::Quiet) {
+            eprintln!("\n💾 Disk space recovered: {} - {:.1} MB free. Downloads resumed",
+                path, available as f64 / 1_048_576.0);
+            io::stderr().flush().unwrap();
+        }
+    }
 }
 
 // Manual Clone implementation since we can't derive it due to Instant
@@ -1,19 +1,43 @@
 
 
+pub mod hash;
 pub mod downloader;
 pub mod parse_wabbajack;
 pub mod integrations;
 pub mod install;
 
 // Re-export commonly used types for convenience
+pub use hash::{Hash, HashParseError, HashState};
+
 pub use downloader::{
     // Core types
     DownloadRequest, DownloadResult, ValidationHandle,
 
+    // Batch control
+    BatchController, BatchHandle,
+
+    // Download journal
+    DownloadJournal, JournalState,
+
+    // Shared archive store
+    ArchiveStore, StoredArchive,
+
+    // Hash-keyed mirror fallback
+    MirrorResolver,
+
+    // Network policy
+    NetworkPolicy, IpPreference,
+
+    // Disk space
+    available_space, check_space,
+
+    // Dry-run planning
+    plan_downloads, DownloadPlan, PlannedArchive, ArchiveStatus, PlanTotals,
+
 
 
     // Validation
-    FileValidation,
+    FileValidation, HashCache,
 
     // Progress tracking
     ProgressCallback, ProgressEvent, ProgressReporter, IntoProgressCallback,
@@ -24,7 +48,7 @@ pub use downloader::{
     DownloadError, Result, ErrorSeverity, FileOperation, ValidationType, ErrorContext,
 
     // Nexus authentication
-    NexusAPI, UserValidation, initialize_nexus_api,
+    NexusAPI, UserValidation, initialize_nexus_api, initialize_nexus_api_with,
 };
 
 // Re-export parse_wabbajack types
@@ -33,6 +57,18 @@ pub use parse_wabbajack::{
     // Source types
     DownloadSource as WabbajackDownloadSource, HttpSource, NexusSource,
     GameFileSource, ManualSource, ArchiveSource,
+
+    // Modlist archives
+    WabbajackFile, ModlistStream, ArchiveMeta,
+
+    // Versioning
+    ModlistVersion, SUPPORTED_WABBAJACK_VERSIONS,
+
+    // Validation
+    ValidationReport, Diagnostic, Severity,
+
+    // Upgrade planning
+    ModlistDiff,
 };
 
 // Re-export high-level convenience APIs (the main improvement!)
@@ -0,0 +1,258 @@
+//! Modlist version diffing
+//!
+//! Compares two versions of a modlist so an upgrade can be planned before
+//! anything is downloaded. Archives are matched by hash and directives by
+//! their `To` path (case-insensitively, as Wabbajack paths are Windows paths).
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::parser::{Archive, Directive, WabbaModlist};
+use crate::hash::Hash;
+
+/// Identifying details of an archive in a diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveSummary {
+    pub hash: Hash,
+    pub name: String,
+    pub size: u64,
+}
+
+impl From<&Archive> for ArchiveSummary {
+    fn from(archive: &Archive) -> Self {
+        Self {
+            hash: archive.hash,
+            name: archive.name.clone(),
+            size: archive.size,
+        }
+    }
+}
+
+/// An archive present in both versions whose details changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveChange {
+    pub old: ArchiveSummary,
+    pub new: ArchiveSummary,
+    /// Which fields differ: `Name`, `Meta` and/or `State`
+    pub changed_fields: Vec<&'static str>,
+}
+
+/// A directive written to the same path in both versions, with different content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirectiveChange {
+    pub to: String,
+    pub old_hash: Hash,
+    pub new_hash: Hash,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+/// Differences between two versions of a modlist
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ModlistDiff {
+    pub added_archives: Vec<ArchiveSummary>,
+    pub removed_archives: Vec<ArchiveSummary>,
+    pub changed_archives: Vec<ArchiveChange>,
+    /// `To` paths only present in the new version
+    pub added_directives: Vec<String>,
+    /// `To` paths only present in the old version, i.e. installed files that would be deleted
+    pub removed_directives: Vec<String>,
+    pub changed_directives: Vec<DirectiveChange>,
+}
+
+impl ModlistDiff {
+    /// Compute the diff from `old` to `new`
+    pub fn between(old: &WabbaModlist, new: &WabbaModlist) -> Self {
+        let mut diff = Self::default();
+        diff.diff_archives(&old.archives, &new.archives);
+        diff.diff_directives(&old.directives, &new.directives);
+        diff
+    }
+
+    fn diff_archives(&mut self, old: &[Archive], new: &[Archive]) {
+        let old_by_hash: HashMap<Hash, &Archive> = old.iter().map(|a| (a.hash, a)).collect();
+        let new_by_hash: HashMap<Hash, &Archive> = new.iter().map(|a| (a.hash, a)).collect();
+
+        for archive in new {
+            match old_by_hash.get(&archive.hash) {
+                None => self.added_archives.push(archive.into()),
+                Some(previous) => {
+                    let mut changed_fields = Vec::new();
+                    if previous.name != archive.name {
+                        changed_fields.push("Name");
+                    }
+                    if previous.meta != archive.meta {
+                        changed_fields.push("Meta");
+                    }
+                    if !same_state(previous, archive) {
+                        changed_fields.push("State");
+                    }
+                    if !changed_fields.is_empty() {
+                        self.changed_archives.push(ArchiveChange {
+                            old: (*previous).into(),
+                            new: archive.into(),
+                            changed_fields,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.removed_archives = old.iter()
+            .filter(|a| !new_by_hash.contains_key(&a.hash))
+            .map(ArchiveSummary::from)
+            .collect();
+
+        self.added_archives.sort_by(|a, b| a.name.cmp(&b.name));
+        self.removed_archives.sort_by(|a, b| a.name.cmp(&b.name));
+        self.changed_archives.sort_by(|a, b| a.new.name.cmp(&b.new.name));
+    }
+
+    fn diff_directives(&mut self, old: &[Directive], new: &[Directive]) {
+        let old_by_path: HashMap<String, &Directive> = old.iter().map(|d| (path_key(d.to()), d)).collect();
+        let new_by_path: HashMap<String, &Directive> = new.iter().map(|d| (path_key(d.to()), d)).collect();
+
+        for directive in new {
+            match old_by_path.get(&path_key(directive.to())) {
+                None => self.added_directives.push(directive.to().to_string()),
+                Some(previous) if previous.hash() != directive.hash() || previous.size() != directive.size() => {
+                    self.changed_directives.push(DirectiveChange {
+                        to: directive.to().to_string(),
+                        old_hash: previous.hash(),
+                        new_hash: directive.hash(),
+                        old_size: previous.size(),
+                        new_size: directive.size(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        self.removed_directives = old.iter()
+            .filter(|d| !new_by_path.contains_key(&path_key(d.to())))
+            .map(|d| d.to().to_string())
+            .collect();
+
+        self.added_directives.sort();
+        self.removed_directives.sort();
+        self.changed_directives.sort_by(|a, b| a.to.cmp(&b.to));
+    }
+
+    /// True if the two versions are identical as far as installation is concerned
+    pub fn is_empty(&self) -> bool {
+        self.added_archives.is_empty()
+            && self.removed_archives.is_empty()
+            && self.changed_archives.is_empty()
+            && self.added_directives.is_empty()
+            && self.removed_directives.is_empty()
+            && self.changed_directives.is_empty()
+    }
+
+    /// Total size of archives that need to be downloaded for the upgrade
+    pub fn new_download_bytes(&self) -> u64 {
+        self.added_archives.iter().map(|a| a.size).sum()
+    }
+
+    /// Installed files that the upgrade would delete
+    pub fn deleted_files(&self) -> &[String] {
+        &self.removed_directives
+    }
+}
+
+impl WabbaModlist {
+    /// Diff this modlist against a newer version of it
+    pub fn diff(&self, newer: &WabbaModlist) -> ModlistDiff {
+        ModlistDiff::between(self, newer)
+    }
+}
+
+/// Compare archive states ignoring the archive context attached to unknown sources
+fn same_state(a: &Archive, b: &Archive) -> bool {
+    match (serde_json::to_value(&a.state), serde_json::to_value(&b.state)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.state == b.state,
+    }
+}
+
+/// Case-insensitive, separator-agnostic key for a `To` path
+fn path_key(path: &str) -> String {
+    path.replace('/', "\\").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hashes in these tests are derived from short labels to keep them readable
+    fn hash(label: &str) -> Hash {
+        Hash::of_bytes(label.as_bytes())
+    }
+
+    fn archive(label: &str, name: &str, size: u64, url: &str) -> String {
+        format!(
+            r#"{{"Hash": "{}", "Meta": "", "Name": "{}", "Size": {}, "State": {{"$type": "HttpDownloader, Wabbajack.Lib", "Url": "{}"}}}}"#,
+            hash(label), name, size, url
+        )
+    }
+
+    fn inline(to: &str, label: &str, size: u64) -> String {
+        format!(
+            r#"{{"$type": "InlineFile", "To": "{}", "Hash": "{}", "Size": {}, "SourceDataID": "id"}}"#,
+            to, hash(label), size
+        )
+    }
+
+    fn modlist(archives: &[String], directives: &[String]) -> WabbaModlist {
+        WabbaModlist::parse(&format!(
+            r#"{{"Archives": [{}], "Directives": [{}]}}"#,
+            archives.join(","),
+            directives.join(",")
+        )).unwrap()
+    }
+
+    #[test]
+    fn test_identical_modlists() {
+        let a = modlist(&[archive("AAAA", "a.7z", 10, "https://x/a")], &[inline("a.txt", "h1", 1)]);
+        let b = modlist(&[archive("AAAA", "a.7z", 10, "https://x/a")], &[inline("A.TXT", "h1", 1)]);
+        assert!(a.diff(&b).is_empty());
+    }
+
+    #[test]
+    fn test_diff_archives_and_directives() {
+        let old = modlist(
+            &[
+                archive("AAAA", "kept.7z", 10, "https://x/kept"),
+                archive("BBBB", "removed.7z", 20, "https://x/removed"),
+                archive("CCCC", "moved.7z", 30, "https://old/moved"),
+            ],
+            &[inline("same.txt", "h1", 1), inline("changed.txt", "h2", 2), inline("gone.txt", "h3", 3)],
+        );
+        let new = modlist(
+            &[
+                archive("AAAA", "kept.7z", 10, "https://x/kept"),
+                archive("CCCC", "moved.7z", 30, "https://new/moved"),
+                archive("DDDD", "added.7z", 40, "https://x/added"),
+                archive("EEEE", "added2.7z", 2, "https://x/added2"),
+            ],
+            &[inline("same.txt", "h1", 1), inline("changed.txt", "h9", 5), inline("new.txt", "h4", 4)],
+        );
+
+        let diff = old.diff(&new);
+
+        assert_eq!(diff.added_archives.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["added.7z", "added2.7z"]);
+        assert_eq!(diff.removed_archives[0].name, "removed.7z");
+        assert_eq!(diff.changed_archives.len(), 1);
+        assert_eq!(diff.changed_archives[0].changed_fields, vec!["State"]);
+        assert_eq!(diff.new_download_bytes(), 42);
+
+        assert_eq!(diff.added_directives, vec!["new.txt".to_string()]);
+        assert_eq!(diff.deleted_files(), &["gone.txt".to_string()]);
+        assert_eq!(diff.changed_directives, vec![DirectiveChange {
+            to: "changed.txt".to_string(),
+            old_hash: hash("h2"),
+            new_hash: hash("h9"),
+            old_size: 2,
+            new_size: 5,
+        }]);
+    }
+}
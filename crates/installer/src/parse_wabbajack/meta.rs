@@ -0,0 +1,220 @@
+//! Archive `Meta` INI parsing
+//!
+//! Every archive in a modlist carries an MO2-style `.meta` blob:
+//!
+//! ```text
+//! [General]
+//! gameName=skyrimspecialedition
+//! modID=12604
+//! fileID=35407
+//! ```
+//!
+//! [`ArchiveMeta`] keeps the sections and keys in their original order so it
+//! can be written back out as an MO2-compatible `.meta` file.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Name of the section holding the well-known keys
+pub const GENERAL_SECTION: &str = "General";
+
+/// A single `[Section]` with its `key=value` entries in file order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MetaSection {
+    /// Section name without brackets (empty for entries before the first header)
+    pub name: String,
+    /// Entries in the order they appeared
+    pub entries: Vec<(String, String)>,
+}
+
+impl MetaSection {
+    /// Look up a value by key (case-insensitive, like MO2)
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parsed archive `Meta` INI block
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ArchiveMeta {
+    sections: Vec<MetaSection>,
+}
+
+impl ArchiveMeta {
+    /// Parse a meta INI string
+    ///
+    /// Parsing is lenient: blank lines, `;`/`#` comments and lines without `=`
+    /// are ignored, matching how MO2 reads these files.
+    pub fn parse(text: &str) -> Self {
+        let mut sections: Vec<MetaSection> = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push(MetaSection {
+                    name: name.trim().to_string(),
+                    entries: Vec::new(),
+                });
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                if sections.is_empty() {
+                    sections.push(MetaSection::default());
+                }
+                let section = sections.last_mut().expect("section was just ensured");
+                section.entries.push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        Self { sections }
+    }
+
+    /// All sections in file order
+    pub fn sections(&self) -> &[MetaSection] {
+        &self.sections
+    }
+
+    /// Find a section by name (case-insensitive)
+    pub fn section(&self, name: &str) -> Option<&MetaSection> {
+        self.sections.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Look up a value in a given section
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.section(section).and_then(|s| s.get(key))
+    }
+
+    /// Set a value, creating the section or key if needed
+    pub fn set<K: Into<String>, V: Into<String>>(&mut self, section: &str, key: K, value: V) {
+        let key = key.into();
+        let value = value.into();
+
+        let index = match self.sections.iter().position(|s| s.name.eq_ignore_ascii_case(section)) {
+            Some(index) => index,
+            None => {
+                self.sections.push(MetaSection {
+                    name: section.to_string(),
+                    entries: Vec::new(),
+                });
+                self.sections.len() - 1
+            }
+        };
+
+        let entries = &mut self.sections[index].entries;
+        match entries.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(&key)) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((key, value)),
+        }
+    }
+
+    /// True if the meta block has no entries at all
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|s| s.entries.is_empty())
+    }
+
+    /// `gameName` from `[General]`
+    pub fn game_name(&self) -> Option<&str> {
+        self.get(GENERAL_SECTION, "gameName")
+    }
+
+    /// `modID` from `[General]`
+    pub fn mod_id(&self) -> Option<u64> {
+        self.get(GENERAL_SECTION, "modID").and_then(|v| v.parse().ok())
+    }
+
+    /// `fileID` from `[General]`
+    pub fn file_id(&self) -> Option<u64> {
+        self.get(GENERAL_SECTION, "fileID").and_then(|v| v.parse().ok())
+    }
+
+    /// `directURL` from `[General]`
+    pub fn direct_url(&self) -> Option<&str> {
+        self.get(GENERAL_SECTION, "directURL")
+    }
+
+    /// `installed` from `[General]`
+    pub fn installed(&self) -> Option<bool> {
+        self.get(GENERAL_SECTION, "installed").and_then(|v| match v.to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        })
+    }
+}
+
+impl FromStr for ArchiveMeta {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl fmt::Display for ArchiveMeta {
+    /// Write the meta back out in MO2 `.meta` format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            if !section.name.is_empty() {
+                writeln!(f, "[{}]", section.name)?;
+            }
+            for (key, value) in &section.entries {
+                writeln!(f, "{}={}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_general_section() {
+        let meta = ArchiveMeta::parse(
+            "[General]\r\ngameName=skyrimspecialedition\r\nmodID=12604\r\nfileID=35407\r\ninstalled=true\r\n",
+        );
+
+        assert_eq!(meta.game_name(), Some("skyrimspecialedition"));
+        assert_eq!(meta.mod_id(), Some(12604));
+        assert_eq!(meta.file_id(), Some(35407));
+        assert_eq!(meta.installed(), Some(true));
+        assert_eq!(meta.direct_url(), None);
+        assert_eq!(meta.get("general", "GAMENAME"), Some("skyrimspecialedition"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "[General]\ndirectURL=https://example.com/a.7z\n\n[installedFiles]\n1\\modid=5\n";
+        let meta: ArchiveMeta = text.parse().unwrap();
+
+        assert_eq!(meta.direct_url(), Some("https://example.com/a.7z"));
+        assert_eq!(meta.get("installedFiles", "1\\modid"), Some("5"));
+        assert_eq!(meta.to_string(), text);
+        assert_eq!(ArchiveMeta::parse(&meta.to_string()), meta);
+    }
+
+    #[test]
+    fn test_set_and_empty() {
+        let mut meta = ArchiveMeta::parse("");
+        assert!(meta.is_empty());
+
+        meta.set(GENERAL_SECTION, "gameName", "fallout4");
+        meta.set(GENERAL_SECTION, "modID", "1");
+        meta.set(GENERAL_SECTION, "modid", "2");
+
+        assert!(!meta.is_empty());
+        assert_eq!(meta.mod_id(), Some(2));
+        assert_eq!(meta.to_string(), "[General]\ngameName=fallout4\nmodID=2\n");
+    }
+}
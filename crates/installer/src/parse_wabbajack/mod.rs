@@ -7,7 +7,19 @@
 //! rather than converting to URL strings, as this provides better type safety,
 //! performance, and allows for richer data representation.
 
+pub mod diff;
+pub mod meta;
 pub mod parser;
+pub mod streaming;
+pub mod validate;
+pub mod version;
+pub mod wabbajack_file;
 
 // Re-export main types
+pub use diff::ModlistDiff;
+pub use meta::ArchiveMeta;
+pub use streaming::ModlistStream;
+pub use validate::{Diagnostic, DiagnosticCode, Severity, ValidationReport};
+pub use version::{ModlistVersion, VersionRange, SUPPORTED_WABBAJACK_VERSIONS};
+pub use wabbajack_file::WabbajackFile;
 pub use crate::downloader::sources::{DownloadSource, HttpSource, NexusSource, GameFileSource, ManualSource, ArchiveSource};
@@ -4,6 +4,7 @@
 //! it into structured DownloadOperation objects.
 
 use crate::downloader::core::{DownloadRequest,  DownloadSource};
+use crate::hash::Hash;
 use crate::downloader::sources::{HttpArchiveState, NexusArchiveState, GameFileArchiveState, WabbajackCDNArchiveState};
 use crate::install::directives::{
     FromArchiveDirective,
@@ -18,11 +19,13 @@ use crate::install::directives::{
     IgnoredDirectlyDirective,
     NoMatchDirective,
 };
-use serde::Deserialize;
-use std::path::PathBuf;
+use super::meta::ArchiveMeta;
+use super::version::{ModlistVersion, VersionRange, SUPPORTED_WABBAJACK_VERSIONS};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// Raw modlist JSON structure as it appears in the file
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct WabbaModlist {
     #[serde(rename = "Archives")]
     pub archives: Vec<Archive>,
@@ -38,6 +41,21 @@ pub struct WabbaModlist {
     pub game: String,
     #[serde(rename = "Description", default)]
     pub description: String,
+    #[serde(rename = "GameType", default)]
+    pub game_type: String,
+    #[serde(rename = "Image", default)]
+    pub image: Option<String>,
+    #[serde(rename = "Readme", default)]
+    pub readme: Option<String>,
+    #[serde(rename = "Website", default)]
+    pub website: Option<String>,
+    #[serde(rename = "IsNSFW", default)]
+    pub is_nsfw: bool,
+    #[serde(rename = "WabbajackVersion", default)]
+    pub wabbajack_version: String,
+    /// Any other header fields, kept verbatim
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl WabbaModlist {
@@ -49,6 +67,74 @@ impl WabbaModlist {
         Ok(modlist)
     }
 
+    /// Load a modlist from disk, accepting either a `.wabbajack` archive or raw modlist JSON
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<WabbaModlist, ParseError> {
+        let path = path.as_ref();
+        if super::wabbajack_file::is_wabbajack_archive(path)? {
+            Ok(super::wabbajack_file::WabbajackFile::open(path)?.into_modlist())
+        } else {
+            let json = std::fs::read_to_string(path)?;
+            Self::parse(&json)
+        }
+    }
+
+    /// Serialize the modlist back to Wabbajack-compatible JSON
+    pub fn to_json(&self) -> Result<String, ParseError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Write the modlist as raw JSON to disk
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<(), ParseError> {
+        use std::io::Write;
+
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(&mut file, self)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// The modlist's own `Version`, parsed (`None` if the field is absent)
+    pub fn parsed_version(&self) -> Result<Option<ModlistVersion>, ParseError> {
+        parse_optional_version(&self.version)
+    }
+
+    /// The `WabbajackVersion` that compiled this modlist, parsed (`None` if absent)
+    pub fn parsed_wabbajack_version(&self) -> Result<Option<ModlistVersion>, ParseError> {
+        parse_optional_version(&self.wabbajack_version)
+    }
+
+    /// Features this modlist needs that we can't provide, such as unsupported downloaders
+    pub fn unsupported_features(&self) -> Vec<String> {
+        let mut features: Vec<String> = self.archives.iter()
+            .filter_map(|archive| match &archive.state {
+                DownloadSource::Unknown(unknown) => Some(format!("downloader '{}'", unknown.downloader_name())),
+                _ => None,
+            })
+            .collect();
+        features.sort();
+        features.dedup();
+        features
+    }
+
+    /// Check that this modlist was built by a supported Wabbajack version and needs no unsupported features
+    pub fn check_compatibility(&self) -> Result<(), ParseError> {
+        if let Some(found) = self.parsed_wabbajack_version()?
+            && !SUPPORTED_WABBAJACK_VERSIONS.contains(&found)
+        {
+            return Err(ParseError::UnsupportedWabbajackVersion {
+                found,
+                supported: SUPPORTED_WABBAJACK_VERSIONS,
+            });
+        }
+
+        let features = self.unsupported_features();
+        if !features.is_empty() {
+            return Err(ParseError::UnsupportedFeatures(features));
+        }
+
+        Ok(())
+    }
+
     pub fn get_dl_requests(&self, base_destination: &PathBuf) -> Result<Vec<DownloadRequest>, ParseError> {
         let requests = self.archives.iter()
             .map(|archive| archive.to_dl_request(base_destination))
@@ -58,8 +144,17 @@ impl WabbaModlist {
 
 }
 
+/// Parse a version header field, treating an empty string as absent
+fn parse_optional_version(value: &str) -> Result<Option<ModlistVersion>, ParseError> {
+    if value.trim().is_empty() {
+        Ok(None)
+    } else {
+        value.parse().map(Some)
+    }
+}
+
 /// Raw directive entry from the JSON
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "$type")]
 pub enum Directive {
     /// Extract a file directly from a downloaded archive
@@ -127,19 +222,19 @@ impl Directive {
     }
 
     /// Get the content hash for any directive type
-    pub fn hash(&self) -> &str {
+    pub fn hash(&self) -> Hash {
         match self {
-            Directive::FromArchive(d) => &d.hash,
-            Directive::PatchedFromArchive(d) => &d.hash,
-            Directive::InlineFile(d) => &d.hash,
-            Directive::RemappedInlineFile(d) => &d.hash,
-            Directive::TransformedTexture(d) => &d.hash,
-            Directive::CreateBSA(d) => &d.hash,
-            Directive::MergedPatch(d) => &d.hash,
-            Directive::PropertyFile(d) => &d.hash,
-            Directive::ArchiveMeta(d) => &d.hash,
-            Directive::IgnoredDirectly(d) => &d.hash,
-            Directive::NoMatch(d) => &d.hash,
+            Directive::FromArchive(d) => d.hash,
+            Directive::PatchedFromArchive(d) => d.hash,
+            Directive::InlineFile(d) => d.hash,
+            Directive::RemappedInlineFile(d) => d.hash,
+            Directive::TransformedTexture(d) => d.hash,
+            Directive::CreateBSA(d) => d.hash,
+            Directive::MergedPatch(d) => d.hash,
+            Directive::PropertyFile(d) => d.hash,
+            Directive::ArchiveMeta(d) => d.hash,
+            Directive::IgnoredDirectly(d) => d.hash,
+            Directive::NoMatch(d) => d.hash,
         }
     }
 
@@ -169,6 +264,16 @@ impl Directive {
         )
     }
 
+    /// Hash of the source archive for directives that extract from one
+    pub fn archive_hash(&self) -> Option<Hash> {
+        match self {
+            Directive::FromArchive(d) => Some(d.archive_hash()),
+            Directive::PatchedFromArchive(d) => Some(d.archive_hash()),
+            Directive::TransformedTexture(d) => Some(d.archive_hash()),
+            _ => None,
+        }
+    }
+
     /// Check if this directive is an inline file (embedded data)
     pub fn is_inline(&self) -> bool {
         matches!(self,
@@ -189,10 +294,11 @@ impl Directive {
 }
 
 /// Raw archive entry from the JSON
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "RawArchive")]
 pub struct Archive {
     #[serde(rename = "Hash")]
-    pub hash: String,
+    pub hash: Hash,
 
     #[serde(rename = "Meta")]
     pub meta: String,
@@ -207,28 +313,139 @@ pub struct Archive {
     pub state: DownloadSource,
 }
 
-/// Raw downloader state from JSON (tag-based deserialization using $type field)
-#[derive(Debug, Deserialize, Clone)]
-#[serde(tag = "$type")]
+/// Archive entry exactly as it appears in the JSON, before context is attached to its source
+#[derive(Deserialize)]
+struct RawArchive {
+    #[serde(rename = "Hash")]
+    hash: Hash,
+
+    #[serde(rename = "Meta")]
+    meta: String,
+
+    #[serde(rename = "Name")]
+    name: String,
+
+    #[serde(rename = "Size")]
+    size: u64,
+
+    #[serde(rename = "State")]
+    state: DownloadSource,
+}
+
+impl From<RawArchive> for Archive {
+    fn from(raw: RawArchive) -> Self {
+        let mut state = raw.state;
+
+        // Unknown sources only see their own state object, so give them the archive context here
+        if let DownloadSource::Unknown(unknown) = &mut state {
+            unknown.archive_name = Some(raw.name.clone());
+            if !raw.meta.is_empty() {
+                unknown.meta = Some(raw.meta.clone());
+            }
+        }
+
+        Self {
+            hash: raw.hash,
+            meta: raw.meta,
+            name: raw.name,
+            size: raw.size,
+            state,
+        }
+    }
+}
+
+/// Raw downloader state from JSON, dispatched on the `$type` field
+///
+/// Downloader types we don't handle are kept as `Unknown` with their original
+/// `$type` string and state object, so callers can report exactly what is missing.
+#[derive(Debug, Clone)]
 pub enum ArchiveState {
-    #[serde(rename = "HttpDownloader, Wabbajack.Lib")]
     Http(HttpArchiveState),
 
-    #[serde(rename = "NexusDownloader, Wabbajack.Lib")]
     Nexus(NexusArchiveState),
 
-    #[serde(rename = "GameFileSourceDownloader, Wabbajack.Lib")]
     GameFile(GameFileArchiveState),
 
-    #[serde(rename = "WabbajackCDNDownloader+State, Wabbajack.Lib")]
     WabbajackCDN(WabbajackCDNArchiveState),
 
-    // Handle unknown downloader types gracefully
-    #[serde(other)]
-    Unknown,
+    Unknown {
+        /// The original `$type` value
+        type_name: String,
+        /// The complete state object as it appeared in the modlist
+        raw: serde_json::Value,
+    },
+}
+
+impl<'de> Deserialize<'de> for ArchiveState {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let raw = serde_json::Value::deserialize(deserializer)?;
+        let type_name = raw.get("$type")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| D::Error::missing_field("$type"))?
+            .to_string();
+
+        let state = match type_name.as_str() {
+            ArchiveState::HTTP_TYPE => serde_json::from_value(raw).map(ArchiveState::Http),
+            ArchiveState::NEXUS_TYPE => serde_json::from_value(raw).map(ArchiveState::Nexus),
+            ArchiveState::GAME_FILE_TYPE => serde_json::from_value(raw).map(ArchiveState::GameFile),
+            ArchiveState::WABBAJACK_CDN_TYPE => serde_json::from_value(raw).map(ArchiveState::WabbajackCDN),
+            _ => return Ok(ArchiveState::Unknown { type_name, raw }),
+        };
+
+        state.map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for ArchiveState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let fields = match self {
+            ArchiveState::Http(state) => serde_json::to_value(state),
+            ArchiveState::Nexus(state) => serde_json::to_value(state),
+            ArchiveState::GameFile(state) => serde_json::to_value(state),
+            ArchiveState::WabbajackCDN(state) => serde_json::to_value(state),
+            // Unknown states are written back exactly as they were read
+            ArchiveState::Unknown { raw, .. } => return raw.serialize(serializer),
+        }.map_err(S::Error::custom)?;
+
+        // `$type` sorts first, which Wabbajack's polymorphic deserializer requires
+        let mut object = serde_json::Map::new();
+        object.insert("$type".to_string(), self.type_name().into());
+        if let serde_json::Value::Object(fields) = fields {
+            object.extend(fields);
+        }
+        object.serialize(serializer)
+    }
+}
+
+impl ArchiveState {
+    pub const HTTP_TYPE: &'static str = "HttpDownloader, Wabbajack.Lib";
+    pub const NEXUS_TYPE: &'static str = "NexusDownloader, Wabbajack.Lib";
+    pub const GAME_FILE_TYPE: &'static str = "GameFileSourceDownloader, Wabbajack.Lib";
+    pub const WABBAJACK_CDN_TYPE: &'static str = "WabbajackCDNDownloader+State, Wabbajack.Lib";
+    pub const MANUAL_TYPE: &'static str = "ManualDownloader, Wabbajack.Lib";
+
+    /// The `$type` tag for this state
+    pub fn type_name(&self) -> &str {
+        match self {
+            ArchiveState::Http(_) => Self::HTTP_TYPE,
+            ArchiveState::Nexus(_) => Self::NEXUS_TYPE,
+            ArchiveState::GameFile(_) => Self::GAME_FILE_TYPE,
+            ArchiveState::WabbajackCDN(_) => Self::WABBAJACK_CDN_TYPE,
+            ArchiveState::Unknown { type_name, .. } => type_name,
+        }
+    }
 }
 
 impl Archive {
+    /// Parse the archive's `Meta` INI block
+    pub fn archive_meta(&self) -> ArchiveMeta {
+        ArchiveMeta::parse(&self.meta)
+    }
+
     /// Convert a wabba modlist archive to a structured download request
     fn to_dl_request(
         &self,
@@ -240,7 +457,7 @@ impl Archive {
             base_destination,
             self.name.clone(),
             self.size,
-            self.hash.clone(),
+            self.hash,
         );
 
         Ok(request)
@@ -263,6 +480,36 @@ pub enum ParseError {
 
     #[error("Invalid archive data: {0}")]
     InvalidArchiveData(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Wabbajack archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("No 'modlist' entry found in {0}")]
+    MissingModlistEntry(PathBuf),
+
+    #[error("Inline data not found: {0}")]
+    InlineDataNotFound(String),
+
+    #[error("Invalid version string: '{0}'")]
+    InvalidVersion(String),
+
+    #[error("Modlist was built with Wabbajack {found}, supported versions are {supported}")]
+    UnsupportedWabbajackVersion {
+        found: ModlistVersion,
+        supported: VersionRange,
+    },
+
+    #[error("Modlist requires unsupported features: {}", .0.join(", "))]
+    UnsupportedFeatures(Vec<String>),
+
+    #[error("Modlist stream error: {0}")]
+    Stream(String),
+
+    #[error("Modlist has more than {0} directives before its Archives")]
+    DirectivesBeforeArchives(usize),
 }
 
 #[cfg(test)]
@@ -321,7 +568,7 @@ mod tests {
         let json = r#"{
             "Archives": [
                 {
-                    "Hash": "testHash123",
+                    "Hash": "dGVzdEhhc2g=",
                     "Meta": "[General]\ngameName=skyrimse\nmodID=12345",
                     "Name": "nexus-mod.zip",
                     "Size": 2048,
@@ -346,6 +593,10 @@ mod tests {
         let manifest: WabbaModlist = serde_json::from_str(json).map_err(ParseError::JsonParseError)
             .expect("Failed to parse JSON");
 
+        let meta = manifest.archives[0].archive_meta();
+        assert_eq!(meta.game_name(), Some("skyrimse"));
+        assert_eq!(meta.mod_id(), Some(12345));
+
         let requests = manifest.archives.iter()
             .map(|archive| archive.to_dl_request(&base_destination))
             .collect::<Result<Vec<DownloadRequest>, ParseError>>()
@@ -369,7 +620,7 @@ mod tests {
         let json = r#"{
             "Archives": [
                 {
-                    "Hash": "testHash456",
+                    "Hash": "dGVzdEhzaDI=",
                     "Meta": "[General]\ngameName=skyrimse\nmodID=71371\nfileID=575985",
                     "Name": "unknown-downloader.zip",
                     "Size": 4096,
@@ -395,12 +646,120 @@ mod tests {
 
         let operation = &requests[0];
         if let DownloadSource::Unknown(unknown_source) = &operation.source {
-            assert_eq!(unknown_source.source_type, "Unknown Downloader Type");
-            // With serde from conversion, archive_name and meta are not available in the ArchiveState::Unknown variant
-            assert_eq!(unknown_source.archive_name, None);
-            assert_eq!(unknown_source.meta, None);
+            assert_eq!(unknown_source.source_type, "SomeNewDownloader, Custom.Lib");
+            assert_eq!(unknown_source.downloader_name(), "SomeNewDownloader");
+            assert_eq!(unknown_source.archive_name.as_deref(), Some("unknown-downloader.zip"));
+            assert_eq!(
+                unknown_source.meta.as_deref(),
+                Some("[General]\ngameName=skyrimse\nmodID=71371\nfileID=575985")
+            );
+            let raw = unknown_source.raw_state.as_ref().expect("raw state should be preserved");
+            assert_eq!(raw["CustomField"], "custom value");
+            assert_eq!(raw["AnotherField"], 42);
         } else {
             panic!("Expected Unknown source, got: {:?}", operation.source);
         }
     }
+
+    #[test]
+    fn test_parse_full_header() {
+        let json = r#"{
+            "Archives": [],
+            "Directives": [],
+            "Name": "Header Test",
+            "Version": "1.4.2",
+            "Author": "someone",
+            "GameType": "SkyrimSpecialEdition",
+            "Image": "modlist-image.png",
+            "Readme": "https://example.com/readme",
+            "Website": null,
+            "IsNSFW": true,
+            "WabbajackVersion": "3.7.0.0",
+            "ModManager": "MO2"
+        }"#;
+
+        let modlist = WabbaModlist::parse(json).expect("Failed to parse JSON");
+        assert_eq!(modlist.game_type, "SkyrimSpecialEdition");
+        assert_eq!(modlist.image.as_deref(), Some("modlist-image.png"));
+        assert_eq!(modlist.readme.as_deref(), Some("https://example.com/readme"));
+        assert_eq!(modlist.website, None);
+        assert!(modlist.is_nsfw);
+        assert_eq!(modlist.extra.get("ModManager").and_then(|v| v.as_str()), Some("MO2"));
+        assert_eq!(modlist.parsed_version().unwrap(), Some(ModlistVersion::new(1, 4, 2, 0)));
+        assert_eq!(modlist.parsed_wabbajack_version().unwrap(), Some(ModlistVersion::new(3, 7, 0, 0)));
+        assert!(modlist.check_compatibility().is_ok());
+    }
+
+    #[test]
+    fn test_compatibility_errors() {
+        let old = WabbaModlist::parse(r#"{"Archives": [], "Directives": [], "WabbajackVersion": "1.1.5.0"}"#).unwrap();
+        match old.check_compatibility() {
+            Err(ParseError::UnsupportedWabbajackVersion { found, .. }) => {
+                assert_eq!(found, ModlistVersion::new(1, 1, 5, 0));
+            }
+            other => panic!("Expected UnsupportedWabbajackVersion, got: {:?}", other),
+        }
+
+        let mega = WabbaModlist::parse(r#"{
+            "Archives": [{
+                "Hash": "rXDEtl7gdOU=", "Meta": "", "Name": "a.7z", "Size": 1,
+                "State": {"$type": "MegaDownloader+State, Wabbajack.Lib", "Url": "https://mega.nz/x"}
+            }],
+            "Directives": []
+        }"#).unwrap();
+        match mega.check_compatibility() {
+            Err(ParseError::UnsupportedFeatures(features)) => {
+                assert_eq!(features, vec!["downloader 'MegaDownloader+State'".to_string()]);
+            }
+            other => panic!("Expected UnsupportedFeatures, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_archives_and_directives() {
+        let json = r#"{
+            "Archives": [
+                {
+                    "Hash": "rXDEtl7gdOU=", "Meta": "[General]\ndirectURL=https://example.com/a.7z", "Name": "a.7z", "Size": 1,
+                    "State": {"$type": "HttpDownloader, Wabbajack.Lib", "Headers": ["User-Agent: test"], "Url": "https://example.com/a.7z"}
+                },
+                {
+                    "Hash": "AAAAAAAAAAE=", "Meta": "", "Name": "b.7z", "Size": 2,
+                    "State": {
+                        "$type": "NexusDownloader, Wabbajack.Lib", "Author": "someone", "Description": "desc",
+                        "FileID": 2, "GameName": "SkyrimSpecialEdition", "ImageURL": null, "IsNSFW": false,
+                        "ModID": 1, "Name": "Mod B", "Version": "1.0"
+                    }
+                },
+                {
+                    "Hash": "AAAAAAAAAAI=", "Meta": "", "Name": "Skyrim.esm", "Size": 3,
+                    "State": {"$type": "GameFileSourceDownloader, Wabbajack.Lib", "Game": "SkyrimSpecialEdition", "GameFile": "Data\\Skyrim.esm", "GameVersion": "1.6.640.0", "Hash": "AAAAAAAAAAI="}
+                },
+                {
+                    "Hash": "AAAAAAAAAAM=", "Meta": "", "Name": "c.7z", "Size": 4,
+                    "State": {"$type": "WabbajackCDNDownloader+State, Wabbajack.Lib", "Url": "https://authored-files.wabbajack.org/c.7z_abc"}
+                },
+                {
+                    "Hash": "AAAAAAAAAAQ=", "Meta": "", "Name": "d.7z", "Size": 5,
+                    "State": {"$type": "MegaDownloader+State, Wabbajack.Lib", "Url": "https://mega.nz/file/xyz"}
+                }
+            ],
+            "Directives": [
+                {"$type": "FromArchive", "ArchiveHashPath": ["rXDEtl7gdOU=", "a.esp"], "Hash": "AAAAAAAAAAU=", "Size": 1, "To": "mods\\a\\a.esp"}
+            ],
+            "Name": "Round Trip"
+        }"#;
+
+        let original: serde_json::Value = serde_json::from_str(json).unwrap();
+        let modlist = WabbaModlist::parse(json).unwrap();
+        let written: serde_json::Value = serde_json::from_str(&modlist.to_json().unwrap()).unwrap();
+
+        assert_eq!(written["Archives"], original["Archives"]);
+        assert_eq!(written["Directives"], original["Directives"]);
+        assert_eq!(written["Name"], "Round Trip");
+
+        let reparsed = WabbaModlist::parse(&modlist.to_json().unwrap()).unwrap();
+        assert_eq!(reparsed.archives.len(), 5);
+        assert_eq!(reparsed.archives[0].state, modlist.archives[0].state);
+    }
 }
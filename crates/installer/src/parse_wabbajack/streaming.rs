@@ -0,0 +1,479 @@
+//! Streaming modlist parser
+//!
+//! Large modlists can contain hundreds of thousands of directives. Parsing the
+//! whole document with [`WabbaModlist::parse`] keeps every directive in memory
+//! at once. The functions here read `Archives` eagerly and hand `Directives`
+//! to the caller in fixed-size chunks as they are parsed, so peak memory is
+//! bounded by the chunk size rather than by the size of the manifest.
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread::JoinHandle;
+
+use super::parser::{Archive, Directive, ParseError, WabbaModlist};
+use super::wabbajack_file::{is_wabbajack_archive, MODLIST_ENTRY};
+
+/// Default number of directives handed out per chunk
+pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// Chunks of directives a [`ModlistStream`] holds while waiting for `Archives`
+/// that come after `Directives` in the document
+pub const MAX_CHUNKS_BEFORE_ARCHIVES: usize = 16;
+
+impl WabbaModlist {
+    /// Parse a modlist from a reader without holding every directive in memory
+    ///
+    /// `on_archives` is called as soon as the `Archives` array has been read,
+    /// and `on_directives` receives directives in chunks of at most `chunk_size`.
+    /// The returned modlist carries the archives and header fields, with an
+    /// empty `directives` list.
+    pub fn parse_streaming<R, A, D>(
+        reader: R,
+        chunk_size: usize,
+        mut on_archives: A,
+        mut on_directives: D,
+    ) -> Result<WabbaModlist, ParseError>
+    where
+        R: Read,
+        A: FnMut(&[Archive]) -> Result<(), ParseError>,
+        D: FnMut(Vec<Directive>) -> Result<(), ParseError>,
+    {
+        let mut callback_error = None;
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+
+        let visitor = ModlistVisitor {
+            chunk_size: chunk_size.max(1),
+            on_archives: &mut on_archives,
+            on_directives: &mut on_directives,
+            callback_error: &mut callback_error,
+        };
+
+        let parsed = serde::Deserializer::deserialize_map(&mut deserializer, visitor)
+            .and_then(|parsed| deserializer.end().map(|_| parsed));
+
+        // An error raised by a callback is surfaced as-is rather than as a JSON error
+        if let Some(e) = callback_error {
+            return Err(e);
+        }
+        let (mut header, archives, saw_directives) = parsed?;
+
+        // Deserialize the header through the regular model so both paths stay in sync
+        if let Some(archives) = archives {
+            header.insert("Archives".to_string(), Value::Array(Vec::new()));
+            if saw_directives {
+                header.insert("Directives".to_string(), Value::Array(Vec::new()));
+            }
+            let mut modlist: WabbaModlist = serde_json::from_value(Value::Object(header))?;
+            modlist.archives = archives;
+            Ok(modlist)
+        } else {
+            // Let serde produce the usual "missing field" error
+            Ok(serde_json::from_value(Value::Object(header))?)
+        }
+    }
+
+    /// Stream a modlist from disk, accepting either a `.wabbajack` archive or raw modlist JSON
+    pub fn parse_streaming_from_path<P, A, D>(
+        path: P,
+        chunk_size: usize,
+        on_archives: A,
+        on_directives: D,
+    ) -> Result<WabbaModlist, ParseError>
+    where
+        P: AsRef<Path>,
+        A: FnMut(&[Archive]) -> Result<(), ParseError>,
+        D: FnMut(Vec<Directive>) -> Result<(), ParseError>,
+    {
+        let path = path.as_ref();
+        if is_wabbajack_archive(path)? {
+            let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+            let entry = archive.by_name(MODLIST_ENTRY).map_err(|e| match e {
+                zip::result::ZipError::FileNotFound => ParseError::MissingModlistEntry(path.to_path_buf()),
+                other => ParseError::Zip(other),
+            })?;
+            Self::parse_streaming(entry, chunk_size, on_archives, on_directives)
+        } else {
+            Self::parse_streaming(File::open(path)?, chunk_size, on_archives, on_directives)
+        }
+    }
+}
+
+/// Top-level visitor: archives eagerly, directives in chunks, everything else kept as JSON
+struct ModlistVisitor<'a, A, D> {
+    chunk_size: usize,
+    on_archives: &'a mut A,
+    on_directives: &'a mut D,
+    callback_error: &'a mut Option<ParseError>,
+}
+
+impl<'de, A, D> Visitor<'de> for ModlistVisitor<'_, A, D>
+where
+    A: FnMut(&[Archive]) -> Result<(), ParseError>,
+    D: FnMut(Vec<Directive>) -> Result<(), ParseError>,
+{
+    type Value = (Map<String, Value>, Option<Vec<Archive>>, bool);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a Wabbajack modlist object")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+        let mut header = Map::new();
+        let mut archives = None;
+        let mut saw_directives = false;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "Archives" => {
+                    let parsed: Vec<Archive> = map.next_value()?;
+                    if let Err(e) = (self.on_archives)(&parsed) {
+                        *self.callback_error = Some(e);
+                        return Err(de::Error::custom("archive callback failed"));
+                    }
+                    archives = Some(parsed);
+                }
+                "Directives" => {
+                    map.next_value_seed(DirectiveChunks {
+                        chunk_size: self.chunk_size,
+                        on_directives: &mut *self.on_directives,
+                        callback_error: &mut *self.callback_error,
+                    })?;
+                    saw_directives = true;
+                }
+                _ => {
+                    let value: Value = map.next_value()?;
+                    header.insert(key, value);
+                }
+            }
+        }
+
+        Ok((header, archives, saw_directives))
+    }
+}
+
+/// Seed that walks the `Directives` array and flushes it in chunks
+struct DirectiveChunks<'a, D> {
+    chunk_size: usize,
+    on_directives: &'a mut D,
+    callback_error: &'a mut Option<ParseError>,
+}
+
+impl<'de, D> DeserializeSeed<'de> for DirectiveChunks<'_, D>
+where
+    D: FnMut(Vec<Directive>) -> Result<(), ParseError>,
+{
+    type Value = ();
+
+    fn deserialize<De: de::Deserializer<'de>>(self, deserializer: De) -> Result<(), De::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, D> Visitor<'de> for DirectiveChunks<'_, D>
+where
+    D: FnMut(Vec<Directive>) -> Result<(), ParseError>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of directives")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<(), S::Error> {
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+
+        while let Some(directive) = seq.next_element::<Directive>()? {
+            chunk.push(directive);
+            if chunk.len() >= self.chunk_size {
+                let full = std::mem::replace(&mut chunk, Vec::with_capacity(self.chunk_size));
+                if let Err(e) = (self.on_directives)(full) {
+                    *self.callback_error = Some(e);
+                    return Err(de::Error::custom("directive callback failed"));
+                }
+            }
+        }
+
+        if !chunk.is_empty() && let Err(e) = (self.on_directives)(chunk) {
+            *self.callback_error = Some(e);
+            return Err(de::Error::custom("directive callback failed"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Message passed from the parser thread to a [`ModlistStream`]
+enum StreamMessage {
+    Archives(Vec<Archive>),
+    Directives(Vec<Directive>),
+}
+
+/// Pull-based view of a streaming parse
+///
+/// The manifest is parsed on a background thread. Archives are available
+/// as soon as the stream is created, and directives are yielded through the
+/// `Iterator` implementation. At most a couple of chunks are buffered between
+/// the parser and the consumer, which keeps memory bounded.
+///
+/// Wabbajack writes `Archives` before `Directives`. A document in the other
+/// order has its directives held until the archives arrive, up to
+/// [`MAX_CHUNKS_BEFORE_ARCHIVES`] chunks; past that, creating the stream fails
+/// with [`ParseError::DirectivesBeforeArchives`].
+pub struct ModlistStream {
+    archives: Vec<Archive>,
+    receiver: Receiver<StreamMessage>,
+    pending: std::vec::IntoIter<Directive>,
+    buffered: std::collections::VecDeque<Vec<Directive>>,
+    handle: Option<JoinHandle<Result<WabbaModlist, ParseError>>>,
+}
+
+impl ModlistStream {
+    /// Start streaming a modlist from any reader
+    pub fn from_reader<R: Read + Send + 'static>(reader: R, chunk_size: usize) -> Result<Self, ParseError> {
+        Self::spawn(chunk_size, move |chunk_size, on_archives, on_directives| {
+            WabbaModlist::parse_streaming(reader, chunk_size, on_archives, on_directives)
+        })
+    }
+
+    /// Start streaming a modlist from a `.wabbajack` archive or raw modlist JSON on disk
+    pub fn open<P: AsRef<Path>>(path: P, chunk_size: usize) -> Result<Self, ParseError> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        Self::spawn(chunk_size, move |chunk_size, on_archives, on_directives| {
+            WabbaModlist::parse_streaming_from_path(&path, chunk_size, on_archives, on_directives)
+        })
+    }
+
+    fn spawn<F>(chunk_size: usize, parse: F) -> Result<Self, ParseError>
+    where
+        F: FnOnce(
+                usize,
+                &mut dyn FnMut(&[Archive]) -> Result<(), ParseError>,
+                &mut dyn FnMut(Vec<Directive>) -> Result<(), ParseError>,
+            ) -> Result<WabbaModlist, ParseError>
+            + Send
+            + 'static,
+    {
+        let (sender, receiver) = sync_channel::<StreamMessage>(2);
+
+        let handle = std::thread::spawn(move || {
+            let archive_sender = sender.clone();
+            let mut on_archives = move |archives: &[Archive]| {
+                // A closed channel means the consumer went away; stop parsing quietly
+                archive_sender.send(StreamMessage::Archives(archives.to_vec()))
+                    .map_err(|_| ParseError::Stream("stream closed".to_string()))
+            };
+            let mut on_directives = move |directives: Vec<Directive>| {
+                sender.send(StreamMessage::Directives(directives))
+                    .map_err(|_| ParseError::Stream("stream closed".to_string()))
+            };
+            parse(chunk_size, &mut on_archives, &mut on_directives)
+        });
+
+        let mut stream = Self {
+            archives: Vec::new(),
+            receiver,
+            pending: Vec::new().into_iter(),
+            buffered: std::collections::VecDeque::new(),
+            handle: Some(handle),
+        };
+
+        // Wait for the archives, buffering a bounded number of directive chunks
+        // that precede them in the document. Dropping the stream on failure
+        // closes the channel, which stops the parser.
+        loop {
+            match stream.receiver.recv() {
+                Ok(StreamMessage::Archives(archives)) => {
+                    stream.archives = archives;
+                    return Ok(stream);
+                }
+                Ok(StreamMessage::Directives(_)) if stream.buffered.len() >= MAX_CHUNKS_BEFORE_ARCHIVES => {
+                    let buffered = stream.buffered.iter().map(Vec::len).sum();
+                    return Err(ParseError::DirectivesBeforeArchives(buffered));
+                }
+                Ok(StreamMessage::Directives(chunk)) => stream.buffered.push_back(chunk),
+                Err(_) => {
+                    // Parser finished without sending archives: report its error
+                    return match stream.join() {
+                        Err(e) => Err(e),
+                        Ok(_) => Err(ParseError::InvalidArchiveData("modlist has no Archives".to_string())),
+                    };
+                }
+            }
+        }
+    }
+
+    /// The archives of the modlist, available before any directive is read
+    pub fn archives(&self) -> &[Archive] {
+        &self.archives
+    }
+
+    /// Take the next chunk of directives, if any
+    pub fn next_chunk(&mut self) -> Option<Vec<Directive>> {
+        let rest: Vec<Directive> = std::mem::replace(&mut self.pending, Vec::new().into_iter()).collect();
+        if !rest.is_empty() {
+            return Some(rest);
+        }
+        if let Some(chunk) = self.buffered.pop_front() {
+            return Some(chunk);
+        }
+        loop {
+            match self.receiver.recv() {
+                Ok(StreamMessage::Directives(chunk)) => return Some(chunk),
+                Ok(StreamMessage::Archives(_)) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Drain the remaining directives and return the modlist header and archives
+    pub fn finish(mut self) -> Result<WabbaModlist, ParseError> {
+        while self.next_chunk().is_some() {}
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<WabbaModlist, ParseError> {
+        match self.handle.take() {
+            Some(handle) => handle.join().map_err(|_| {
+                ParseError::Stream("parser thread panicked".to_string())
+            })?,
+            None => Err(ParseError::Stream("stream already finished".to_string())),
+        }
+    }
+}
+
+impl Iterator for ModlistStream {
+    type Item = Directive;
+
+    fn next(&mut self) -> Option<Directive> {
+        loop {
+            if let Some(directive) = self.pending.next() {
+                return Some(directive);
+            }
+            self.pending = self.next_chunk()?.into_iter();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modlist_json(directive_count: usize) -> String {
+        let directives: Vec<String> = (0..directive_count)
+            .map(|i| format!(
+                r#"{{"$type": "InlineFile", "To": "file{}.txt", "Hash": "rXDEtl7gdOU=", "Size": 1, "SourceDataID": "id{}"}}"#,
+                i, i
+            ))
+            .collect();
+        format!(
+            r#"{{
+                "Name": "Streamed",
+                "Archives": [
+                    {{
+                        "Hash": "rXDEtl7gdOU=",
+                        "Meta": "",
+                        "Name": "a.zip",
+                        "Size": 10,
+                        "State": {{"$type": "HttpDownloader, Wabbajack.Lib", "Url": "https://example.com/a.zip"}}
+                    }}
+                ],
+                "Directives": [{}],
+                "Version": "1.0"
+            }}"#,
+            directives.join(",")
+        )
+    }
+
+    #[test]
+    fn test_parse_streaming_chunks() {
+        let json = modlist_json(10);
+        let mut archive_count = None;
+        let mut chunk_sizes = Vec::new();
+
+        let modlist = WabbaModlist::parse_streaming(
+            json.as_bytes(),
+            4,
+            |archives| {
+                archive_count = Some(archives.len());
+                Ok(())
+            },
+            |chunk| {
+                chunk_sizes.push(chunk.len());
+                Ok(())
+            },
+        ).unwrap();
+
+        assert_eq!(archive_count, Some(1));
+        assert_eq!(chunk_sizes, vec![4, 4, 2]);
+        assert_eq!(modlist.name, "Streamed");
+        assert_eq!(modlist.version, "1.0");
+        assert_eq!(modlist.archives.len(), 1);
+        assert!(modlist.directives.is_empty());
+    }
+
+    #[test]
+    fn test_parse_streaming_callback_error_stops_parse() {
+        let json = modlist_json(10);
+        let result = WabbaModlist::parse_streaming(
+            json.as_bytes(),
+            2,
+            |_| Ok(()),
+            |_| Err(ParseError::InvalidArchiveData("stop".to_string())),
+        );
+
+        match result {
+            Err(ParseError::InvalidArchiveData(msg)) => assert_eq!(msg, "stop"),
+            other => panic!("Expected callback error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_streaming_missing_archives() {
+        let result = WabbaModlist::parse_streaming(r#"{"Directives": []}"#.as_bytes(), 2, |_| Ok(()), |_| Ok(()));
+        assert!(matches!(result, Err(ParseError::JsonParseError(_))));
+    }
+
+    #[test]
+    fn test_modlist_stream_iterator() {
+        let json = modlist_json(25);
+        let mut stream = ModlistStream::from_reader(std::io::Cursor::new(json.into_bytes()), 8).unwrap();
+        assert_eq!(stream.archives().len(), 1);
+
+        let first = stream.next().unwrap();
+        assert_eq!(first.to(), "file0.txt");
+        assert_eq!(stream.by_ref().count(), 24);
+
+        let header = stream.finish().unwrap();
+        assert_eq!(header.name, "Streamed");
+    }
+
+    #[test]
+    fn test_modlist_stream_bounds_directives_before_archives() {
+        let directives_first = |count: usize| {
+            let directives: Vec<String> = (0..count)
+                .map(|i| format!(r#"{{"$type": "InlineFile", "To": "file{}.txt", "Hash": "rXDEtl7gdOU=", "Size": 1, "SourceDataID": "id{}"}}"#, i, i))
+                .collect();
+            format!(
+                r#"{{"Name": "Reordered", "Directives": [{}], "Archives": [{{"Hash": "rXDEtl7gdOU=", "Meta": "", "Name": "a.zip", "Size": 10,
+                    "State": {{"$type": "HttpDownloader, Wabbajack.Lib", "Url": "https://example.com/a.zip"}}}}], "Version": "1.0"}}"#,
+                directives.join(",")
+            )
+        };
+
+        // A few chunks are held until the archives arrive
+        let stream = ModlistStream::from_reader(std::io::Cursor::new(directives_first(3).into_bytes()), 1).unwrap();
+        assert_eq!(stream.archives().len(), 1);
+        assert_eq!(stream.count(), 3);
+
+        let result = ModlistStream::from_reader(
+            std::io::Cursor::new(directives_first(MAX_CHUNKS_BEFORE_ARCHIVES + 1).into_bytes()),
+            1,
+        );
+        assert!(matches!(result, Err(ParseError::DirectivesBeforeArchives(n)) if n == MAX_CHUNKS_BEFORE_ARCHIVES));
+    }
+}
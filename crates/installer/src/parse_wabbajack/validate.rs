@@ -0,0 +1,335 @@
+//! Structural validation of parsed modlists
+//!
+//! [`WabbaModlist::validate`] runs a lint pass over a modlist and returns a
+//! [`ValidationReport`] that UIs can render before any download starts.
+//! For streamed modlists, drive a [`ModlistValidator`] directly: feed it the
+//! archives, then each chunk of directives as it arrives.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::parser::{Archive, Directive, WabbaModlist};
+use crate::hash::Hash;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing, installation is unaffected
+    Info,
+    /// Suspicious, installation will likely still work
+    Warning,
+    /// Installation will fail or produce wrong output
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// What kind of problem a diagnostic describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticCode {
+    /// Two directives write to the same `To` path
+    DuplicateTo,
+    /// A `To` path is absolute or climbs out of the install root
+    PathEscapesRoot,
+    /// A directive references an archive hash that is not in `Archives`
+    MissingArchive,
+    /// An archive declares a size of zero
+    ZeroSizeArchive,
+    /// An archive has no hash to verify against
+    MissingArchiveHash,
+    /// An `IgnoredDirectly` directive was left in the published list
+    IgnoredDirective,
+    /// A `NoMatch` directive was left in the published list
+    NoMatchDirective,
+}
+
+impl DiagnosticCode {
+    /// Default severity for this kind of problem
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticCode::DuplicateTo
+            | DiagnosticCode::PathEscapesRoot
+            | DiagnosticCode::MissingArchive
+            | DiagnosticCode::MissingArchiveHash => Severity::Error,
+            DiagnosticCode::ZeroSizeArchive
+            | DiagnosticCode::IgnoredDirective
+            | DiagnosticCode::NoMatchDirective => Severity::Warning,
+        }
+    }
+}
+
+/// A single finding from the lint pass
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    /// Human-readable description of the problem
+    pub message: String,
+    /// The `To` path or archive name the diagnostic is about
+    pub subject: String,
+}
+
+impl Diagnostic {
+    fn new<S: Into<String>>(code: DiagnosticCode, subject: S, message: String) -> Self {
+        Self {
+            severity: code.severity(),
+            code,
+            message,
+            subject: subject.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Result of validating a modlist
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    /// True if no diagnostics were produced at all
+    pub fn is_clean(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// True if any diagnostic is an error
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    /// Number of diagnostics with the given severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == severity).count()
+    }
+
+    /// Diagnostics with the given severity
+    pub fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(move |d| d.severity == severity)
+    }
+}
+
+/// Incremental validator, usable with both whole and streamed modlists
+pub struct ModlistValidator {
+    archive_hashes: HashSet<Hash>,
+    /// Normalised `To` path -> first spelling seen
+    seen_paths: HashMap<String, String>,
+    report: ValidationReport,
+}
+
+impl ModlistValidator {
+    /// Start validating a modlist with the given archives
+    pub fn new(archives: &[Archive]) -> Self {
+        let mut report = ValidationReport::default();
+
+        for archive in archives {
+            if archive.hash.is_empty() {
+                report.diagnostics.push(Diagnostic::new(
+                    DiagnosticCode::MissingArchiveHash,
+                    &archive.name,
+                    format!("Archive '{}' has no hash", archive.name),
+                ));
+            }
+            if archive.size == 0 {
+                report.diagnostics.push(Diagnostic::new(
+                    DiagnosticCode::ZeroSizeArchive,
+                    &archive.name,
+                    format!("Archive '{}' has a size of zero", archive.name),
+                ));
+            }
+        }
+
+        Self {
+            archive_hashes: archives.iter().map(|a| a.hash).collect(),
+            seen_paths: HashMap::new(),
+            report,
+        }
+    }
+
+    /// Check a single directive
+    pub fn check_directive(&mut self, directive: &Directive) {
+        let to = directive.to();
+
+        match normalize_install_path(to) {
+            Some(normalized) => {
+                if let Some(first) = self.seen_paths.get(&normalized) {
+                    let message = if first == to {
+                        format!("Multiple directives write to '{}'", to)
+                    } else {
+                        format!("Multiple directives write to '{}' (also written as '{}')", to, first)
+                    };
+                    self.report.diagnostics.push(Diagnostic::new(DiagnosticCode::DuplicateTo, to, message));
+                } else {
+                    self.seen_paths.insert(normalized, to.to_string());
+                }
+            }
+            None => {
+                self.report.diagnostics.push(Diagnostic::new(
+                    DiagnosticCode::PathEscapesRoot,
+                    to,
+                    format!("Path '{}' escapes the install root", to),
+                ));
+            }
+        }
+
+        if let Some(hash) = directive.archive_hash()
+            && !self.archive_hashes.contains(&hash)
+        {
+            self.report.diagnostics.push(Diagnostic::new(
+                DiagnosticCode::MissingArchive,
+                to,
+                format!("'{}' references archive {} which is not in Archives", to, hash),
+            ));
+        }
+
+        match directive {
+            Directive::IgnoredDirectly(_) => self.report.diagnostics.push(Diagnostic::new(
+                DiagnosticCode::IgnoredDirective,
+                to,
+                format!("IgnoredDirectly entry for '{}' should not be in a published list", to),
+            )),
+            Directive::NoMatch(_) => self.report.diagnostics.push(Diagnostic::new(
+                DiagnosticCode::NoMatchDirective,
+                to,
+                format!("NoMatch entry for '{}' should not be in a published list", to),
+            )),
+            _ => {}
+        }
+    }
+
+    /// Finish validation and return the report, errors first
+    pub fn finish(mut self) -> ValidationReport {
+        self.report.diagnostics.sort_by_key(|d| std::cmp::Reverse(d.severity));
+        self.report
+    }
+}
+
+impl WabbaModlist {
+    /// Lint the modlist for structural problems
+    pub fn validate(&self) -> ValidationReport {
+        let mut validator = ModlistValidator::new(&self.archives);
+        for directive in &self.directives {
+            validator.check_directive(directive);
+        }
+        validator.finish()
+    }
+}
+
+/// Normalise a relative install path for comparison
+///
+/// Wabbajack paths use `\` and are compared case-insensitively, as on Windows.
+/// Returns `None` if the path is absolute or climbs above the install root.
+fn normalize_install_path(path: &str) -> Option<String> {
+    let is_drive_path = path.len() >= 2 && path.as_bytes()[1] == b':' && path.as_bytes()[0].is_ascii_alphabetic();
+    if path.starts_with(['/', '\\']) || is_drive_path {
+        return None;
+    }
+
+    let mut components: Vec<String> = Vec::new();
+    for component in path.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            other => components.push(other.to_lowercase()),
+        }
+    }
+
+    if components.is_empty() {
+        None
+    } else {
+        Some(components.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modlist(archives: &str, directives: &str) -> WabbaModlist {
+        WabbaModlist::parse(&format!(r#"{{"Archives": [{}], "Directives": [{}]}}"#, archives, directives)).unwrap()
+    }
+
+    const ARCHIVE: &str = r#"{
+        "Hash": "rXDEtl7gdOU=", "Meta": "", "Name": "a.7z", "Size": 10,
+        "State": {"$type": "HttpDownloader, Wabbajack.Lib", "Url": "https://example.com/a.7z"}
+    }"#;
+
+    fn from_archive(to: &str, archive_hash: &str) -> String {
+        format!(
+            r#"{{"$type": "FromArchive", "To": "{}", "Hash": "AAAAAAAAAAA=", "Size": 1, "ArchiveHashPath": ["{}", "file.esp"]}}"#,
+            to.replace('\\', "\\\\"), archive_hash
+        )
+    }
+
+    #[test]
+    fn test_clean_modlist() {
+        let report = modlist(ARCHIVE, &from_archive("mods\\a\\file.esp", "rXDEtl7gdOU=")).validate();
+        assert!(report.is_clean(), "unexpected diagnostics: {:?}", report.diagnostics);
+    }
+
+    #[test]
+    fn test_directive_problems() {
+        let directives = [
+            from_archive("mods\\a\\file.esp", "rXDEtl7gdOU="),
+            from_archive("MODS/A/FILE.ESP", "rXDEtl7gdOU="),
+            from_archive("..\\..\\Windows\\evil.dll", "rXDEtl7gdOU="),
+            from_archive("mods\\b\\other.esp", "AAAAAAAAAAk="),
+            r#"{"$type": "NoMatch", "To": "mods\\c\\stray.txt", "Hash": "AAAAAAAAAAA=", "Size": 1, "Reason": "no match"}"#.to_string(),
+        ].join(",");
+
+        let report = modlist(ARCHIVE, &directives).validate();
+        let codes: Vec<DiagnosticCode> = report.diagnostics.iter().map(|d| d.code).collect();
+
+        assert!(report.has_errors());
+        assert_eq!(report.count(Severity::Error), 3);
+        assert_eq!(report.count(Severity::Warning), 1);
+        assert!(codes.contains(&DiagnosticCode::DuplicateTo));
+        assert!(codes.contains(&DiagnosticCode::PathEscapesRoot));
+        assert!(codes.contains(&DiagnosticCode::MissingArchive));
+        assert_eq!(codes.last(), Some(&DiagnosticCode::NoMatchDirective));
+    }
+
+    #[test]
+    fn test_archive_problems() {
+        let archives = r#"{
+            "Hash": "AAAAAAAAAAA=", "Meta": "", "Name": "empty.7z", "Size": 0,
+            "State": {"$type": "HttpDownloader, Wabbajack.Lib", "Url": "https://example.com/empty.7z"}
+        }"#;
+        let report = modlist(archives, "").validate();
+
+        assert_eq!(report.count(Severity::Error), 1);
+        assert_eq!(report.diagnostics[0].code, DiagnosticCode::MissingArchiveHash);
+        assert_eq!(report.diagnostics[1].code, DiagnosticCode::ZeroSizeArchive);
+        assert_eq!(report.diagnostics[1].subject, "empty.7z");
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["diagnostics"][0]["severity"], "error");
+        assert_eq!(json["diagnostics"][0]["code"], "missing_archive_hash");
+    }
+
+    #[test]
+    fn test_normalize_install_path() {
+        assert_eq!(normalize_install_path("mods\\A\\..\\b.esp").as_deref(), Some("mods/b.esp"));
+        assert_eq!(normalize_install_path("C:\\Windows\\x.dll"), None);
+        assert_eq!(normalize_install_path("/etc/passwd"), None);
+        assert_eq!(normalize_install_path("a\\..\\..\\b"), None);
+    }
+}
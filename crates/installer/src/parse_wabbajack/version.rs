@@ -0,0 +1,132 @@
+//! Modlist and Wabbajack version handling
+//!
+//! Wabbajack writes .NET `System.Version` strings (`"3.0.1.7"`), which have
+//! up to four numeric components and are not valid semver. [`ModlistVersion`]
+//! parses them into comparable values, and [`SUPPORTED_WABBAJACK_VERSIONS`]
+//! describes which modlist formats this installer understands.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use super::parser::ParseError;
+
+/// A dotted numeric version such as `1.2`, `3.0.1` or `3.0.1.7`
+///
+/// Missing components compare as zero, so `3.0` == `3.0.0.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModlistVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+    pub revision: u32,
+}
+
+impl ModlistVersion {
+    /// Create a version from its four components
+    pub const fn new(major: u32, minor: u32, build: u32, revision: u32) -> Self {
+        Self { major, minor, build, revision }
+    }
+
+    fn as_tuple(&self) -> (u32, u32, u32, u32) {
+        (self.major, self.minor, self.build, self.revision)
+    }
+}
+
+impl Ord for ModlistVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_tuple().cmp(&other.as_tuple())
+    }
+}
+
+impl PartialOrd for ModlistVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for ModlistVersion {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().trim_start_matches(['v', 'V']);
+        let parts: Vec<&str> = trimmed.split('.').collect();
+
+        if trimmed.is_empty() || parts.len() > 4 {
+            return Err(ParseError::InvalidVersion(s.to_string()));
+        }
+
+        let mut components = [0u32; 4];
+        for (component, part) in components.iter_mut().zip(&parts) {
+            *component = part.parse().map_err(|_| ParseError::InvalidVersion(s.to_string()))?;
+        }
+
+        let [major, minor, build, revision] = components;
+        Ok(Self::new(major, minor, build, revision))
+    }
+}
+
+impl fmt::Display for ModlistVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.major, self.minor, self.build, self.revision)
+    }
+}
+
+/// A half-open range of versions: `min <= v < max`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    pub min: ModlistVersion,
+    pub max: ModlistVersion,
+}
+
+impl VersionRange {
+    /// Check whether a version falls inside the range
+    pub fn contains(&self, version: &ModlistVersion) -> bool {
+        *version >= self.min && *version < self.max
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ">= {}, < {}", self.min, self.max)
+    }
+}
+
+/// Wabbajack versions whose modlist format this parser supports
+pub const SUPPORTED_WABBAJACK_VERSIONS: VersionRange = VersionRange {
+    min: ModlistVersion::new(2, 0, 0, 0),
+    max: ModlistVersion::new(5, 0, 0, 0),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_compare() {
+        let v: ModlistVersion = "3.0.1.7".parse().unwrap();
+        assert_eq!(v, ModlistVersion::new(3, 0, 1, 7));
+        assert_eq!(v.to_string(), "3.0.1.7");
+
+        assert_eq!("3.0".parse::<ModlistVersion>().unwrap(), ModlistVersion::new(3, 0, 0, 0));
+        assert_eq!("v1.2.3".parse::<ModlistVersion>().unwrap(), ModlistVersion::new(1, 2, 3, 0));
+        assert!("2.9.9.9".parse::<ModlistVersion>().unwrap() < v);
+    }
+
+    #[test]
+    fn test_invalid_versions() {
+        for input in ["", "1.2.3.4.5", "1.x", "beta"] {
+            assert!(matches!(
+                input.parse::<ModlistVersion>(),
+                Err(ParseError::InvalidVersion(_))
+            ), "{input:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_supported_range() {
+        assert!(SUPPORTED_WABBAJACK_VERSIONS.contains(&"3.7.0.0".parse().unwrap()));
+        assert!(!SUPPORTED_WABBAJACK_VERSIONS.contains(&"1.1.5.0".parse().unwrap()));
+        assert!(!SUPPORTED_WABBAJACK_VERSIONS.contains(&"5.0".parse().unwrap()));
+    }
+}
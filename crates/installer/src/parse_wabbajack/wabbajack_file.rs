@@ -0,0 +1,295 @@
+//! `.wabbajack` archive access
+//!
+//! A `.wabbajack` file is a ZIP archive containing the modlist manifest in an
+//! entry named `modlist`, plus one entry per inline blob (named by the
+//! `SourceDataID` referenced from directives such as `InlineFile`).
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::parser::{ParseError, WabbaModlist};
+
+/// Name of the zip entry holding the modlist manifest JSON
+pub const MODLIST_ENTRY: &str = "modlist";
+
+/// An opened `.wabbajack` file with its parsed manifest
+///
+/// The manifest is parsed once on open. Inline data entries are read lazily
+/// on request, so opening a multi-gigabyte modlist stays cheap.
+pub struct WabbajackFile {
+    /// Path of the archive on disk
+    path: PathBuf,
+    /// Underlying zip archive (reads need `&mut`, so it is behind a lock)
+    archive: Mutex<ZipArchive<BufReader<File>>>,
+    /// Parsed modlist manifest
+    modlist: WabbaModlist,
+}
+
+impl std::fmt::Debug for WabbajackFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WabbajackFile")
+            .field("path", &self.path)
+            .field("name", &self.modlist.name)
+            .field("archives", &self.modlist.archives.len())
+            .field("directives", &self.modlist.directives.len())
+            .finish()
+    }
+}
+
+impl WabbajackFile {
+    /// Open a `.wabbajack` file and parse its `modlist` entry
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let mut archive = ZipArchive::new(BufReader::new(file))?;
+
+        let modlist = {
+            let entry = archive.by_name(MODLIST_ENTRY).map_err(|e| match e {
+                zip::result::ZipError::FileNotFound => ParseError::MissingModlistEntry(path.clone()),
+                other => ParseError::Zip(other),
+            })?;
+            serde_json::from_reader(BufReader::new(entry))?
+        };
+
+        Ok(Self {
+            path,
+            archive: Mutex::new(archive),
+            modlist,
+        })
+    }
+
+    /// Path of the archive on disk
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The parsed modlist manifest
+    pub fn modlist(&self) -> &WabbaModlist {
+        &self.modlist
+    }
+
+    /// Mutable access to the manifest, for patching before [`save_as`](Self::save_as)
+    pub fn modlist_mut(&mut self) -> &mut WabbaModlist {
+        &mut self.modlist
+    }
+
+    /// Write the manifest and all inline data to a `.wabbajack` file
+    ///
+    /// Inline entries are copied as-is without recompression. The output is
+    /// written to a temporary file next to `path` and moved into place, so
+    /// saving over the file this handle was opened from is safe.
+    pub fn save_as<P: AsRef<Path>>(&self, path: P) -> Result<(), ParseError> {
+        let path = path.as_ref();
+        let parent = path.parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let temp = tempfile::NamedTempFile::new_in(parent)?;
+
+        {
+            let mut writer = ZipWriter::new(BufWriter::new(temp.as_file()));
+            write_manifest(&mut writer, &self.modlist)?;
+
+            let mut archive = self.archive.lock().unwrap();
+            for index in 0..archive.len() {
+                let entry = archive.by_index_raw(index)?;
+                if entry.name() != MODLIST_ENTRY {
+                    writer.raw_copy_file(entry)?;
+                }
+            }
+            writer.finish()?.flush()?;
+        }
+
+        temp.persist(path).map_err(|e| ParseError::Io(e.error))?;
+        Ok(())
+    }
+
+    /// Consume the archive handle and keep only the parsed manifest
+    pub fn into_modlist(self) -> WabbaModlist {
+        self.modlist
+    }
+
+    /// List the ids of all inline data entries (every entry except the manifest)
+    pub fn inline_data_ids(&self) -> Vec<String> {
+        let archive = self.archive.lock().unwrap();
+        archive.file_names()
+            .filter(|name| *name != MODLIST_ENTRY)
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    /// Check whether an inline data entry exists
+    pub fn has_inline_data(&self, source_data_id: &str) -> bool {
+        source_data_id != MODLIST_ENTRY
+            && self.archive.lock().unwrap().index_for_name(source_data_id).is_some()
+    }
+
+    /// Get the uncompressed size of an inline data entry
+    pub fn inline_data_size(&self, source_data_id: &str) -> Result<u64, ParseError> {
+        let mut archive = self.archive.lock().unwrap();
+        let entry = Self::inline_entry(&mut archive, source_data_id)?;
+        Ok(entry.size())
+    }
+
+    /// Read an inline data entry into memory
+    pub fn read_inline_data(&self, source_data_id: &str) -> Result<Vec<u8>, ParseError> {
+        let mut archive = self.archive.lock().unwrap();
+        let mut entry = Self::inline_entry(&mut archive, source_data_id)?;
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Look up an inline entry by id, mapping a missing entry to a parse error
+    fn inline_entry<'a>(
+        archive: &'a mut ZipArchive<BufReader<File>>,
+        source_data_id: &str,
+    ) -> Result<zip::read::ZipFile<'a>, ParseError> {
+        if source_data_id == MODLIST_ENTRY {
+            return Err(ParseError::InlineDataNotFound(source_data_id.to_string()));
+        }
+        archive.by_name(source_data_id).map_err(|e| match e {
+            zip::result::ZipError::FileNotFound => ParseError::InlineDataNotFound(source_data_id.to_string()),
+            other => ParseError::Zip(other),
+        })
+    }
+}
+
+/// Pack a modlist and its inline data into a new `.wabbajack` file
+pub fn write_wabbajack<P, I>(path: P, modlist: &WabbaModlist, inline_data: I) -> Result<(), ParseError>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = (String, Vec<u8>)>,
+{
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(path)?));
+    write_manifest(&mut writer, modlist)?;
+
+    for (source_data_id, data) in inline_data {
+        writer.start_file(source_data_id, SimpleFileOptions::default().compression_method(CompressionMethod::Deflated))?;
+        writer.write_all(&data)?;
+    }
+
+    writer.finish()?.flush()?;
+    Ok(())
+}
+
+/// Write the modlist manifest as the `modlist` entry
+fn write_manifest<W: Write + Seek>(writer: &mut ZipWriter<W>, modlist: &WabbaModlist) -> Result<(), ParseError> {
+    writer.start_file(MODLIST_ENTRY, SimpleFileOptions::default().compression_method(CompressionMethod::Deflated))?;
+    serde_json::to_writer(&mut *writer, modlist)?;
+    Ok(())
+}
+
+/// Check whether a file starts with the ZIP local file header signature
+pub fn is_wabbajack_archive<P: AsRef<Path>>(path: P) -> Result<bool, ParseError> {
+    let mut magic = [0u8; 4];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(magic == *b"PK\x03\x04"),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODLIST_JSON: &str = r#"{
+        "Archives": [],
+        "Directives": [
+            {
+                "$type": "InlineFile",
+                "To": "profiles\\Default\\modlist.txt",
+                "Hash": "rXDEtl7gdOU=",
+                "Size": 5,
+                "SourceDataID": "0b7d2c4e-inline"
+            }
+        ],
+        "Name": "Zipped Modlist"
+    }"#;
+
+    fn write_wabbajack(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_open_wabbajack_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.wabbajack");
+        write_wabbajack(&path, &[
+            (MODLIST_ENTRY, MODLIST_JSON.as_bytes()),
+            ("0b7d2c4e-inline", b"hello"),
+        ]);
+
+        assert!(is_wabbajack_archive(&path).unwrap());
+
+        let wabbajack = WabbajackFile::open(&path).unwrap();
+        assert_eq!(wabbajack.modlist().name, "Zipped Modlist");
+        assert_eq!(wabbajack.modlist().directives.len(), 1);
+        assert_eq!(wabbajack.inline_data_ids(), vec!["0b7d2c4e-inline".to_string()]);
+        assert!(wabbajack.has_inline_data("0b7d2c4e-inline"));
+        assert!(!wabbajack.has_inline_data(MODLIST_ENTRY));
+        assert_eq!(wabbajack.inline_data_size("0b7d2c4e-inline").unwrap(), 5);
+        assert_eq!(wabbajack.read_inline_data("0b7d2c4e-inline").unwrap(), b"hello");
+
+        match wabbajack.read_inline_data("missing") {
+            Err(ParseError::InlineDataNotFound(id)) => assert_eq!(id, "missing"),
+            other => panic!("Expected InlineDataNotFound, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_wabbajack_file_without_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("broken.wabbajack");
+        write_wabbajack(&path, &[("some-blob", b"data")]);
+
+        match WabbajackFile::open(&path) {
+            Err(ParseError::MissingModlistEntry(p)) => assert_eq!(p, path),
+            other => panic!("Expected MissingModlistEntry, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_modlist_from_path_accepts_json_and_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let json_path = temp_dir.path().join("modlist");
+        std::fs::write(&json_path, MODLIST_JSON).unwrap();
+        assert!(!is_wabbajack_archive(&json_path).unwrap());
+        assert_eq!(WabbaModlist::from_path(&json_path).unwrap().name, "Zipped Modlist");
+
+        let zip_path = temp_dir.path().join("test.wabbajack");
+        write_wabbajack(&zip_path, &[(MODLIST_ENTRY, MODLIST_JSON.as_bytes())]);
+        assert_eq!(WabbaModlist::from_path(&zip_path).unwrap().name, "Zipped Modlist");
+    }
+
+    #[test]
+    fn test_patch_and_save_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.wabbajack");
+        super::write_wabbajack(
+            &path,
+            &WabbaModlist::parse(MODLIST_JSON).unwrap(),
+            vec![("0b7d2c4e-inline".to_string(), b"hello".to_vec())],
+        ).unwrap();
+
+        let mut wabbajack = WabbajackFile::open(&path).unwrap();
+        wabbajack.modlist_mut().name = "Patched Modlist".to_string();
+        wabbajack.save_as(&path).unwrap();
+
+        let reopened = WabbajackFile::open(&path).unwrap();
+        assert_eq!(reopened.modlist().name, "Patched Modlist");
+        assert_eq!(reopened.modlist().directives.len(), 1);
+        assert_eq!(reopened.read_inline_data("0b7d2c4e-inline").unwrap(), b"hello");
+    }
+}
@@ -79,7 +79,7 @@ async fn main() -> Result<()> {
     println!("   Skipped downloads: {}", result.skipped_downloads);
     println!("   Total download size: {:.1} MB", result.total_bytes_downloaded as f64 / 1_048_576.0);
 
-    let filtered_count = result.total_requests - result.successful_downloads - result.failed_downloads - result.skipped_downloads;
+    let filtered_count = result.total_requests - result.successful_downloads - result.failed_downloads - result.skipped_downloads - result.cancelled_downloads;
     if filtered_count > 0 {
         println!("   Other downloads: {}", filtered_count);
     }